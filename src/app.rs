@@ -1,21 +1,165 @@
 //! Main application state and event loop.
 
+use std::collections::{HashSet, VecDeque};
 use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
 use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use rand::seq::SliceRandom;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 
-use crate::audio::{AudioAnalyzer, AudioDecoder, AudioPlayer};
-use crate::presets::{get_preset, Preset, PRESETS};
-use crate::tracks::{DownloadProgress, Track, TrackDownloader, TrackLoader};
-use crate::ui::visualizers::Visualizer;
-use crate::ui::render::{render_ui, open_support_url};
+use crate::audio::decoder::{probe_duration, TrackMeta};
+use crate::audio::player::{VOLUME_STEP, VOLUME_STEP_LARGE};
+use crate::audio::{AnalyzerConfig, AudioAnalyzer, AudioDecoder, AudioPlayer, Recorder, SoundscapeDecoder, WavFormat};
+#[cfg(unix)]
+use crate::ipc::{self, IpcServer};
+#[cfg(feature = "mpris")]
+use crate::mpris::{MprisServer, MprisState};
+#[cfg(unix)]
+use crate::signals::SignalHandler;
+use crate::config;
+use crate::keys::{self, Action, Keymap};
+use crate::presets::{all_presets, get_preset, resolve_auto_preset, Preset, ScheduleEntry};
+use crate::resume::ResumePoint;
+use crate::stats::{self, Stats};
+use crate::status_file::{NowPlaying, StatusFile};
+use crate::tracks::{
+    DownloadEntry, DownloadProgress, DownloadState, Favorites, PlaylistSort, QueueState, Track, TrackDownloader, TrackLoader, VerifyResult,
+};
+use crate::tracks::loader::get_data_dir;
+use crate::tracks::catalog::{all_tracks, SOUNDSCAPES};
+use crate::ui::glyphs::{Glyphs, UNICODE};
+use crate::ui::visualizers::{beat_phase, Visualizer, VisualizerStyle};
+use crate::hooks::{HookContext, HookEvent, Hooks};
+use crate::platform;
+use crate::ui::render::{render_ui, open_support_url, UiLayout};
+
+/// Playback commands shared by keybindings and external controllers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(not(any(unix, feature = "mpris")), allow(dead_code))]
+pub enum Command {
+    TogglePause,
+    Play,
+    Pause,
+    Next,
+    VolumeUp,
+    VolumeDown,
+    SetVolume(f32),
+    ToggleMute,
+    /// Play the track with this slug next
+    Queue(&'static str),
+    Quit,
+}
+
+/// How often listening stats are written to disk
+const STATS_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a status message stays in the header
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+/// How long the startup offer to resume the last track stays up
+const RESUME_OFFER_DURATION: Duration = Duration::from_secs(8);
+
+/// Tracks stopped this close to either end start over instead of resuming
+const RESUME_MARGIN: Duration = Duration::from_secs(10);
+
+/// How long a toast stays over the visualization, fading out at the end
+pub const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// A gap between ticks longer than this means the system was suspended
+const SUSPEND_GAP: Duration = Duration::from_secs(5);
+
+/// Refresh interval once nothing on screen is moving
+const IDLE_TICK: Duration = Duration::from_millis(500);
+
+/// How long playback must be paused or silent before the refresh rate drops
+const IDLE_AFTER: Duration = Duration::from_secs(3);
+
+/// How long the full refresh rate lasts after a key press or mouse event
+const INPUT_GRACE: Duration = Duration::from_secs(2);
+
+/// Redraw at least this often even when nothing moved, for the clocks
+const REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Level change that's worth a redraw (levels are 0.0-1.0)
+const REDRAW_EPSILON: f32 = 0.01;
+
+/// Output RMS below this counts as silence
+const SILENCE_RMS: f32 = 0.001;
+
+/// How often to retry opening the audio device after losing it
+const AUDIO_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long the volume fades out before a timed session exits
+const FADE_OUT_DURATION: Duration = Duration::from_secs(3);
+
+/// How often the `--auto-switch` schedule is re-evaluated
+const AUTO_SWITCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time-of-day preset switching for `--preset auto --auto-switch`.
+struct AutoSwitch {
+    schedule: Vec<ScheduleEntry>,
+    /// Preset the schedule last resolved to
+    current: &'static str,
+    checked_at: Instant,
+}
+
+/// Time limit for a `--for` session.
+struct SessionLimit {
+    /// How long to play
+    limit: Duration,
+    /// Count wall-clock time, including pauses
+    include_paused: bool,
+    /// Fade out before exiting (`--for`) rather than stopping on the dot (`--duration`)
+    fade: bool,
+    /// When the session started (wall clock)
+    started: Instant,
+    /// Listening time when the session started
+    listened_at_start: Duration,
+}
+
+/// Volume ramp down to silence before exiting.
+struct FadeOut {
+    started: Instant,
+    from: f32,
+}
+
+/// Linear startup volume ramp, advanced once per tick.
+struct FadeIn {
+    /// Volume the fade ends at
+    target: f32,
+    /// Volume added per tick
+    step: f32,
+    /// Ticks left before reaching the target
+    remaining_steps: u32,
+    /// Volume the fade last set, to detect manual changes
+    last_set: f32,
+}
+
+/// What the debug overlay shows about the audio and analysis buffers.
+pub struct BufferStats {
+    /// Samples queued for playback, and the buffer's capacity
+    pub audio: (usize, usize),
+    /// Played samples waiting for the analyzer, and the buffer's capacity
+    pub analysis: (usize, usize),
+    /// Samples the analyzer missed because its buffer was full
+    pub dropped: u64,
+    /// Samples the analyzer skipped to catch up
+    pub skipped: u64,
+    pub latency: Duration,
+    pub underruns: u64,
+}
 
 /// Main application state.
 pub struct App {
@@ -33,35 +177,219 @@ pub struct App {
     preset: &'static Preset,
     /// Current track
     current_track: Option<&'static Track>,
+    /// Duration of the current track, if the file reports it
+    track_length: Option<Duration>,
     /// Playlist of tracks
     playlist: Vec<&'static Track>,
+    /// User-chosen tracks to play before the playlist continues
+    queue: VecDeque<&'static Track>,
+    /// Track to open with (`--play`)
+    start_track: Option<&'static Track>,
+    /// Last session's track and position, if it can be picked up again
+    resume: Option<(&'static Track, Duration)>,
+    /// Resume without asking (`--resume`)
+    resume_auto: bool,
+    /// When the offer to resume went up
+    resume_offered_at: Option<Instant>,
+    /// Where in the current track's file playback started
+    track_start: Duration,
     /// Current index in playlist
     playlist_index: usize,
+    /// Playlist tracks started since the playlist was created
+    playlist_played: usize,
+    /// `--play-once`: exit after the playlist instead of reshuffling
+    play_once: bool,
+    /// Favorited tracks
+    favorites: Favorites,
+    /// Play only downloaded favorites (`--favorites-only`)
+    favorites_only: bool,
+    /// Whether playlists are shuffled rather than in catalog order
+    shuffle: bool,
+    /// Playlist order by tempo (`--sort-by`), applied after any shuffle
+    sort: Option<PlaylistSort>,
+    /// Playlist entries to pass over on startup (`--skip-n`)
+    skip: usize,
+    /// Whether a `--play-once` playlist has started its last track
+    played_through: bool,
+    /// Slugs of the downloaded tracks the playlist was last built from
+    library: HashSet<&'static str>,
     /// Visualizer
     visualizer: Visualizer,
+    /// Characters the UI draws with
+    glyphs: &'static Glyphs,
     /// Whether app is running
     running: bool,
-    /// Start time
-    start_time: Instant,
+    /// UI refresh interval at the full rate
+    tick_rate: Duration,
+    /// Last key press or mouse event
+    last_input: Instant,
+    /// Since when playback has been paused or silent, if it is
+    still_since: Option<Instant>,
+    /// Set when something on screen changed since the last draw
+    dirty: bool,
+    /// When the screen was last drawn
+    drawn_at: Instant,
+    /// Meter levels and download counts as of the last draw
+    drawn_levels: Vec<f32>,
+    /// Underrun count already reported to the log
+    logged_underruns: u64,
+    /// Startup volume fade, if active
+    fade_in: Option<FadeIn>,
+    /// Auto-exit time limit, if set
+    session_limit: Option<SessionLimit>,
+    /// Schedule-driven preset switching, if enabled
+    auto_switch: Option<AutoSwitch>,
+    /// Exit fade, once the session limit is reached
+    fade_out: Option<FadeOut>,
+    /// When the event loop last ran, for suspend detection
+    last_tick: Instant,
+    /// Next attempt to reopen the audio device, if it's unavailable
+    audio_retry_at: Option<Instant>,
+    /// Transient header message and when it was shown
+    status_message: Option<(String, Instant)>,
+    /// Toast over the visualization (track changes, volume, downloads) and
+    /// when it was shown; a newer one replaces it
+    toast: Option<(String, Instant)>,
+    /// Completed downloads already toasted
+    toasted_downloads: usize,
+    /// Persistent listening statistics
+    stats: Stats,
+    /// Listening time already added to `stats`
+    stats_recorded: Duration,
+    /// When `stats` was last written to disk
+    stats_saved_at: Instant,
+    /// Listening time from tracks before the current one
+    listened_before_track: Duration,
     /// Preset selection state
     selecting_preset: bool,
     selected_preset_idx: usize,
     /// Pending preset switch (waiting for download)
     pending_preset: Option<String>,
+    /// Clickable regions from the last render
+    layout: UiLayout,
+    /// Whether the BPM estimate is shown
+    show_bpm: bool,
+    /// Whether the dominant frequency is shown
+    show_peak_frequency: bool,
+    /// Whether the download panel is open
+    show_downloads: bool,
+    /// Whether the download queue panel is open
+    show_queue: bool,
+    /// Whether the key help overlay is open
+    show_help: bool,
+    /// Whether the buffer debug overlay is open
+    show_debug: bool,
+    /// Decoder looping the soundscape under the music
+    soundscape_decoder: Option<SoundscapeDecoder>,
+    /// Chosen soundscape, if the layer is on
+    soundscape: Option<&'static Track>,
+    /// Whether the chosen soundscape is playing (it may still be downloading)
+    soundscape_playing: bool,
+    /// Whether the soundscape level changed since it was last saved
+    soundscape_level_unsaved: bool,
+    /// Background download of the chosen soundscape
+    soundscape_download: Option<thread::JoinHandle<Result<PathBuf>>>,
+    /// Volume from before a preset's own volume took over, restored when
+    /// switching to a preset without one
+    manual_volume: Option<f32>,
+    /// Key bindings, from the config file or the defaults
+    keymap: Keymap,
+    /// Whether onsets pulse the header accent
+    pulse_enabled: bool,
+    /// Text-only UI without analysis or visualizations (`--minimal`)
+    minimal: bool,
+    /// Programs run on track changes, pauses and resumes
+    hooks: Hooks,
+    /// Now-playing JSON for status bars (`--status-file`)
+    status_file: Option<StatusFile>,
+    /// Pause state the hooks last heard about
+    hooks_paused: bool,
+    /// Recording in progress, if any
+    recorder: Option<Recorder>,
+    /// `--record` path, used by the first recording
+    record_path: Option<PathBuf>,
+    /// Sample format of every recording this session
+    record_format: WavFormat,
+    /// Rows the download panel is scrolled up from the newest entry
+    downloads_scroll: usize,
+    /// Rows the queue panel is scrolled down from its first track
+    queue_scroll: usize,
+    /// Commands from external controllers, drained in the event loop
+    #[cfg_attr(not(any(unix, feature = "mpris")), allow(dead_code))]
+    command_tx: Sender<Command>,
+    command_rx: Receiver<Command>,
+    /// Control socket, if it could be bound
+    #[cfg(unix)]
+    ipc: Option<IpcServer>,
+    /// Where to bind the control socket
+    #[cfg(unix)]
+    ipc_path: std::path::PathBuf,
+    /// SIGUSR1/SIGUSR2/SIGTERM watcher
+    #[cfg(unix)]
+    signals: Option<SignalHandler>,
+    /// MPRIS service, if the session bus was reachable
+    #[cfg(feature = "mpris")]
+    mpris: Option<MprisServer>,
+}
+
+/// Raw mode, the alternate screen and mouse capture for the TUI, undone
+/// when dropped so an error or early return can't leave the shell broken.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        // From here on, dropping the guard restores the terminal
+        let guard = TerminalGuard;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        if platform::mouse_capture_supported() {
+            execute!(stdout, EnableMouseCapture)?;
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Leave raw mode and the alternate screen and show the cursor. Safe to
+/// call when the TUI never started, and more than once.
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, crossterm::cursor::Show);
+}
+
+/// Quit at once on Ctrl+C, for when the graceful shutdown is stuck behind
+/// a blocked thread. Only the terminal is restored: recordings and the
+/// status file are left as they are.
+pub fn emergency_exit() -> ! {
+    restore_terminal();
+    std::process::exit(0);
 }
 
 impl App {
     /// Create a new application.
-    pub fn new(preset_name: &str) -> Result<Self> {
-        let preset = get_preset(preset_name).unwrap_or(&PRESETS[0]);
+    pub fn new(preset_name: &str, analyzer_config: AnalyzerConfig, backend: Option<&str>) -> Result<Self> {
+        let preset = get_preset(preset_name).unwrap_or(&all_presets()[0]);
         let loader = TrackLoader::new();
-        let downloader = TrackDownloader::new();
-        let player = AudioPlayer::new()?;
+        let downloader = TrackDownloader::new()?;
+        let player = AudioPlayer::new(backend)?;
+        let soundscape_decoder = player
+            .take_soundscape_producer()
+            .map(|producer| SoundscapeDecoder::new(producer, player.soundscape_flush()));
         let decoder = AudioDecoder::new();
-        let analyzer = AudioAnalyzer::new();
+        let mut analyzer = AudioAnalyzer::new(analyzer_config);
+        if let Some(consumer) = player.take_analysis_consumer() {
+            analyzer.set_consumer(consumer);
+        }
+        let (command_tx, command_rx) = mpsc::channel();
 
         // Find initial preset index
-        let selected_preset_idx = PRESETS
+        let selected_preset_idx = all_presets()
             .iter()
             .position(|p| p.name == preset.name)
             .unwrap_or(0);
@@ -74,14 +402,84 @@ impl App {
             downloader,
             preset,
             current_track: None,
+            track_length: None,
             playlist: Vec::new(),
+            queue: VecDeque::new(),
+            start_track: None,
+            resume: None,
+            resume_auto: false,
+            resume_offered_at: None,
+            track_start: Duration::ZERO,
             playlist_index: 0,
-            visualizer: Visualizer::new(),
+            playlist_played: 0,
+            play_once: false,
+            favorites: Favorites::load(&Favorites::default_path()),
+            favorites_only: false,
+            shuffle: true,
+            sort: None,
+            skip: 0,
+            played_through: false,
+            library: HashSet::new(),
+            visualizer: Visualizer::new(&UNICODE),
+            glyphs: &UNICODE,
             running: true,
-            start_time: Instant::now(),
+            tick_rate: Duration::from_millis(1000 / 15),
+            last_input: Instant::now(),
+            still_since: None,
+            dirty: true,
+            drawn_at: Instant::now(),
+            drawn_levels: Vec::new(),
+            logged_underruns: 0,
+            fade_in: None,
+            session_limit: None,
+            auto_switch: None,
+            fade_out: None,
+            last_tick: Instant::now(),
+            audio_retry_at: None,
+            status_message: None,
+            toast: None,
+            toasted_downloads: 0,
+            stats: Stats::load(&Stats::default_path()),
+            stats_recorded: Duration::ZERO,
+            stats_saved_at: Instant::now(),
+            listened_before_track: Duration::ZERO,
             selecting_preset: false,
             selected_preset_idx,
             pending_preset: None,
+            layout: UiLayout::default(),
+            show_bpm: false,
+            show_peak_frequency: false,
+            show_downloads: false,
+            show_queue: false,
+            show_help: false,
+            show_debug: false,
+            soundscape_decoder,
+            soundscape: None,
+            soundscape_playing: false,
+            soundscape_level_unsaved: false,
+            soundscape_download: None,
+            manual_volume: None,
+            keymap: Keymap::default(),
+            hooks: Hooks::new(None, None, None),
+            status_file: None,
+            hooks_paused: false,
+            pulse_enabled: true,
+            minimal: false,
+            recorder: None,
+            record_path: None,
+            record_format: WavFormat::default(),
+            downloads_scroll: 0,
+            queue_scroll: 0,
+            command_tx,
+            command_rx,
+            #[cfg(unix)]
+            ipc: None,
+            #[cfg(unix)]
+            ipc_path: ipc::socket_path(),
+            #[cfg(unix)]
+            signals: None,
+            #[cfg(feature = "mpris")]
+            mpris: None,
         })
     }
 
@@ -92,7 +490,7 @@ impl App {
 
     /// Get all presets.
     pub fn all_presets(&self) -> &'static [Preset] {
-        PRESETS
+        all_presets()
     }
 
     /// Get selected preset index.
@@ -110,14 +508,300 @@ impl App {
         self.pending_preset.as_deref()
     }
 
+    /// Get the status message, if it is still fresh.
+    pub fn status_message(&self) -> Option<&str> {
+        self.status_message
+            .as_ref()
+            .filter(|(_, shown)| shown.elapsed() < STATUS_MESSAGE_DURATION)
+            .map(|(msg, _)| msg.as_str())
+    }
+
+    /// The offer to continue last session's track, while it's up.
+    pub fn resume_prompt(&self) -> Option<String> {
+        let (track, position) = self.resume?;
+        // Switching away from the preset withdraws the offer
+        if !self.preset.pool_list().contains(&track.pool) {
+            return None;
+        }
+        self.resume_offered_at
+            .filter(|offered| offered.elapsed() < RESUME_OFFER_DURATION)
+            .map(|_| format!("press c to continue {} at {}", track.name, format_track_time(position)))
+    }
+
+    /// Show a transient message in the header.
+    fn flash(&mut self, message: impl Into<String>) {
+        self.status_message = Some((message.into(), Instant::now()));
+        self.dirty = true;
+    }
+
+    /// The current toast and how long it has been showing.
+    pub fn toast(&self) -> Option<(&str, Duration)> {
+        self.toast
+            .as_ref()
+            .map(|(msg, shown)| (msg.as_str(), shown.elapsed()))
+            .filter(|(_, age)| *age < TOAST_DURATION)
+    }
+
+    /// Show a toast over the visualization, replacing any current one.
+    fn show_toast(&mut self, message: impl Into<String>) {
+        self.toast = Some((message.into(), Instant::now()));
+        self.dirty = true;
+    }
+
+    /// Toast the newest download that finished since the last tick.
+    fn check_download_toast(&mut self) {
+        let history = self.downloader.history();
+        let completed: Vec<_> = history
+            .iter()
+            .filter(|entry| entry.state == DownloadState::Completed)
+            .collect();
+        if completed.len() > self.toasted_downloads {
+            if let Some(entry) = completed.last() {
+                self.show_toast(format!("Downloaded {}", entry.track_name));
+            }
+            self.toasted_downloads = completed.len();
+        }
+    }
+
+    /// Start recording to `path` right away; later `R` presses record next to
+    /// it in the same format.
+    pub fn set_record_path(&mut self, path: PathBuf, format: WavFormat) -> Result<()> {
+        self.record_path = Some(path.clone());
+        self.record_format = format;
+        self.start_recording(path)
+    }
+
+    /// Length recorded so far, while recording.
+    pub fn recording_elapsed(&self) -> Option<Duration> {
+        self.recorder.as_ref().map(Recorder::elapsed)
+    }
+
+    fn start_recording(&mut self, path: PathBuf) -> Result<()> {
+        let (recorder, sink) = Recorder::start(&path, self.record_format)?;
+        self.player.set_record_sink(Some(sink));
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Finish the current recording, returning where it was saved.
+    fn stop_recording(&mut self) -> Option<PathBuf> {
+        let recorder = self.recorder.take()?;
+        // Dropping the only sender lets the writer finish the file
+        self.player.set_record_sink(None);
+        let path = recorder.path().to_path_buf();
+        if let Err(e) = recorder.finish() {
+            log::error!("Recording to {:?} failed: {:#}", path, e);
+        }
+        Some(path)
+    }
+
+    /// `R`: stop the current recording, or start a new timestamped one.
+    fn toggle_recording(&mut self) {
+        if let Some(path) = self.stop_recording() {
+            self.flash(format!("Saved {}", path.display()));
+            return;
+        }
+
+        let dir = self
+            .record_path
+            .as_deref()
+            .and_then(Path::parent)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| get_data_dir().join("recordings"));
+        let name = chrono::Local::now().format("fomu-%Y%m%d-%H%M%S.wav").to_string();
+        let result = std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {:?}", dir))
+            .and_then(|_| self.start_recording(dir.join(name)));
+        if let Err(e) = result {
+            log::error!("Could not start recording: {:#}", e);
+            self.flash(format!("Recording failed: {:#}", e));
+        }
+    }
+
+    /// Stop recording, without touching playback, if the writer hit an error.
+    fn check_recording(&mut self) {
+        let Some(e) = self.recorder.as_mut().and_then(Recorder::take_error) else {
+            return;
+        };
+        self.player.set_record_sink(None);
+        self.recorder = None;
+        log::error!("Recording stopped: {:#}", e);
+        self.flash(format!("Recording stopped: {:#}", e));
+    }
+
+    /// Whether the download panel is open.
+    pub fn is_showing_downloads(&self) -> bool {
+        self.show_downloads
+    }
+
+    /// Downloads started this session, oldest first.
+    pub fn download_history(&self) -> Vec<DownloadEntry> {
+        self.downloader.history()
+    }
+
+    /// Rows the download panel is scrolled up from the newest entry.
+    pub fn downloads_scroll(&self) -> usize {
+        self.downloads_scroll
+    }
+
+    /// Whether the download queue panel is open.
+    pub fn is_showing_queue(&self) -> bool {
+        self.show_queue
+    }
+
+    /// Whether the buffer debug overlay is open.
+    pub fn is_showing_debug(&self) -> bool {
+        self.show_debug
+    }
+
+    /// Buffer fill levels and sample counters for the debug overlay.
+    pub fn buffer_stats(&self) -> BufferStats {
+        BufferStats {
+            audio: self.player.buffer_fill(),
+            analysis: self.player.analysis_fill(),
+            dropped: self.player.analysis_dropped(),
+            skipped: self.analyzer.skipped_samples(),
+            latency: self.player.output_latency(),
+            underruns: self.player.underruns(),
+        }
+    }
+
+    /// Check if the key help overlay is open.
+    pub fn is_showing_help(&self) -> bool {
+        self.show_help
+    }
+
+    /// Key bindings in effect.
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Keep now-playing JSON at `path` while running.
+    pub fn set_status_file(&mut self, path: std::path::PathBuf) {
+        self.status_file = Some(StatusFile::new(path));
+    }
+
+    pub fn set_hooks(&mut self, hooks: Hooks) {
+        self.hooks = hooks;
+    }
+
+    /// Use `keymap` for key handling; its first conflict is flashed on start.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        if let Some(conflict) = keymap.conflicts().first() {
+            self.flash(conflict.clone());
+        }
+        self.keymap = keymap;
+    }
+
+    /// Rows the queue panel is scrolled down from its first track.
+    pub fn queue_scroll(&self) -> usize {
+        self.queue_scroll
+    }
+
     /// Get download progress.
     pub fn download_progress(&self) -> DownloadProgress {
         self.downloader.get_progress()
     }
 
-    /// Check if preset has available tracks.
-    pub fn preset_has_tracks(&self, preset: &Preset) -> bool {
-        !self.loader.get_available_tracks_from_pools(preset.pools).is_empty()
+    /// Bind the control socket at `path` instead of the default location.
+    #[cfg(unix)]
+    pub fn set_ipc_socket(&mut self, path: std::path::PathBuf) {
+        self.ipc_path = path;
+    }
+
+    /// Set how many tracks background downloads fetch at once.
+    pub fn set_max_downloads(&mut self, max: usize) {
+        self.downloader.set_max_downloads(max);
+    }
+
+    /// `(available, total)` track counts for a preset.
+    pub fn preset_track_stats(&self, preset: &Preset) -> (usize, usize) {
+        let pools = preset.pool_list();
+        let available = self.loader.get_available_tracks_from_pools(&pools).len();
+        let missing = self.loader.get_missing_tracks_from_pools(&pools).len();
+        (available, available + missing)
+    }
+
+    /// Bytes left to download for a preset, if every missing track's size is known.
+    pub fn preset_missing_bytes(&self, preset: &Preset) -> Option<u64> {
+        let missing = self.loader.get_missing_tracks_from_pools(&preset.pool_list());
+        self.downloader.sizes().total(missing)
+    }
+
+    /// Bytes left in the download queue, if every queued track's size is known.
+    pub fn queue_missing_bytes(&self) -> Option<u64> {
+        let progress = self.downloader.get_progress();
+        let left = progress
+            .queue
+            .iter()
+            .filter(|item| item.state != QueueState::Done)
+            .map(|item| item.track);
+        self.downloader.sizes().total(left)
+    }
+
+    /// Start with `track` before the preset's rotation, downloading it first
+    /// if needed.
+    pub fn set_start_track(&mut self, track: &'static Track) {
+        self.start_track = Some(track);
+    }
+
+    /// Pick up last session's track where it stopped: right away with
+    /// `auto`, otherwise offered on `c` for the first few seconds. Only
+    /// when it was the same preset and the file is still cached unchanged;
+    /// anything else starts normally. Call after `set_start_track`.
+    pub fn set_resume(&mut self, auto: bool) {
+        if self.start_track.is_some() {
+            return;
+        }
+        self.resume = self.resume_candidate();
+        self.resume_auto = auto;
+    }
+
+    fn resume_candidate(&self) -> Option<(&'static Track, Duration)> {
+        let point = ResumePoint::load(&ResumePoint::default_path())?;
+        if point.preset != self.preset.name {
+            return None;
+        }
+        let track = TrackLoader::get_track_by_slug(&point.slug)?;
+        let path = self.loader.get_track_path(track);
+        if std::fs::metadata(&path).ok()?.len() != point.size {
+            return None;
+        }
+        let position = Duration::try_from_secs_f64(point.position).ok()?;
+        let length = probe_duration(&path)?;
+        (position >= RESUME_MARGIN && position + RESUME_MARGIN < length).then_some((track, position))
+    }
+
+    /// Play the offered track from where last session stopped.
+    fn accept_resume(&mut self) {
+        if let Some((track, position)) = self.resume.take() {
+            self.play_track_at(track, position);
+        }
+    }
+
+    /// Remember the current track and position for the next launch.
+    fn save_resume_point(&self) {
+        let Some(track) = self.current_track else {
+            return;
+        };
+        let Ok(file) = std::fs::metadata(self.loader.get_track_path(track)) else {
+            return;
+        };
+        let point = ResumePoint {
+            preset: self.preset.name.to_string(),
+            slug: track.slug.to_string(),
+            position: self.track_elapsed().as_secs_f64(),
+            size: file.len(),
+        };
+        if let Err(e) = point.save(&ResumePoint::default_path()) {
+            log::warn!("Failed to save resume point: {:#}", e);
+        }
+    }
+
+    /// Track queued to play next, if any.
+    pub fn up_next(&self) -> Option<&'static Track> {
+        self.queue.front().copied()
     }
 
     /// Get current track.
@@ -125,6 +809,32 @@ impl App {
         self.current_track
     }
 
+    /// Title and artist tags of the playing file, once the decoder has read them.
+    pub fn current_track_meta(&self) -> Option<TrackMeta> {
+        self.decoder.meta()
+    }
+
+    /// Set the spectrum level shown as an empty bar, in dBFS.
+    pub fn set_spectrum_floor(&mut self, db: f32) {
+        self.analyzer.set_db_floor(db);
+    }
+
+    /// Draw with `glyphs` rather than the Unicode set.
+    pub fn set_glyphs(&mut self, glyphs: &'static Glyphs) {
+        self.glyphs = glyphs;
+        self.visualizer.set_glyphs(glyphs);
+    }
+
+    /// Characters the UI draws with.
+    pub fn glyphs(&self) -> &'static Glyphs {
+        self.glyphs
+    }
+
+    /// Show or hide the falling peak markers above the bars.
+    pub fn set_show_peaks(&mut self, show: bool) {
+        self.visualizer.set_show_peaks(show);
+    }
+
     /// Get visualizer.
     pub fn visualizer(&self) -> &Visualizer {
         &self.visualizer
@@ -135,49 +845,637 @@ impl App {
         self.analyzer.rms()
     }
 
+    /// Get held peak level.
+    pub fn peak(&self) -> f32 {
+        self.analyzer.peak()
+    }
+
     /// Get frequency bands.
     pub fn bands(&self) -> &[f32] {
         self.analyzer.bands()
     }
 
+    /// Get left and right RMS levels.
+    pub fn rms_stereo(&self) -> (f32, f32) {
+        self.analyzer.rms_stereo()
+    }
+
+    /// Get the L/R correlation (-1 to 1).
+    pub fn correlation(&self) -> f32 {
+        self.analyzer.correlation()
+    }
+
+    /// Play every available track of the preset once, then exit.
+    pub fn set_play_once(&mut self, enabled: bool) {
+        self.play_once = enabled;
+    }
+
+    /// Build playlists from downloaded favorites instead of the preset's pools.
+    pub fn set_favorites_only(&mut self, enabled: bool) {
+        self.favorites_only = enabled;
+    }
+
+    /// Whether the playing track is a favorite.
+    pub fn is_current_track_favorited(&self) -> bool {
+        self.current_track.is_some_and(|track| self.favorites.contains(track))
+    }
+
+    /// Favorite the playing track, or unfavorite it if it already is.
+    fn toggle_favorite(&mut self) {
+        let Some(track) = self.current_track else {
+            return;
+        };
+        match self.favorites.toggle(track) {
+            Ok(true) => self.flash(format!("Added {} to favorites", track.name)),
+            Ok(false) => self.flash(format!("Removed {} from favorites", track.name)),
+            Err(e) => {
+                log::warn!("Could not save favorites: {:#}", e);
+                self.flash("Could not save favorites");
+            }
+        }
+    }
+
+    /// Play tracks in catalog order instead of shuffling them.
+    pub fn set_shuffle(&mut self, enabled: bool) {
+        self.shuffle = enabled;
+    }
+
+    /// Order playlists by tempo. Ties and tracks without one stay shuffled
+    /// unless shuffling is off too.
+    pub fn set_sort(&mut self, sort: Option<PlaylistSort>) {
+        self.sort = sort;
+    }
+
+    /// Start `count` tracks into the first playlist, wrapping around.
+    pub fn set_skip(&mut self, count: usize) {
+        self.skip = count;
+    }
+
+    /// `(started, total)` tracks of a `--play-once` playlist.
+    pub fn playlist_progress(&self) -> Option<(usize, usize)> {
+        self.play_once.then_some((self.playlist_played, self.playlist.len()))
+    }
+
+    /// Bar height multiplier on each detected beat (1.0 disables it).
+    pub fn set_beat_boost(&mut self, boost: f32) {
+        self.visualizer.set_beat_boost(boost);
+    }
+
+    /// Enable or disable the header pulse on onsets.
+    pub fn set_pulse(&mut self, enabled: bool) {
+        self.pulse_enabled = enabled;
+    }
+
+    /// Show text only: no visualizer, no colors, and no audio analysis.
+    pub fn set_minimal(&mut self, minimal: bool) {
+        self.minimal = minimal;
+    }
+
+    /// Whether the UI is text only.
+    pub fn is_minimal(&self) -> bool {
+        self.minimal
+    }
+
+    /// Current onset pulse (0.0 - 1.0), or 0 when pulsing is disabled.
+    pub fn pulse(&self) -> f32 {
+        if self.pulse_enabled {
+            self.analyzer.onset_strength()
+        } else {
+            0.0
+        }
+    }
+
+    /// Get the center frequency of each band (Hz).
+    pub fn band_center_freqs(&self) -> Vec<f32> {
+        self.analyzer.band_center_freqs()
+    }
+
+    /// Get BPM estimate.
+    pub fn bpm(&self) -> Option<f32> {
+        self.analyzer.bpm()
+    }
+
+    /// Dominant frequency (Hz), if there's sound.
+    pub fn peak_frequency(&self) -> Option<f32> {
+        let hz = self.analyzer.peak_frequency();
+        (hz > 0.0).then_some(hz)
+    }
+
+    /// Whether the dominant frequency readout is on.
+    pub fn is_showing_peak_frequency(&self) -> bool {
+        self.show_peak_frequency
+    }
+
+    /// Check if the BPM estimate is shown.
+    pub fn is_showing_bpm(&self) -> bool {
+        self.show_bpm
+    }
+
     /// Get volume.
     pub fn volume(&self) -> f32 {
         self.player.volume()
     }
 
+    /// Position in the current track, counting from the start of the file
+    /// even when playback started partway in.
+    pub fn track_elapsed(&self) -> Duration {
+        self.track_start + self.player.position()
+    }
+
+    /// Whether the output is muted; `volume` is what unmuting returns to.
+    pub fn is_muted(&self) -> bool {
+        self.player.is_muted()
+    }
+
+    /// Volume in dB relative to full scale.
+    pub fn volume_db(&self) -> f32 {
+        self.player.volume_db()
+    }
+
     /// Set volume.
     pub fn set_volume(&self, vol: f32) {
         self.player.set_volume(vol);
     }
 
+    /// Loop `track` under the music, or turn the layer off with `None`.
+    ///
+    /// A soundscape that isn't downloaded yet is fetched in the background
+    /// and starts once it's there.
+    pub fn set_soundscape(&mut self, track: Option<&'static Track>) {
+        self.soundscape = track;
+        self.soundscape_playing = false;
+        let Some(decoder) = &self.soundscape_decoder else {
+            return;
+        };
+        let Some(track) = track else {
+            decoder.play(None);
+            return;
+        };
+
+        if self.loader.track_exists(track) {
+            log::info!("Soundscape: {}", track.name);
+            decoder.play(Some(self.loader.get_track_path(track)));
+            self.soundscape_playing = true;
+            return;
+        }
+        // Keep the old layer off rather than let it play under the new name
+        decoder.play(None);
+        if self.soundscape_download.is_none() {
+            log::info!("Downloading soundscape {}", track.name);
+            self.soundscape_download = Some(thread::spawn(move || {
+                TrackDownloader::new()?.download_track(track)
+            }));
+        }
+    }
+
+    /// Soundscape level relative to the music (0.0-1.0).
+    pub fn set_soundscape_level(&mut self, level: f32) {
+        self.player.set_soundscape_level(level);
+    }
+
+    /// Chosen soundscape, its level, and whether it's playing yet.
+    pub fn soundscape(&self) -> Option<(&'static Track, f32, bool)> {
+        self.soundscape
+            .map(|track| (track, self.player.soundscape_level(), self.soundscape_playing))
+    }
+
+    /// Step through off, then each soundscape, then off again.
+    fn cycle_soundscape(&mut self) {
+        let next = match self.soundscape {
+            None => SOUNDSCAPES.first(),
+            Some(current) => SOUNDSCAPES
+                .iter()
+                .position(|t| t.slug == current.slug)
+                .and_then(|i| SOUNDSCAPES.get(i + 1)),
+        };
+        self.set_soundscape(next);
+        match next {
+            Some(track) if self.soundscape_playing => self.flash(format!("Soundscape: {}", track.name)),
+            Some(track) => self.flash(format!("Soundscape: {} (downloading)", track.name)),
+            None => self.flash("Soundscape off"),
+        }
+        let name = next.map_or("off".to_string(), |t| t.name.to_lowercase());
+        self.save_setting("soundscape", toml::Value::String(name));
+    }
+
+    /// Nudge the soundscape level by `step`.
+    fn adjust_soundscape_level(&mut self, step: f32) {
+        if self.soundscape.is_none() {
+            self.flash("No soundscape playing (g picks one)");
+            return;
+        }
+        let level = (self.player.soundscape_level() + step).clamp(0.0, 1.0);
+        self.player.set_soundscape_level(level);
+        self.flash(format!("Soundscape level {}%", (level * 100.0).round() as u32));
+        // Saved with the stats rather than on every keypress
+        self.soundscape_level_unsaved = true;
+    }
+
+    /// Write the soundscape level to the config file if it changed.
+    fn save_soundscape_level(&mut self) {
+        if !std::mem::take(&mut self.soundscape_level_unsaved) {
+            return;
+        }
+        // Round so the config file doesn't collect float noise
+        let rounded = (f64::from(self.player.soundscape_level()) * 100.0).round() / 100.0;
+        self.save_setting("soundscape_volume", toml::Value::Float(rounded));
+    }
+
+    /// Remember a setting for next time in the config file.
+    fn save_setting(&mut self, key: &str, value: toml::Value) {
+        let path = config::default_path();
+        if let Err(e) = config::set_value(&path, key, value) {
+            log::warn!("Could not save {} to {}: {:#}", key, path.display(), e);
+        }
+    }
+
+    /// Run the hook for `event` with the current track.
+    fn fire_hook(&self, event: HookEvent) {
+        let Some(track) = self.current_track else {
+            return;
+        };
+        self.hooks.fire(
+            event,
+            &HookContext {
+                track: track.name,
+                slug: track.slug,
+                preset: self.preset.name,
+                position: self.track_elapsed(),
+            },
+        );
+    }
+
+    /// Run the pause or resume hook when playback was paused or resumed
+    /// since the last tick, whatever did it (a key, IPC, MPRIS, suspend).
+    fn check_pause_hooks(&mut self) {
+        let paused = self.player.is_paused();
+        if paused != self.hooks_paused {
+            self.hooks_paused = paused;
+            self.fire_hook(if paused { HookEvent::Pause } else { HookEvent::Resume });
+        }
+    }
+
+    /// Start the chosen soundscape once its download has finished.
+    fn check_soundscape(&mut self) {
+        let Some(handle) = self.soundscape_download.take_if(|h| h.is_finished()) else {
+            return;
+        };
+        let result = handle.join().unwrap_or_else(|_| Err(anyhow::anyhow!("Download thread panicked")));
+        match (result, self.soundscape) {
+            (Err(e), Some(track)) => {
+                log::warn!("Soundscape download failed: {:#}", e);
+                self.flash(format!("Could not download {}", track.name));
+            }
+            // A later pick may not be the one that finished; this starts or re-fetches it
+            (_, Some(track)) if !self.soundscape_playing => self.set_soundscape(Some(track)),
+            _ => {}
+        }
+    }
+
+    /// Apply the current preset's own volume and visualizer, if it has them.
+    ///
+    /// Called on every preset switch. The volume in use before the first
+    /// preset with its own volume is kept, and restored on switching to a
+    /// preset without one. Volume changes in between last until the next
+    /// switch.
+    pub fn apply_preset_settings(&mut self) {
+        let (volume, style) = preset_overrides(self.preset, self.volume(), &mut self.manual_volume);
+        if let Some(volume) = volume {
+            self.set_volume(volume);
+        }
+        if let Some(style) = style {
+            self.visualizer.set_style(style);
+        }
+    }
+
+    /// Set UI refresh rate.
+    pub fn set_fps(&mut self, fps: u32) {
+        self.tick_rate = Duration::from_millis(1000 / fps.max(1) as u64);
+    }
+
+    /// How long to wait for input before the next frame.
+    fn frame_interval(&self) -> Duration {
+        let still_for = self.still_since.map_or(Duration::ZERO, |since| since.elapsed());
+        idle_interval(self.tick_rate, still_for, self.last_input.elapsed())
+    }
+
+    /// Levels and counts the screen shows that change without an event:
+    /// bands, peaks, meters, volume and download progress.
+    fn drawn_state(&self) -> Vec<f32> {
+        let progress = self.downloader.get_progress();
+        let (left, right) = self.analyzer.rms_stereo();
+        let counts = [
+            progress.done(),
+            progress.failed(),
+            progress.queue.len(),
+            progress.active_workers,
+            usize::from(progress.is_active()),
+            usize::from(self.soundscape_playing),
+        ];
+        self.analyzer
+            .bands()
+            .iter()
+            .copied()
+            .chain(self.visualizer.levels())
+            .chain([left, right, self.analyzer.peak(), self.pulse(), self.analyzer.correlation()])
+            .chain([self.player.volume(), self.player.soundscape_level()])
+            .chain(counts.map(|count| count as f32))
+            .collect()
+    }
+
+    /// Whether the frame would differ from the last one drawn. Panels with
+    /// spinners always redraw, and so does everything once a second.
+    fn needs_redraw(&self, levels: &[f32]) -> bool {
+        self.dirty
+            || self.show_downloads
+            || self.show_queue
+            || self.show_debug
+            || self.toast().is_some()
+            || self.drawn_at.elapsed() >= REDRAW_INTERVAL
+            || levels.len() != self.drawn_levels.len()
+            || levels
+                .iter()
+                .zip(&self.drawn_levels)
+                .any(|(now, drawn)| (now - drawn).abs() > REDRAW_EPSILON)
+    }
+
+    /// Track whether playback is paused or silent, for `frame_interval`.
+    fn update_idle(&mut self) {
+        if self.is_playing() && self.analyzer.rms() > SILENCE_RMS {
+            self.still_since = None;
+        } else {
+            self.still_since.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// Fade volume in from silence to the current volume over `secs` seconds.
+    ///
+    /// Call after `set_volume` and `set_fps`, since the step size depends on both.
+    pub fn set_fade_in(&mut self, secs: f32) {
+        let target = self.player.volume();
+        let steps = (secs / self.tick_rate.as_secs_f32()).round() as u32;
+        if steps == 0 || target <= 0.0 {
+            return;
+        }
+
+        self.player.set_volume(0.0);
+        self.fade_in = Some(FadeIn {
+            target,
+            step: target / steps as f32,
+            remaining_steps: steps,
+            last_set: 0.0,
+        });
+    }
+
+    /// Exit after `limit` of playback, fading out first when `fade` is set.
+    ///
+    /// Paused time doesn't count unless `include_paused` is set.
+    pub fn set_session_limit(&mut self, limit: Duration, include_paused: bool, fade: bool) {
+        self.session_limit = Some(SessionLimit {
+            limit,
+            include_paused,
+            fade,
+            started: Instant::now(),
+            listened_at_start: self.total_listened(),
+        });
+    }
+
+    /// Follow a time-of-day schedule, switching presets as its boundaries pass.
+    pub fn set_auto_switch(&mut self, schedule: Vec<ScheduleEntry>) {
+        let current = resolve_auto_preset(chrono::Local::now().time(), &schedule);
+        self.auto_switch = Some(AutoSwitch {
+            schedule,
+            current,
+            checked_at: Instant::now(),
+        });
+    }
+
+    /// Whether the session limit fades out (`--for`) rather than stopping exactly (`--duration`).
+    pub fn session_fades(&self) -> bool {
+        self.session_limit.as_ref().is_some_and(|limit| limit.fade)
+    }
+
+    /// Time left before a `--for` or `--duration` session ends, if one is set.
+    pub fn session_remaining(&self) -> Option<Duration> {
+        let limit = self.session_limit.as_ref()?;
+        let used = if limit.include_paused {
+            limit.started.elapsed()
+        } else {
+            self.total_listened().saturating_sub(limit.listened_at_start)
+        };
+        Some(limit.limit.saturating_sub(used))
+    }
+
+    /// Switch presets when the auto schedule crosses a boundary.
+    ///
+    /// The current track plays out; the new preset's playlist takes over
+    /// from the next track.
+    fn check_auto_switch(&mut self) {
+        let Some(auto) = &mut self.auto_switch else {
+            return;
+        };
+        if auto.checked_at.elapsed() < AUTO_SWITCH_INTERVAL {
+            return;
+        }
+        auto.checked_at = Instant::now();
+
+        let name = resolve_auto_preset(chrono::Local::now().time(), &auto.schedule);
+        if name == auto.current {
+            return;
+        }
+        auto.current = name;
+
+        let Some(preset) = get_preset(name) else {
+            return;
+        };
+        if preset.name == self.preset.name {
+            return;
+        }
+
+        let pools = preset.pool_list();
+        if self.loader.get_available_tracks_from_pools(&pools).is_empty() {
+            log::info!("Auto preset {} has no tracks yet, downloading", preset.name);
+            self.pending_preset = Some(preset.name.to_string());
+            self.downloader.start_background_download(pools);
+            return;
+        }
+
+        log::info!("Auto switching preset: {} -> {}", self.preset.name, preset.name);
+        self.preset = preset;
+        self.apply_preset_settings();
+        self.selected_preset_idx = all_presets()
+            .iter()
+            .position(|p| p.name == preset.name)
+            .unwrap_or(0);
+        self.create_playlist(None);
+        self.flash(format!("Schedule: {} after this track", preset.name));
+        self.downloader.start_background_download(pools);
+    }
+
+    /// Start the exit fade once the session limit is reached, and quit when it's done.
+    fn update_session_limit(&mut self) {
+        if self.fade_out.is_none() && self.session_remaining() == Some(Duration::ZERO) {
+            if !self.session_fades() {
+                log::info!("Session duration reached, stopping");
+                self.running = false;
+                return;
+            }
+            log::info!("Session time limit reached, fading out");
+            self.fade_in = None;
+            self.fade_out = Some(FadeOut {
+                started: Instant::now(),
+                from: self.player.volume(),
+            });
+        }
+
+        if let Some(fade) = &self.fade_out {
+            let t = fade.started.elapsed().as_secs_f32() / FADE_OUT_DURATION.as_secs_f32();
+            self.player.set_volume(fade.from * (1.0 - t).max(0.0));
+            if t >= 1.0 {
+                self.running = false;
+            }
+        }
+    }
+
+    /// Detect suspend/resume and dead audio streams, and recover from them.
+    ///
+    /// `gap` is the time since the previous tick.
+    fn check_audio_health(&mut self, gap: Duration) {
+        if gap > SUSPEND_GAP {
+            log::info!("Event loop stalled for {:.1}s, assuming suspend", gap.as_secs_f32());
+            self.player.set_paused(true);
+            self.recover_audio("Resumed after suspend — paused");
+
+            // The download's HTTP connection is likely dead too
+            let pools = self
+                .pending_preset
+                .as_deref()
+                .and_then(get_preset)
+                .unwrap_or(self.preset)
+                .pool_list();
+            self.downloader.stop_background_download();
+            self.downloader.start_background_download(pools);
+        } else if self.player.take_stream_error() {
+            self.player.set_paused(true);
+            self.recover_audio("Audio device changed — paused");
+        } else if self.audio_retry_at.is_some_and(|at| Instant::now() >= at) {
+            self.recover_audio("Audio device available again — paused");
+        }
+    }
+
+    /// Rebuild the output stream, retrying later if no device is available.
+    fn recover_audio(&mut self, message: &str) {
+        match self.player.rebuild_stream() {
+            Ok(()) => {
+                self.audio_retry_at = None;
+                // A track picked while the device was gone never started
+                let never_started = !self.decoder.is_running() && !self.player.is_finished();
+                if let Some(track) = self.current_track.filter(|_| never_started) {
+                    self.play_track_at(track, self.track_start);
+                    self.player.set_paused(true);
+                }
+                self.flash(message);
+            }
+            Err(e) => {
+                log::warn!("Could not rebuild audio stream: {:#}", e);
+                self.audio_retry_at = Some(Instant::now() + AUDIO_RETRY_INTERVAL);
+                self.flash("Audio unavailable — paused");
+            }
+        }
+    }
+
+    /// Advance the startup fade by one tick.
+    fn update_fade_in(&mut self) {
+        let Some(fade) = &mut self.fade_in else {
+            return;
+        };
+
+        // A manual volume change takes over from the fade
+        if (self.player.volume() - fade.last_set).abs() > f32::EPSILON {
+            self.fade_in = None;
+            return;
+        }
+
+        fade.remaining_steps -= 1;
+        let volume = if fade.remaining_steps == 0 {
+            fade.target
+        } else {
+            (fade.last_set + fade.step).min(fade.target)
+        };
+        self.player.set_volume(volume);
+        fade.last_set = self.player.volume();
+
+        if fade.remaining_steps == 0 {
+            self.fade_in = None;
+        }
+    }
+
     /// Check if playing.
     pub fn is_playing(&self) -> bool {
         self.player.is_playing()
     }
 
-    /// Get elapsed time formatted.
-    pub fn elapsed_time(&self) -> String {
-        let elapsed = self.start_time.elapsed();
-        let secs = elapsed.as_secs();
+    /// Get playback position in the current track formatted, with duration if known.
+    pub fn track_position(&self) -> String {
+        let position = format_track_time(self.track_elapsed());
+        match self.track_length {
+            Some(length) => format!("{} / {}", position, format_track_time(length)),
+            None => position,
+        }
+    }
+
+    /// Get total listening time formatted.
+    ///
+    /// Derived from samples actually played, so paused time doesn't count.
+    pub fn listening_time(&self) -> String {
+        let secs = self.total_listened().as_secs();
         let hours = secs / 3600;
         let mins = (secs % 3600) / 60;
         let secs = secs % 60;
         format!("{:02}:{:02}:{:02}", hours, mins, secs)
     }
 
+    /// Total time spent playing audio this session.
+    fn total_listened(&self) -> Duration {
+        self.listened_before_track + self.player.position()
+    }
+
+    /// Credit listening since the last call to the current preset and track.
+    fn record_listening(&mut self) {
+        let total = self.total_listened();
+        let delta = total.saturating_sub(self.stats_recorded);
+        self.stats_recorded = total;
+
+        if let Some(track) = self.current_track {
+            if !delta.is_zero() {
+                self.stats.add(self.preset.name, track.slug, delta.as_secs_f64());
+            }
+        }
+    }
+
+    /// Write stats to disk.
+    fn save_stats(&mut self) {
+        self.record_listening();
+        if let Err(e) = self.stats.save(&Stats::default_path()) {
+            log::warn!("Failed to save stats: {:#}", e);
+        }
+        self.stats_saved_at = Instant::now();
+    }
+
     /// Ensure at least one track is available.
     fn ensure_tracks(&mut self) -> Result<bool> {
-        let available = self.loader.get_available_tracks_from_pools(self.preset.pools);
+        let available = self.loader.get_available_tracks_from_pools(&self.preset.pool_list());
         if !available.is_empty() {
             return Ok(true);
         }
 
         // Download one track
         println!("First run: downloading a track (only happens once)...");
-        match self.downloader.download_one_track(self.preset.pools) {
+        match self.downloader.download_one_track(&self.preset.pool_list()) {
             Ok(Some(_)) => Ok(true),
             Ok(None) => Ok(false),
             Err(e) => {
+                log::error!("First track download failed: {:#}", e);
                 eprintln!("Download error: {}", e);
                 Ok(false)
             }
@@ -185,102 +1483,499 @@ impl App {
     }
 
     /// Create playlist from current preset.
-    fn create_playlist(&mut self) {
-        self.playlist = self.loader.create_playlist(self.preset.pools, true);
+    ///
+    /// The new order starts away from the current track and `upcoming`, the
+    /// track about to play when reshuffling mid-sequence.
+    fn create_playlist(&mut self, upcoming: Option<&'static Track>) {
+        let recent: Vec<&'static Track> = self.current_track.into_iter().chain(upcoming).collect();
+        let source = self.playlist_source();
+        self.library = source.iter().map(|t| t.slug).collect();
+        self.playlist = if self.plays_each_once() {
+            // Weights would repeat tracks, so play each available one once
+            let mut tracks = source;
+            if self.shuffle {
+                tracks.shuffle(&mut rand::thread_rng());
+            }
+            tracks
+        } else {
+            self.loader.create_weighted_playlist(self.preset.pool_weights, &recent)
+        };
+        if let Some(sort) = self.sort {
+            sort.sort(&mut self.playlist);
+        }
         self.playlist_index = 0;
+        self.playlist_played = 0;
+        self.played_through = false;
+    }
+
+    /// Downloaded tracks a playlist is built from: the favorites with
+    /// `--favorites-only`, otherwise the preset's pools.
+    fn playlist_source(&self) -> Vec<&'static Track> {
+        if self.favorites_only {
+            self.loader.create_playlist_from_favorites()
+        } else {
+            self.loader.get_available_tracks_from_pools(&self.preset.pool_list())
+        }
+    }
+
+    /// Whether the playlist holds each available track once, rather than a
+    /// weighted shuffle that can repeat them.
+    fn plays_each_once(&self) -> bool {
+        self.favorites_only || self.play_once || !self.shuffle || self.sort.is_some()
+    }
+
+    /// Pass over the first `--skip-n` tracks of the playlist without
+    /// playing them. They count as played for `--play-once`.
+    fn skip_tracks(&mut self) {
+        if self.skip == 0 || self.playlist.is_empty() {
+            return;
+        }
+        let skip = self.skip % self.playlist.len();
+        log::info!("Skipping {} of {} tracks", skip, self.playlist.len());
+        self.playlist_index = skip;
+        self.playlist_played = skip;
     }
 
     /// Load next track.
     fn load_next_track(&mut self) -> bool {
+        if self.played_through && self.queue.is_empty() {
+            log::info!("Played every track once, exiting");
+            self.running = false;
+            return true;
+        }
+
         if self.playlist.is_empty() {
-            self.create_playlist();
+            self.create_playlist(None);
         }
 
         if self.playlist.is_empty() {
             return false;
         }
 
-        // Get next track
-        let track = self.playlist[self.playlist_index];
-        self.playlist_index = (self.playlist_index + 1) % self.playlist.len();
+        // Queued tracks play first without moving through the playlist
+        let track = if let Some(track) = self.queue.pop_front() {
+            track
+        } else {
+            let track = self.playlist[self.playlist_index];
+            self.playlist_index = (self.playlist_index + 1) % self.playlist.len();
+            self.playlist_played += 1;
+
+            // Reshuffle when we've played through all tracks, keeping the
+            // outgoing and incoming tracks off the front of the new order
+            if self.playlist_index == 0 {
+                if self.play_once {
+                    self.played_through = true;
+                } else {
+                    self.create_playlist(Some(track));
+                }
+            }
+            track
+        };
 
-        // Reshuffle when we've played through all tracks
-        if self.playlist_index == 0 {
-            self.create_playlist();
-        }
+        self.play_track(track)
+    }
+
+    /// Start decoding `track` from the beginning.
+    fn play_track(&mut self, track: &'static Track) -> bool {
+        self.play_track_at(track, Duration::ZERO)
+    }
 
+    /// Start decoding `track` from `position` into it.
+    fn play_track_at(&mut self, track: &'static Track, position: Duration) -> bool {
+        // Credit the outgoing track before switching
+        self.record_listening();
         self.current_track = Some(track);
+        self.dirty = true;
+        log::info!("Now playing: {} ({})", track.name, self.preset.name);
 
-        // Start decoding with analysis buffer
         let path = self.loader.get_track_path(track);
-        let producer = self.player.init_buffer();
+        self.track_length = probe_duration(&path);
+        // Bank the previous track's playback before the position resets
+        self.listened_before_track += self.player.position();
+        let backend = match self.player.init_buffer() {
+            Ok(backend) => backend,
+            Err(e) => {
+                // No device to play on: hold the track until
+                // `recover_audio` gets one and starts it again
+                log::warn!("Could not start audio output: {:#}", e);
+                self.decoder.stop();
+                self.player.set_paused(true);
+                self.audio_retry_at.get_or_insert(Instant::now() + AUDIO_RETRY_INTERVAL);
+                self.track_start = position;
+                self.flash("Audio unavailable — paused");
+                return false;
+            }
+        };
         let finished = self.player.finished_flag();
-        let analysis_producer = self.analyzer.create_buffer();
+        self.analyzer.reset();
 
-        if let Err(e) = self.decoder.start(&path, producer, finished, Some(analysis_producer)) {
-            eprintln!("Failed to start decoder: {}", e);
-            return false;
+        self.track_start = position;
+        match self.decoder.start_at(&path, backend, finished, position) {
+            Ok(token) => self.player.set_decode_token(token),
+            Err(e) => {
+                log::error!("Failed to start decoder for {:?}: {:#}", path, e);
+                return false;
+            }
         }
+        // A new track always starts playing
+        self.hooks_paused = false;
+        self.fire_hook(HookEvent::TrackChange);
+        self.show_toast(format!("Now playing: {}", track.name));
 
         true
     }
 
+    /// Restart the current track if playback has been starved mid-track
+    /// for too long, e.g. because the decoder died without finishing.
+    fn check_stall(&mut self) {
+        if !self.player.is_stalled() || self.player.is_finished() || !self.player.is_playing() {
+            return;
+        }
+        let Some(track) = self.current_track else {
+            return;
+        };
+        self.decoder.stop();
+
+        // A damaged file stalls again on every restart, so fetch it afresh
+        if let VerifyResult::Corrupt(reason) = self.loader.verify(track) {
+            log::error!("{} is corrupt ({}), downloading it again", track.name, reason);
+            if let Err(e) = self.loader.discard(track) {
+                log::error!("Could not delete {}: {:#}", track.name, e);
+            }
+            self.flash(format!("{} was damaged, downloading it again", track.name));
+            let earlier = self.playlist[..self.playlist_index]
+                .iter()
+                .filter(|t| t.slug == track.slug)
+                .count();
+            self.playlist.retain(|t| t.slug != track.slug);
+            self.playlist_index -= earlier;
+            if self.playlist_index >= self.playlist.len() {
+                self.playlist_index = 0;
+            }
+            self.downloader.start_background_download(self.preset.pool_list());
+            self.load_next_track();
+            return;
+        }
+
+        log::warn!("Playback stalled on {}, restarting decoder", track.name);
+        self.play_track(track);
+    }
+
     /// Handle key events.
     fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        let key = keys::normalize(code, modifiers);
+        if code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL) {
+            emergency_exit();
+        }
+        // While the resume offer is up, c takes it instead of its usual action
+        if code == KeyCode::Char('c') && key.modifiers.is_empty() && self.resume_prompt().is_some() {
+            self.accept_resume();
+            return;
+        }
+
         if self.selecting_preset {
-            match code {
-                KeyCode::Esc | KeyCode::Char('q') => {
-                    self.selecting_preset = false;
-                    // Reset to current preset
-                    self.selected_preset_idx = PRESETS
-                        .iter()
-                        .position(|p| p.name == self.preset.name)
-                        .unwrap_or(0);
-                }
-                KeyCode::Enter => {
-                    self.confirm_preset_selection();
-                }
-                KeyCode::Char('j') | KeyCode::Left => {
-                    if self.selected_preset_idx > 0 {
-                        self.selected_preset_idx -= 1;
-                    } else {
-                        self.selected_preset_idx = PRESETS.len() - 1;
-                    }
+            match picker_key(&self.keymap, &key, self.selected_preset_idx, all_presets().len()) {
+                PickerKey::Select(idx) => self.selected_preset_idx = idx,
+                PickerKey::Confirm => self.confirm_preset_selection(),
+                PickerKey::Close => self.close_preset_selection(),
+                PickerKey::Prefetch => self.prefetch_selected_preset(),
+                PickerKey::Ignore => {}
+            }
+            return;
+        }
+
+        match code {
+            KeyCode::Esc if self.show_help => {
+                self.show_help = false;
+                return;
+            }
+            KeyCode::Esc => {
+                self.apply_command(Command::Quit);
+                return;
+            }
+            KeyCode::Up if self.show_downloads => {
+                let max = self.downloader.history().len().saturating_sub(1);
+                self.downloads_scroll = (self.downloads_scroll + 1).min(max);
+                return;
+            }
+            KeyCode::Down if self.show_downloads => {
+                self.downloads_scroll = self.downloads_scroll.saturating_sub(1);
+                return;
+            }
+            KeyCode::Up if self.show_queue => {
+                self.queue_scroll = self.queue_scroll.saturating_sub(1);
+                return;
+            }
+            KeyCode::Down if self.show_queue => {
+                let max = self.downloader.get_progress().queue.len().saturating_sub(1);
+                self.queue_scroll = (self.queue_scroll + 1).min(max);
+                return;
+            }
+            _ => {}
+        }
+
+        let Some(action) = self.keymap.main_action(&key) else {
+            return;
+        };
+        match action {
+            Action::Quit => self.apply_command(Command::Quit),
+            Action::Pause => self.apply_command(Command::TogglePause),
+            Action::Next => self.apply_command(Command::Next),
+            Action::VolumeUp => self.apply_command(Command::VolumeUp),
+            Action::VolumeDown => self.apply_command(Command::VolumeDown),
+            Action::VolumeUpLarge => self.step_volume(VOLUME_STEP_LARGE),
+            Action::VolumeDownLarge => self.step_volume(-VOLUME_STEP_LARGE),
+            Action::Mute => self.apply_command(Command::ToggleMute),
+            Action::PresetMenu => {
+                self.selecting_preset = true;
+                self.show_help = false;
+            }
+            Action::Downloads => {
+                self.show_downloads = !self.show_downloads;
+                self.show_queue = false;
+                self.downloads_scroll = 0;
+            }
+            Action::DownloadQueue => {
+                self.show_queue = !self.show_queue;
+                self.show_downloads = false;
+                self.queue_scroll = 0;
+            }
+            Action::Retry if self.show_queue => match self.downloader.retry_failed() {
+                0 => self.flash("No failed downloads"),
+                n => self.flash(format!("Retrying {} downloads", n)),
+            },
+            Action::CancelDownload if self.show_queue => self.cancel_download(),
+            Action::ClearQueue if !self.queue.is_empty() => {
+                self.queue.clear();
+                self.flash("Queue cleared");
+            }
+            Action::Support => open_support_url(),
+            Action::Rescan => self.rescan_library(),
+            Action::Bpm | Action::PeakFrequency | Action::Visualizer | Action::Radial if self.minimal => {
+                self.flash("No analysis or visualizer in --minimal mode");
+            }
+            Action::Bpm => self.show_bpm = !self.show_bpm,
+            Action::PeakFrequency => self.show_peak_frequency = !self.show_peak_frequency,
+            Action::Favorite => self.toggle_favorite(),
+            Action::Visualizer => self.visualizer.cycle_style(),
+            Action::Radial => self.visualizer.toggle_radial(),
+            Action::Record => self.toggle_recording(),
+            Action::Help => self.show_help = !self.show_help,
+            Action::Debug => self.show_debug = !self.show_debug,
+            Action::Soundscape => self.cycle_soundscape(),
+            Action::SoundscapeUp => self.adjust_soundscape_level(0.05),
+            Action::SoundscapeDown => self.adjust_soundscape_level(-0.05),
+            Action::Retry | Action::CancelDownload | Action::ClearQueue | Action::MenuUp | Action::MenuDown | Action::Prefetch => {}
+        }
+    }
+
+    /// Leave the preset picker without switching.
+    fn close_preset_selection(&mut self) {
+        self.selecting_preset = false;
+        // Reset to current preset
+        self.selected_preset_idx = all_presets()
+            .iter()
+            .position(|p| p.name == self.preset.name)
+            .unwrap_or(0);
+    }
+
+    /// Apply a playback command.
+    fn apply_command(&mut self, command: Command) {
+        self.dirty = true;
+        match command {
+            Command::TogglePause => {
+                self.player.toggle_pause();
+            }
+            Command::Play => self.player.set_paused(false),
+            Command::Pause => self.player.set_paused(true),
+            Command::Next => self.skip_track(),
+            Command::VolumeUp => self.step_volume(VOLUME_STEP),
+            Command::VolumeDown => self.step_volume(-VOLUME_STEP),
+            Command::SetVolume(vol) => {
+                // Asking for a level means wanting to hear it
+                if vol > 0.0 {
+                    self.player.set_muted(false);
                 }
-                KeyCode::Char('k') | KeyCode::Right | KeyCode::Char('p') => {
-                    self.selected_preset_idx = (self.selected_preset_idx + 1) % PRESETS.len();
+                self.player.set_volume(vol);
+                self.show_toast(format!("Vol {:.0}%", self.player.volume() * 100.0));
+            }
+            Command::ToggleMute => {
+                let muted = !self.player.is_muted();
+                self.player.set_muted(muted);
+                if muted {
+                    self.show_toast("Muted");
+                } else {
+                    self.show_toast(format!("Vol {:.0}%", self.player.volume() * 100.0));
                 }
-                _ => {}
             }
+            Command::Queue(slug) => self.queue_track(slug),
+            Command::Quit => self.running = false,
+        }
+    }
+
+    /// Skip the topmost download in progress in the queue panel's view,
+    /// else the first one, sending it to the end of the queue.
+    fn cancel_download(&mut self) {
+        let progress = self.downloader.get_progress();
+        let downloading = progress
+            .queue
+            .iter()
+            .skip(self.queue_scroll)
+            .chain(&progress.queue)
+            .find(|item| matches!(item.state, QueueState::Downloading(_)));
+        match downloading {
+            Some(item) if self.downloader.cancel_track(item.track.slug) => {
+                self.flash(format!("Skipped {}, queued again last", item.track.name));
+            }
+            _ => self.flash("Nothing downloading"),
+        }
+    }
+
+    /// Raise the volume by `delta`, or lower it when negative.
+    fn step_volume(&mut self, delta: f32) {
+        // Turning it up while muted unmutes first
+        if delta > 0.0 {
+            self.player.set_muted(false);
+        }
+        let volume = if delta >= 0.0 {
+            self.player.volume_up(delta)
         } else {
-            match code {
-                KeyCode::Char('q') | KeyCode::Esc => {
-                    self.running = false;
-                }
-                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.running = false;
-                }
-                KeyCode::Char(' ') => {
-                    self.player.toggle_pause();
-                }
-                KeyCode::Char('p') => {
-                    self.selecting_preset = true;
-                }
-                KeyCode::Char('n') => {
-                    self.skip_track();
+            self.player.volume_down(-delta)
+        };
+        self.show_toast(format!("Vol {:.0}%", volume * 100.0));
+    }
+
+    /// Add a downloaded track to the up-next queue.
+    fn queue_track(&mut self, slug: &str) {
+        let Some(track) = TrackLoader::get_track_by_slug(slug) else {
+            return;
+        };
+        if !self.loader.track_exists(track) {
+            self.flash(format!("{} isn't downloaded yet", track.name));
+            return;
+        }
+        log::info!("Queued {}", track.name);
+        self.queue.push_back(track);
+        self.flash(format!("Queued {}", track.name));
+    }
+
+    /// Apply commands queued by external controllers.
+    fn drain_commands(&mut self) {
+        while let Ok(command) = self.command_rx.try_recv() {
+            self.apply_command(command);
+        }
+    }
+
+    /// Push current playback state to the control socket.
+    #[cfg(unix)]
+    fn update_ipc(&self) {
+        if let Some(ipc) = &self.ipc {
+            ipc.update(ipc::Status {
+                preset: self.preset.name.to_string(),
+                track: self.current_track.map(|t| t.name.to_string()),
+                position: self.track_elapsed().as_secs_f64(),
+                duration: self.track_length.map(|d| d.as_secs_f64()),
+                volume: self.volume(),
+                muted: self.is_muted(),
+                paused: !self.is_playing(),
+            });
+        }
+    }
+
+    /// Rewrite the status file if what's playing changed.
+    fn update_status_file(&mut self) {
+        let status = NowPlaying {
+            track: self.current_track.map(|t| t.name.to_string()),
+            artist: self.current_track.and_then(|t| t.artist()).map(str::to_string),
+            preset: self.preset.name.to_string(),
+            paused: !self.is_playing(),
+            muted: self.is_muted(),
+            position: self.track_elapsed().as_secs(),
+            volume: (self.volume() * 100.0).round() / 100.0,
+        };
+        if let Some(file) = &mut self.status_file {
+            file.update(&status);
+        }
+    }
+
+    /// Push current playback state to the MPRIS service.
+    #[cfg(feature = "mpris")]
+    fn update_mpris(&self) {
+        if let Some(mpris) = &self.mpris {
+            mpris.update(MprisState {
+                playing: self.is_playing(),
+                title: self.current_track.map(|t| t.name.to_string()),
+                slug: self.current_track.map(|t| t.slug.to_string()),
+                artist: self.current_track.and_then(|t| t.artist()).map(str::to_string),
+                length: self.track_length,
+                // MPRIS has no mute property; players report zero instead
+                volume: if self.is_muted() { 0.0 } else { self.volume() },
+            });
+        }
+    }
+
+    /// Handle mouse events.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => {
+                self.apply_command(Command::VolumeUp);
+            }
+            MouseEventKind::ScrollDown => {
+                self.apply_command(Command::VolumeDown);
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let (column, row) = (mouse.column, mouse.row);
+                if self.selecting_preset {
+                    if let Some(idx) = self.layout.preset_at(column, row) {
+                        self.selected_preset_idx = idx;
+                        self.confirm_preset_selection();
+                        return;
+                    }
                 }
-                KeyCode::Char('s') => {
+                if self.layout.is_track_info(column, row) {
+                    self.apply_command(Command::TogglePause);
+                } else if self.layout.is_support_link(column, row) {
                     open_support_url();
                 }
-                KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Char(']') | KeyCode::Up => {
-                    self.player.volume_up();
-                }
-                KeyCode::Char('-') | KeyCode::Char('_') | KeyCode::Char('[') | KeyCode::Down => {
-                    self.player.volume_down();
-                }
-                _ => {}
+            }
+            _ => {}
+        }
+    }
+
+    /// Rescan the tracks directory and rebuild the playlist.
+    ///
+    /// The current track keeps playing and the playlist keeps its position:
+    /// tracks already played stay ahead of it, so `--play-once` progress
+    /// carries over, and new files join the part still to come.
+    fn rescan_library(&mut self) {
+        let before = std::mem::take(&mut self.library);
+        let played = self.playlist[..self.playlist_index].to_vec();
+        let (played_count, played_through) = (self.playlist_played, self.played_through);
+
+        self.loader = TrackLoader::new();
+        self.create_playlist(None);
+        let fresh = std::mem::take(&mut self.playlist);
+        self.playlist = keep_played(&played, fresh, self.plays_each_once());
+        self.playlist_index = played.len();
+        self.playlist_played = played_count;
+        self.played_through = played_through;
+        // Everything left to play was deleted: start the next cycle
+        if self.playlist_index >= self.playlist.len() {
+            if self.play_once {
+                self.playlist_index = 0;
+                self.played_through = true;
+            } else {
+                self.create_playlist(None);
             }
         }
+
+        let added = self.library.difference(&before).count();
+        log::info!("Library rescanned: {} tracks, {} new", self.library.len(), added);
+        self.flash(format!(
+            "Library rescanned: {} new track{}",
+            added,
+            if added == 1 { "" } else { "s" }
+        ));
     }
 
     /// Skip to next track.
@@ -289,33 +1984,49 @@ impl App {
         self.load_next_track();
     }
 
+    /// Download the highlighted preset's tracks without switching to it.
+    fn prefetch_selected_preset(&mut self) {
+        let preset = &all_presets()[self.selected_preset_idx];
+        let pools = preset.pool_list();
+        if self.loader.get_missing_tracks_from_pools(&pools).is_empty() {
+            self.flash(format!("{} is fully downloaded", preset.name));
+            return;
+        }
+        log::info!("Prefetching tracks for preset {}", preset.name);
+        self.downloader.start_background_download(pools);
+        self.flash(format!("Downloading {} tracks", preset.name));
+    }
+
     /// Confirm preset selection.
     fn confirm_preset_selection(&mut self) {
         self.selecting_preset = false;
-        let new_preset = &PRESETS[self.selected_preset_idx];
+        let new_preset = &all_presets()[self.selected_preset_idx];
 
         if new_preset.name == self.preset.name {
             return; // No change
         }
 
         // Check if tracks are available
-        let available = self.loader.get_available_tracks_from_pools(new_preset.pools);
+        let available = self.loader.get_available_tracks_from_pools(&new_preset.pool_list());
         if available.is_empty() {
             // Start background download
+            log::info!("Preset {} has no tracks yet, downloading", new_preset.name);
             self.pending_preset = Some(new_preset.name.to_string());
-            self.downloader.start_background_download(new_preset.pools.to_vec());
+            self.downloader.start_background_download(new_preset.pool_list());
             return;
         }
 
         // Switch preset
+        log::info!("Switching preset: {} -> {}", self.preset.name, new_preset.name);
         self.preset = new_preset;
+        self.apply_preset_settings();
         self.pending_preset = None;
-        self.create_playlist();
+        self.create_playlist(None);
         self.decoder.stop();
         self.load_next_track();
 
         // Start background download for remaining tracks
-        self.downloader.start_background_download(self.preset.pools.to_vec());
+        self.downloader.start_background_download(self.preset.pool_list());
     }
 
     /// Check for pending preset switch.
@@ -326,16 +2037,18 @@ impl App {
 
         let pending_name = self.pending_preset.as_ref().unwrap().clone();
         if let Some(pending_preset) = get_preset(&pending_name) {
-            let available = self.loader.get_available_tracks_from_pools(pending_preset.pools);
+            let available = self.loader.get_available_tracks_from_pools(&pending_preset.pool_list());
             if !available.is_empty() {
                 // Switch to pending preset
+                log::info!("Pending preset {} ready, switching", pending_preset.name);
                 self.preset = pending_preset;
+                self.apply_preset_settings();
                 self.pending_preset = None;
-                self.selected_preset_idx = PRESETS
+                self.selected_preset_idx = all_presets()
                     .iter()
                     .position(|p| p.name == self.preset.name)
                     .unwrap_or(0);
-                self.create_playlist();
+                self.create_playlist(None);
                 self.decoder.stop();
                 self.load_next_track();
             }
@@ -344,83 +2057,565 @@ impl App {
 
     /// Run the application.
     pub fn run(&mut self) -> Result<()> {
+        if let Some(track) = self.start_track.take() {
+            if !self.loader.track_exists(track) {
+                println!("Downloading {}...", track.name);
+            }
+            match self.downloader.download_track(track) {
+                Ok(_) => self.queue.push_front(track),
+                Err(e) => {
+                    log::error!("Could not download start track {}: {:#}", track.name, e);
+                    eprintln!("Could not download {}: {:#}", track.name, e);
+                }
+            }
+        }
+
         // Ensure tracks are available
         if !self.ensure_tracks()? {
+            log::error!("No tracks available for preset {}", self.preset.name);
             eprintln!("No tracks available. Please check your internet connection.");
             return Ok(());
         }
 
         // Start background download
-        self.downloader.start_background_download(self.preset.pools.to_vec());
+        self.downloader.start_background_download(self.preset.pool_list());
+        let missing = all_tracks().filter(|t| !self.loader.track_exists(t)).collect();
+        self.downloader.probe_sizes(missing);
 
         // Create playlist and load first track
-        self.create_playlist();
-        if !self.load_next_track() {
+        self.create_playlist(None);
+        self.skip_tracks();
+        if self.favorites_only && self.playlist.is_empty() {
+            eprintln!("No downloaded favorites yet. Press F while a track plays to add it.");
+            return Ok(());
+        }
+        let loaded = match self.resume.filter(|_| self.resume_auto) {
+            Some((track, position)) => {
+                self.resume = None;
+                self.play_track_at(track, position)
+            }
+            None => self.load_next_track(),
+        };
+        if self.resume.is_some() {
+            self.resume_offered_at = Some(Instant::now());
+        }
+        if !loaded {
+            log::error!("Failed to load the first track");
             eprintln!("Failed to load track.");
             return Ok(());
         }
 
+        // External control socket (best effort: another instance may own it)
+        // and signals, so SIGTERM restores the terminal like `q` does
+        #[cfg(unix)]
+        {
+            self.ipc = match IpcServer::start(self.command_tx.clone(), self.ipc_path.clone()) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    log::warn!("Control socket disabled: {:#}", e);
+                    None
+                }
+            };
+            self.signals = SignalHandler::start(self.command_tx.clone()).ok();
+        }
+
+        // Media keys / playerctl (best effort: no session bus is fine)
+        #[cfg(feature = "mpris")]
+        {
+            self.mpris = MprisServer::start(self.command_tx.clone()).ok();
+        }
+
+        // Raw mode turns the Ctrl+C key into a key event (see handle_key);
+        // this catches a SIGINT sent from outside
+        if let Err(e) = ctrlc::set_handler(|| emergency_exit()) {
+            log::warn!("Could not install the Ctrl+C handler: {}", e);
+        }
+
         // Setup terminal with cleanup guard
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
+        let guard = TerminalGuard::enter()?;
+        let backend = CrosstermBackend::new(io::stdout());
         let mut terminal = Terminal::new(backend)?;
 
         // Run the main loop, ensuring cleanup happens
         let result = self.run_loop(&mut terminal);
 
+        // Bars read a missing status file as fomu not running
+        self.status_file = None;
+
+        // Stop accepting external commands
+        #[cfg(unix)]
+        {
+            self.ipc = None;
+            self.signals = None;
+        }
+
         // Cleanup audio (with timeouts to avoid blocking)
+        let recording = self.stop_recording();
         self.decoder.stop();
         self.player.stop();
         self.downloader.stop_background_download();
 
         // Cleanup terminal (always do this, even if loop errored)
-        let _ = disable_raw_mode();
-        let _ = execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        );
-        let _ = terminal.show_cursor();
+        drop(terminal);
+        drop(guard);
+
+        self.save_stats();
+        self.save_resume_point();
+        self.save_soundscape_level();
+        if let Some(summary) = stats::session_summary(&self.stats) {
+            println!("{}", summary);
+        }
+        if let Some(path) = recording {
+            println!("Recording saved to {}", path.display());
+        }
 
         result
     }
 
     /// Main event loop - separated for easier cleanup handling.
     fn run_loop(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
-        let tick_rate = Duration::from_millis(1000 / 15); // 15 FPS
+        // Startup (first download, probing) doesn't count as a suspend
+        self.last_tick = Instant::now();
 
         while self.running {
             // Handle events
-            if event::poll(tick_rate)? {
-                if let Event::Key(key) = event::read()? {
-                    self.handle_key(key.code, key.modifiers);
+            if event::poll(self.frame_interval())? {
+                match event::read()? {
+                    Event::Key(key) => {
+                        self.last_input = Instant::now();
+                        self.dirty = true;
+                        self.handle_key(key.code, key.modifiers);
+                    }
+                    Event::Mouse(mouse) => {
+                        self.last_input = Instant::now();
+                        self.dirty = true;
+                        self.handle_mouse(mouse);
+                    }
+                    // Re-layout now rather than drawing one frame at the old
+                    // size; the draw below follows straight away
+                    Event::Resize(..) => {
+                        terminal.autoresize()?;
+                        self.dirty = true;
+                    }
+                    _ => {}
                 }
             }
+            self.drain_commands();
 
-            // Update audio analysis
-            self.analyzer.update();
+            let now = Instant::now();
+            let dt = now - self.last_tick;
+            self.last_tick = now;
+            self.check_audio_health(dt);
+            self.check_stall();
+            self.check_recording();
+            self.check_soundscape();
+            self.check_pause_hooks();
+            self.check_download_toast();
 
-            // Update visualizer
-            self.visualizer.update(self.analyzer.rms(), self.analyzer.bands());
+            self.update_fade_in();
+            self.update_session_limit();
 
-            // Check if track ended
-            if self.player.is_finished() && !self.decoder.is_running() {
-                if !self.load_next_track() {
-                    // Restart playlist
-                    self.create_playlist();
-                    self.load_next_track();
+            if !self.minimal {
+                // Update audio analysis
+                self.analyzer.update(self.player.output_latency());
+                if self.analyzer.beat() {
+                    log::trace!("Onset, strength {:.2}", self.analyzer.onset_strength());
                 }
+
+                // Update visualizer, pulsing on the beat while there's a tempo
+                let beat = self
+                    .analyzer
+                    .bpm()
+                    .filter(|_| self.is_playing())
+                    .map(|bpm| (bpm, beat_phase(self.track_elapsed(), bpm)));
+                self.visualizer.update(self.analyzer.rms(), self.analyzer.bands(), dt, beat);
+                self.visualizer.update_meter(self.analyzer.rms_stereo(), dt);
+            }
+            self.update_idle();
+
+            // Check if track ended
+            if self.player.is_finished() && !self.decoder.is_running() && !self.load_next_track() {
+                // Restart playlist
+                self.create_playlist(None);
+                self.load_next_track();
             }
 
             // Check for pending preset switch
+            self.check_auto_switch();
             self.check_pending_preset();
 
-            // Render
-            terminal.draw(|f| render_ui(f, self))?;
+            // Persist stats periodically so a crash loses at most a minute
+            self.record_listening();
+            if self.stats_saved_at.elapsed() >= STATS_SAVE_INTERVAL {
+                self.save_stats();
+                self.save_resume_point();
+                self.save_soundscape_level();
+            }
+
+            // Report underruns counted by the audio callback
+            let underruns = self.player.underruns();
+            if underruns > self.logged_underruns {
+                log::warn!(
+                    "Audio underrun: {} new ({} total)",
+                    underruns - self.logged_underruns,
+                    underruns
+                );
+                self.logged_underruns = underruns;
+            }
+
+            #[cfg(unix)]
+            self.update_ipc();
+            #[cfg(feature = "mpris")]
+            self.update_mpris();
+            self.update_status_file();
+
+            // Render only when something on screen changed
+            let levels = self.drawn_state();
+            if self.needs_redraw(&levels) {
+                let mut layout = UiLayout::default();
+                terminal.draw(|f| layout = render_ui(f, self))?;
+                self.layout = layout;
+                self.dirty = false;
+                self.drawn_at = Instant::now();
+                self.drawn_levels = levels;
+            }
         }
 
         Ok(())
     }
 }
+
+/// What a key does in the preset picker.
+#[derive(Debug, PartialEq)]
+enum PickerKey {
+    /// Highlight the preset at this index
+    Select(usize),
+    Confirm,
+    Close,
+    Prefetch,
+    Ignore,
+}
+
+/// Map a key pressed in the preset picker, with preset `selected` of
+/// `count` highlighted. Up and down wrap around the list.
+fn picker_key(keymap: &Keymap, key: &KeyEvent, selected: usize, count: usize) -> PickerKey {
+    // Fixed keys first, so a remap can't lock the picker
+    match key.code {
+        KeyCode::Esc => return PickerKey::Close,
+        KeyCode::Enter => return PickerKey::Confirm,
+        KeyCode::Home => return PickerKey::Select(0),
+        KeyCode::End => return PickerKey::Select(count - 1),
+        // 1-9 jump straight to a preset; Enter still confirms
+        KeyCode::Char(c @ '1'..='9') if key.modifiers.is_empty() => {
+            let idx = c as usize - '1' as usize;
+            return if idx < count { PickerKey::Select(idx) } else { PickerKey::Ignore };
+        }
+        _ => {}
+    }
+    match keymap.picker_action(key) {
+        Some(Action::Quit) => PickerKey::Close,
+        Some(Action::PresetMenu) => PickerKey::Confirm,
+        Some(Action::Prefetch) => PickerKey::Prefetch,
+        Some(Action::MenuUp) => PickerKey::Select(selected.checked_sub(1).unwrap_or(count - 1)),
+        Some(Action::MenuDown) => PickerKey::Select((selected + 1) % count),
+        _ => PickerKey::Ignore,
+    }
+}
+
+/// Volume and visualizer to switch to for `preset`, or `None` to keep
+/// the one in use. `manual` holds the volume from before a preset's own
+/// volume took over; `current` is the volume playing now.
+fn preset_overrides(preset: &Preset, current: f32, manual: &mut Option<f32>) -> (Option<f32>, Option<VisualizerStyle>) {
+    let volume = match preset.volume {
+        Some(volume) => {
+            manual.get_or_insert(current);
+            Some(volume)
+        }
+        None => manual.take(),
+    };
+    (volume, preset.visualizer.and_then(VisualizerStyle::from_name))
+}
+
+/// Refresh interval after playback has been paused or silent for
+/// `still_for`, with the last input `since_input` ago: the full rate, or
+/// `IDLE_TICK` once nothing has moved for a while.
+fn idle_interval(tick_rate: Duration, still_for: Duration, since_input: Duration) -> Duration {
+    if still_for >= IDLE_AFTER && since_input >= INPUT_GRACE {
+        tick_rate.max(IDLE_TICK)
+    } else {
+        tick_rate
+    }
+}
+
+/// A rescanned playlist: the `played` part of the old one, then `fresh`.
+/// When the playlist holds each track once, `fresh` loses the tracks
+/// already played so they don't come around again this cycle.
+fn keep_played(played: &[&'static Track], mut fresh: Vec<&'static Track>, each_once: bool) -> Vec<&'static Track> {
+    if each_once {
+        fresh.retain(|t| !played.iter().any(|p| p.slug == t.slug));
+    }
+    played.iter().copied().chain(fresh).collect()
+}
+
+/// Format a track time as `m:ss`.
+fn format_track_time(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracks::catalog::TRACK_CATALOG;
+
+    fn tracks(range: std::ops::Range<usize>) -> Vec<&'static Track> {
+        TRACK_CATALOG[range].iter().collect()
+    }
+
+    fn slugs(tracks: &[&'static Track]) -> Vec<&'static str> {
+        tracks.iter().map(|t| t.slug).collect()
+    }
+
+    #[test]
+    fn rescan_keeps_the_played_part_in_place() {
+        let old = tracks(0..5);
+        let played = &old[..2];
+        // A reshuffled library with one new track
+        let mut fresh = tracks(0..6);
+        fresh.reverse();
+
+        let playlist = keep_played(played, fresh, true);
+        assert_eq!(slugs(&playlist[..2]), slugs(played));
+        // The rest holds every track not yet played, once, including the new one
+        let rest: HashSet<&str> = playlist[2..].iter().map(|t| t.slug).collect();
+        let expected: HashSet<&str> = TRACK_CATALOG[2..6].iter().map(|t| t.slug).collect();
+        assert_eq!(rest, expected);
+        assert_eq!(playlist.len(), 6);
+    }
+
+    #[test]
+    fn rescan_of_a_weighted_playlist_keeps_repeats() {
+        let played = tracks(0..1);
+        let fresh = tracks(0..3);
+        let playlist = keep_played(&played, fresh.clone(), false);
+        assert_eq!(playlist[0].slug, played[0].slug);
+        assert_eq!(slugs(&playlist[1..]), slugs(&fresh));
+    }
+
+    #[test]
+    fn rescan_at_the_start_of_a_cycle_is_the_fresh_playlist() {
+        let fresh = tracks(0..4);
+        assert_eq!(slugs(&keep_played(&[], fresh.clone(), true)), slugs(&fresh));
+    }
+
+    fn pick(keymap: &Keymap, spec: &str, selected: usize, count: usize) -> PickerKey {
+        picker_key(keymap, &keys::parse_key(spec).unwrap(), selected, count)
+    }
+
+    /// Highlighted preset after pressing `specs` in a picker of `count`
+    /// presets, starting from the first.
+    fn walk(keymap: &Keymap, specs: &[&str], count: usize) -> usize {
+        specs.iter().fold(0, |selected, spec| match pick(keymap, spec, selected, count) {
+            PickerKey::Select(idx) => idx,
+            other => panic!("{} did {:?} instead of moving", spec, other),
+        })
+    }
+
+    #[test]
+    fn picker_j_moves_down_and_k_moves_up() {
+        let keymap = Keymap::default();
+        assert_eq!(walk(&keymap, &["j"], 6), 1);
+        assert_eq!(walk(&keymap, &["j", "j", "j", "k"], 6), 2);
+        assert_eq!(walk(&keymap, &["down", "down", "up"], 6), 1);
+    }
+
+    #[test]
+    fn picker_wraps_at_both_ends() {
+        let keymap = Keymap::default();
+        assert_eq!(walk(&keymap, &["k"], 6), 5);
+        assert_eq!(walk(&keymap, &["k", "j"], 6), 0);
+        assert_eq!(walk(&keymap, &["end", "j"], 6), 0);
+    }
+
+    #[test]
+    fn picker_home_end_and_number_keys() {
+        let keymap = Keymap::default();
+        assert_eq!(walk(&keymap, &["end"], 6), 5);
+        assert_eq!(walk(&keymap, &["j", "j", "home"], 6), 0);
+        assert_eq!(walk(&keymap, &["3"], 6), 2);
+        assert_eq!(walk(&keymap, &["6", "1"], 6), 0);
+        // Past the last preset a number does nothing
+        assert_eq!(pick(&keymap, "7", 2, 6), PickerKey::Ignore);
+        // With a modifier it's just an unbound key
+        assert_eq!(pick(&keymap, "alt+2", 0, 6), PickerKey::Ignore);
+    }
+
+    #[test]
+    fn picker_confirm_close_and_prefetch() {
+        let keymap = Keymap::default();
+        assert_eq!(pick(&keymap, "p", 3, 6), PickerKey::Confirm);
+        assert_eq!(pick(&keymap, "enter", 3, 6), PickerKey::Confirm);
+        assert_eq!(pick(&keymap, "q", 3, 6), PickerKey::Close);
+        assert_eq!(pick(&keymap, "esc", 3, 6), PickerKey::Close);
+        assert_eq!(pick(&keymap, "d", 3, 6), PickerKey::Prefetch);
+        // Main-view keys do nothing while the picker is open
+        assert_eq!(pick(&keymap, "n", 3, 6), PickerKey::Ignore);
+        assert_eq!(pick(&keymap, "space", 3, 6), PickerKey::Ignore);
+    }
+
+    #[test]
+    fn picker_follows_remapped_keys_but_keeps_fixed_ones() {
+        let bindings: toml::Table = toml::from_str(
+            r#"
+            menu_up = "w"
+            menu_down = "s"
+            quit = "1"
+            "#,
+        )
+        .unwrap();
+        let keymap = Keymap::new(&bindings, &UNICODE).unwrap();
+        assert_eq!(walk(&keymap, &["s", "s", "w"], 6), 1);
+        assert_eq!(pick(&keymap, "j", 0, 6), PickerKey::Ignore);
+        // 1 stays a number key in the picker
+        assert_eq!(pick(&keymap, "1", 4, 6), PickerKey::Select(0));
+        assert_eq!(pick(&keymap, "esc", 4, 6), PickerKey::Close);
+    }
+
+    fn preset(name: &'static str, volume: Option<f32>, visualizer: Option<&'static str>) -> Preset {
+        Preset {
+            volume,
+            visualizer,
+            ..get_preset(name).unwrap().clone()
+        }
+    }
+
+    /// Volume after switching through `presets` from `start`, with each
+    /// preset's manual change (if any) made right after its switch.
+    fn switch_through(start: f32, presets: &[(Preset, Option<f32>)]) -> Vec<f32> {
+        let mut manual = None;
+        let mut current = start;
+        presets
+            .iter()
+            .map(|(preset, change)| {
+                if let Some(volume) = preset_overrides(preset, current, &mut manual).0 {
+                    current = volume;
+                }
+                let heard = current;
+                if let Some(change) = change {
+                    current = *change;
+                }
+                heard
+            })
+            .collect()
+    }
+
+    #[test]
+    fn preset_volume_applies_and_the_earlier_volume_comes_back() {
+        let relax = preset("relax", Some(0.4), None);
+        let focus = preset("focus", None, None);
+        assert_eq!(switch_through(0.7, &[(relax, None), (focus, None)]), [0.4, 0.7]);
+    }
+
+    #[test]
+    fn volume_from_before_the_first_preset_volume_is_kept() {
+        let relax = preset("relax", Some(0.4), None);
+        let deep = preset("deep", Some(0.3), None);
+        let focus = preset("focus", None, None);
+        assert_eq!(
+            switch_through(0.7, &[(relax.clone(), None), (deep, None), (relax, None), (focus, None)]),
+            [0.4, 0.3, 0.4, 0.7]
+        );
+    }
+
+    #[test]
+    fn manual_change_lasts_until_the_next_switch() {
+        let relax = preset("relax", Some(0.4), None);
+        let deep = preset("deep", Some(0.3), None);
+        let focus = preset("focus", None, None);
+        // Turned up on relax: deep still gets its own volume, focus the old one
+        assert_eq!(
+            switch_through(0.7, &[(relax, Some(0.5)), (deep, None), (focus, None)]),
+            [0.4, 0.3, 0.7]
+        );
+    }
+
+    #[test]
+    fn presets_without_a_volume_leave_it_alone() {
+        let focus = preset("focus", None, None);
+        let morning = preset("morning", None, None);
+        let relax = preset("relax", Some(0.4), None);
+        let mut manual = None;
+        assert_eq!(preset_overrides(&focus, 0.7, &mut manual), (None, None));
+        assert_eq!(manual, None);
+        // A change made on a preset without a volume is what comes back
+        assert_eq!(
+            switch_through(0.7, &[(focus, Some(0.55)), (relax, None), (morning, None)]),
+            [0.7, 0.4, 0.55]
+        );
+    }
+
+    #[test]
+    fn preset_visualizer_switches_and_unknown_names_are_ignored() {
+        let mut manual = None;
+        let radial = preset("relax", None, Some("radial"));
+        assert_eq!(preset_overrides(&radial, 0.7, &mut manual).1, Some(VisualizerStyle::Radial));
+        let meter = preset("focus", Some(0.5), Some("meter"));
+        assert_eq!(preset_overrides(&meter, 0.7, &mut manual), (Some(0.5), Some(VisualizerStyle::Meter)));
+        let plain = preset("deep", None, None);
+        assert_eq!(preset_overrides(&plain, 0.5, &mut manual), (Some(0.7), None));
+        let unknown = preset("deep", None, Some("sparkles"));
+        assert_eq!(preset_overrides(&unknown, 0.7, &mut manual).1, None);
+    }
+
+    const FULL: Duration = Duration::from_millis(66);
+
+    fn secs(secs: f32) -> Duration {
+        Duration::from_secs_f32(secs)
+    }
+
+    #[test]
+    fn full_rate_while_playing() {
+        assert_eq!(idle_interval(FULL, Duration::ZERO, secs(60.0)), FULL);
+        assert_eq!(idle_interval(FULL, Duration::ZERO, Duration::ZERO), FULL);
+    }
+
+    #[test]
+    fn rate_drops_once_paused_or_silent_for_a_while() {
+        assert_eq!(idle_interval(FULL, secs(1.0), secs(60.0)), FULL);
+        assert_eq!(idle_interval(FULL, IDLE_AFTER - secs(0.01), secs(60.0)), FULL);
+        assert_eq!(idle_interval(FULL, IDLE_AFTER, secs(60.0)), IDLE_TICK);
+        assert_eq!(idle_interval(FULL, secs(600.0), secs(600.0)), IDLE_TICK);
+    }
+
+    #[test]
+    fn input_restores_the_full_rate_for_a_grace_period() {
+        let still = secs(30.0);
+        assert_eq!(idle_interval(FULL, still, Duration::ZERO), FULL);
+        assert_eq!(idle_interval(FULL, still, INPUT_GRACE - secs(0.01)), FULL);
+        assert_eq!(idle_interval(FULL, still, INPUT_GRACE), IDLE_TICK);
+    }
+
+    #[test]
+    fn idle_never_refreshes_faster_than_asked() {
+        // --fps 1 is already slower than the idle rate
+        let slow = secs(1.0);
+        assert_eq!(idle_interval(slow, secs(30.0), secs(30.0)), slow);
+        assert_eq!(idle_interval(slow, Duration::ZERO, Duration::ZERO), slow);
+    }
+
+    #[test]
+    fn interval_follows_a_pause_a_key_press_and_playback_resuming() {
+        // (paused or silent for, since the last input) -> interval
+        let steps = [
+            ((0.0, 10.0), FULL),      // playing
+            ((2.0, 12.0), FULL),      // paused two seconds ago
+            ((5.0, 15.0), IDLE_TICK), // still paused: drops
+            ((6.0, 0.5), FULL),       // a key press brings the rate back
+            ((8.0, 2.5), IDLE_TICK),  // and it drops again after the grace period
+            ((0.0, 5.0), FULL),       // samples resume
+        ];
+        for ((still, input), expected) in steps {
+            assert_eq!(idle_interval(FULL, secs(still), secs(input)), expected, "still {}s, input {}s ago", still, input);
+        }
+    }
+}
@@ -2,22 +2,146 @@
 //!
 //! Computes RMS level and frequency bands from audio samples.
 
-use ringbuf::{traits::*, HeapRb};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use ringbuf::traits::*;
 use rustfft::{num_complex::Complex, FftPlanner};
 
-/// FFT window size - must be power of 2
-const FFT_SIZE: usize = 2048;
+use super::player::SAMPLE_RATE;
+
+/// Smallest and largest accepted FFT sizes
+const MIN_FFT_SIZE: usize = 256;
+const MAX_FFT_SIZE: usize = 16384;
+
+/// Frequency range covered by the bands (Hz)
+const BAND_LOW_HZ: f32 = 30.0;
+const BAND_HIGH_HZ: f32 = 16_000.0;
+
+/// Level shown as an empty band (dBFS)
+const DEFAULT_DB_FLOOR: f32 = -60.0;
+
+/// Boost per octave above 1 kHz (cut below), offsetting the natural
+/// high-frequency roll-off of music so upper bands stay visible
+const TILT_DB_PER_OCTAVE: f32 = 3.0;
+
+/// How long the peak level is held before it starts decaying
+const PEAK_HOLD: Duration = Duration::from_millis(1500);
+
+/// Bass range used for BPM detection (Hz)
+const BASS_LOW_HZ: f32 = 100.0;
+const BASS_HIGH_HZ: f32 = 300.0;
 
-/// Number of frequency bands for visualization
-const NUM_BANDS: usize = 64;
+/// Number of FFT frames of bass history kept (~8 seconds at 15 FPS)
+const BPM_HISTORY_LEN: usize = 128;
 
-/// Analysis ring buffer size - enough for a few FFT windows
-pub const ANALYSIS_BUFFER_SIZE: usize = FFT_SIZE * 4;
+/// Tempo range considered by the BPM estimator
+const BPM_MIN: f32 = 50.0;
+const BPM_MAX: f32 = 200.0;
+
+/// Minimum normalized autocorrelation to report a BPM
+const BPM_MIN_CONFIDENCE: f32 = 0.3;
+
+/// Analyzer tuning, from the command line.
+#[derive(Debug, Clone, Copy)]
+pub struct AnalyzerConfig {
+    /// FFT window size in samples - must be a power of 2
+    pub fft_size: usize,
+    /// Number of frequency bands for visualization
+    pub num_bands: usize,
+    /// Smoothing factor for values (0 = raw, higher = smoother)
+    pub smoothing: f32,
+    /// Most FFT frames computed per second
+    pub update_rate: u32,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            fft_size: 2048,
+            num_bands: 64,
+            smoothing: 0.7,
+            update_rate: 60,
+        }
+    }
+}
+
+impl AnalyzerConfig {
+    /// Reject values the analyzer can't work with.
+    pub fn validate(&self) -> Result<()> {
+        if !self.fft_size.is_power_of_two() || !(MIN_FFT_SIZE..=MAX_FFT_SIZE).contains(&self.fft_size) {
+            bail!(
+                "FFT size must be a power of two between {} and {}, got {}",
+                MIN_FFT_SIZE,
+                MAX_FFT_SIZE,
+                self.fft_size
+            );
+        }
+        let bins = self.spectrum_bins();
+        if self.num_bands == 0 || self.num_bands > bins {
+            bail!(
+                "Band count must be between 1 and {} for an FFT size of {}, got {}",
+                bins,
+                self.fft_size,
+                self.num_bands
+            );
+        }
+        if !(0.0..1.0).contains(&self.smoothing) {
+            bail!("Smoothing must be at least 0 and below 1, got {}", self.smoothing);
+        }
+        if self.update_rate == 0 {
+            bail!("Analyzer update rate must be at least 1 per second");
+        }
+        Ok(())
+    }
+
+    /// FFT bins between `BAND_LOW_HZ` and `BAND_HIGH_HZ`.
+    fn spectrum_bins(&self) -> usize {
+        let bin_hz = SAMPLE_RATE as f32 / self.fft_size as f32;
+        let high_hz = BAND_HIGH_HZ.min(SAMPLE_RATE as f32 / 2.0);
+        ((high_hz - BAND_LOW_HZ) / bin_hz) as usize
+    }
+
+    /// Most samples read per update - enough for a few FFT windows, and
+    /// even so it always covers whole stereo frames.
+    fn buffer_size(&self) -> usize {
+        self.fft_size * 4
+    }
+
+    /// Most mono samples kept between updates; older samples are dropped.
+    fn max_sample_buffer(&self) -> usize {
+        self.fft_size * 2
+    }
+}
+
+/// How far back the loudest RMS is remembered when normalizing `rms()`
+const RMS_WINDOW: Duration = Duration::from_secs(10);
+
+/// Most RMS readings kept (10 seconds at the fastest update rate)
+const RMS_HISTORY_LEN: usize = 600;
+
+/// Quietest level `rms()` normalizes against, so silence stays near zero
+const RMS_NORMALIZE_FLOOR: f32 = 0.01;
+
+/// Number of FFT frames of spectral flux the onset threshold averages over
+const ONSET_HISTORY_LEN: usize = 32;
+
+/// Flux must exceed the recent average by this factor to count as an onset
+const ONSET_THRESHOLD_RATIO: f32 = 1.5;
+
+/// Shortest gap between two onsets
+const ONSET_MIN_GAP: Duration = Duration::from_millis(150);
+
+/// How long an onset's pulse takes to fade out
+const ONSET_PULSE: Duration = Duration::from_millis(400);
 
 /// Audio analyzer for computing RMS and frequency bands.
 pub struct AudioAnalyzer {
     /// Ring buffer consumer for analysis samples
     consumer: Option<ringbuf::HeapCons<f32>>,
+    /// Samples skipped unanalyzed because updates fell behind
+    skipped: u64,
     /// Sample buffer for FFT
     sample_buffer: Vec<f32>,
     /// FFT input buffer
@@ -26,99 +150,234 @@ pub struct AudioAnalyzer {
     fft_output: Vec<Complex<f32>>,
     /// FFT planner
     fft: std::sync::Arc<dyn rustfft::Fft<f32>>,
+    /// Hann window coefficients, one per FFT input sample
+    window: Vec<f32>,
+    /// FFT size, band count, smoothing and update rate
+    config: AnalyzerConfig,
+    /// When the last FFT frame was computed
+    last_fft: Option<Instant>,
     /// Current RMS level
     rms: f32,
+    /// Smoothed RMS per FFT frame over the last `RMS_WINDOW`, with the time it was measured
+    rms_history: VecDeque<(Instant, f32)>,
+    /// Current per-channel RMS levels (left, right)
+    rms_stereo: (f32, f32),
+    /// Current L/R correlation (-1 = out of phase, 0 = unrelated, 1 = mono)
+    correlation: f32,
+    /// Held peak sample level
+    peak: f32,
+    /// When the current peak was last raised
+    peak_hold_timer: Instant,
     /// Current frequency bands
     bands: Vec<f32>,
-    /// Smoothing factor for values (higher = smoother)
-    smoothing: f32,
+    /// FFT bin range `[start, end)` of each band
+    band_bins: Vec<(usize, usize)>,
+    /// Per-band gain (dB) applied before mapping to 0-1
+    band_gain_db: Vec<f32>,
+    /// Level that maps to an empty band (dBFS)
+    db_floor: f32,
+    /// Bass energy per FFT frame, with the time it was measured
+    bass_history: VecDeque<(Instant, f32)>,
+    /// Current BPM estimate
+    bpm: Option<f32>,
+    /// Frequency of the loudest FFT bin in the latest frame (Hz)
+    peak_frequency: f32,
+    /// Spectrum magnitudes of the previous FFT frame, for spectral flux
+    prev_magnitudes: Vec<f32>,
+    /// Spectral flux of recent FFT frames
+    flux_history: VecDeque<f32>,
+    /// Whether the latest FFT frame was an onset
+    beat: bool,
+    /// When the last onset happened, and how far it rose above the threshold (0-1)
+    last_onset: Option<(Instant, f32)>,
 }
 
 impl AudioAnalyzer {
     /// Create a new analyzer without a consumer.
     /// Use `create_buffer()` to get a producer/consumer pair for each track.
-    pub fn new() -> Self {
+    ///
+    /// `config` should already have passed `AnalyzerConfig::validate`.
+    pub fn new(config: AnalyzerConfig) -> Self {
+        let fft_size = config.fft_size;
         let mut planner = FftPlanner::new();
-        let fft = planner.plan_fft_forward(FFT_SIZE);
+        let fft = planner.plan_fft_forward(fft_size);
+        let window = (0..fft_size)
+            .map(|i| 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / (fft_size - 1) as f32).cos()))
+            .collect();
 
         Self {
             consumer: None,
-            sample_buffer: Vec::with_capacity(FFT_SIZE),
-            fft_input: vec![Complex::new(0.0, 0.0); FFT_SIZE],
-            fft_output: vec![Complex::new(0.0, 0.0); FFT_SIZE],
+            skipped: 0,
+            sample_buffer: Vec::with_capacity(config.max_sample_buffer() + config.buffer_size() / 2),
+            fft_input: vec![Complex::new(0.0, 0.0); fft_size],
+            fft_output: vec![Complex::new(0.0, 0.0); fft_size],
             fft,
+            window,
+            config,
+            last_fft: None,
             rms: 0.0,
-            bands: vec![0.0; NUM_BANDS],
-            smoothing: 0.7,
+            rms_history: VecDeque::with_capacity(RMS_HISTORY_LEN),
+            rms_stereo: (0.0, 0.0),
+            correlation: 0.0,
+            peak: 0.0,
+            peak_hold_timer: Instant::now(),
+            bands: vec![0.0; config.num_bands],
+            band_bins: band_bins(SAMPLE_RATE as f32, fft_size, config.num_bands),
+            band_gain_db: band_gains(config.num_bands),
+            db_floor: DEFAULT_DB_FLOOR,
+            bass_history: VecDeque::with_capacity(BPM_HISTORY_LEN),
+            bpm: None,
+            peak_frequency: 0.0,
+            prev_magnitudes: vec![0.0; fft_size / 2],
+            flux_history: VecDeque::with_capacity(ONSET_HISTORY_LEN),
+            beat: false,
+            last_onset: None,
         }
     }
 
-    /// Create a new analysis buffer and return the producer.
-    /// The analyzer will consume from the new buffer.
-    pub fn create_buffer(&mut self) -> ringbuf::HeapProd<f32> {
-        let ring = HeapRb::<f32>::new(ANALYSIS_BUFFER_SIZE);
-        let (producer, consumer) = ring.split();
+    /// Set the level shown as an empty band, in dBFS (e.g. -60).
+    pub fn set_db_floor(&mut self, db: f32) {
+        self.db_floor = db.min(-1.0);
+    }
+
+    /// Read samples from `consumer`, the player's tee of what it plays.
+    pub fn set_consumer(&mut self, consumer: ringbuf::HeapCons<f32>) {
         self.consumer = Some(consumer);
+    }
+
+    /// Samples skipped so far because updates came too slowly to analyze
+    /// all of them.
+    pub fn skipped_samples(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Forget the previous track's samples, beats and tempo.
+    pub fn reset(&mut self) {
         self.sample_buffer.clear();
-        producer
+        self.bass_history.clear();
+        self.bpm = None;
+        self.flux_history.clear();
+        self.beat = false;
+        self.last_onset = None;
     }
 
-    /// Process available samples and update analysis.
-    pub fn update(&mut self) {
+    /// Process the samples that are audible by now and update analysis.
+    ///
+    /// The player hands over samples as it writes them to the device, which
+    /// plays them `latency` later; the newest `latency` worth stay queued so
+    /// the display lines up with what's heard.
+    pub fn update(&mut self, latency: Duration) {
         // Drain available samples from ring buffer (limit to avoid blocking event loop)
-        const MAX_SAMPLES_PER_UPDATE: usize = 8192;
         let mut samples_read = 0;
+        let mut raw_peak = 0f32;
+        let max_samples = self.config.buffer_size();
+        let mut stereo = StereoSums::default();
 
         if let Some(ref mut consumer) = self.consumer {
-            while samples_read < MAX_SAMPLES_PER_UPDATE {
-                if let Some(sample) = consumer.try_pop() {
-                    // Convert stereo to mono by averaging pairs
-                    if samples_read % 2 == 1 {
-                        // This is the right channel, average with previous left
-                        if let Some(last) = self.sample_buffer.last_mut() {
-                            *last = (*last + sample) * 0.5;
-                        }
-                    } else {
-                        // This is the left channel
-                        self.sample_buffer.push(sample);
-                    }
-                    samples_read += 1;
-                } else {
+            // Skip a backlog from slow updates so the display keeps up with
+            // the audio; the count stays even to keep frames whole
+            let held = (latency.as_secs_f32() * SAMPLE_RATE as f32) as usize * 2;
+            let held = held.min(consumer.capacity().get() / 2);
+            let audible = consumer.occupied_len().saturating_sub(held);
+            let backlog = audible.saturating_sub(max_samples) / 2 * 2;
+            self.skipped += consumer.skip(backlog) as u64;
+
+            // Whole frames only: the player pushes whole frames, so popping
+            // in pairs keeps left and right from ever swapping
+            while samples_read < max_samples && samples_read + 2 <= audible - backlog {
+                let (Some(left), Some(right)) = (consumer.try_pop(), consumer.try_pop()) else {
                     break;
-                }
+                };
+                raw_peak = raw_peak.max(left.abs()).max(right.abs());
+                // Convert stereo to mono by averaging the pair
+                self.sample_buffer.push((left + right) * 0.5);
+                stereo.add(left, right);
+                samples_read += 2;
             }
         }
 
+        self.update_peak(raw_peak);
+
         if samples_read == 0 {
             // Decay values when no new samples
             self.rms *= 0.95;
+            self.rms_stereo.0 *= 0.95;
+            self.rms_stereo.1 *= 0.95;
             for band in &mut self.bands {
                 *band *= 0.95;
             }
             return;
         }
 
+        self.update_stereo(&stereo);
+
+        // Bound the buffer when samples arrive faster than we analyze them,
+        // keeping the newest so the display doesn't lag behind the audio
+        let max_buffer = self.config.max_sample_buffer();
+        if self.sample_buffer.len() > max_buffer {
+            let excess = self.sample_buffer.len() - max_buffer;
+            self.sample_buffer.drain(..excess);
+        }
+
+        // Hold off until the next frame is due at the configured rate
+        let interval = Duration::from_secs(1) / self.config.update_rate;
+        if self.last_fft.is_some_and(|t| t.elapsed() < interval) {
+            return;
+        }
+
         // Process if we have enough samples (only do one FFT per update)
-        if self.sample_buffer.len() >= FFT_SIZE {
+        let fft_size = self.config.fft_size;
+        if self.sample_buffer.len() >= fft_size {
             self.process_fft();
+            self.last_fft = Some(Instant::now());
             // Keep last quarter for overlap
-            let keep_from = self.sample_buffer.len() - FFT_SIZE / 4;
-            self.sample_buffer = self.sample_buffer[keep_from..].to_vec();
+            let keep_from = self.sample_buffer.len() - fft_size / 4;
+            self.sample_buffer.drain(..keep_from);
+        }
+    }
+
+    /// Smooth in the channel levels and correlation of the samples just read.
+    fn update_stereo(&mut self, stereo: &StereoSums) {
+        if stereo.frames == 0 {
+            return;
+        }
+        let smoothing = self.config.smoothing;
+        let frames = stereo.frames as f32;
+        let left = (stereo.left_sq / frames).sqrt();
+        let right = (stereo.right_sq / frames).sqrt();
+        self.rms_stereo.0 = self.rms_stereo.0 * smoothing + left * (1.0 - smoothing);
+        self.rms_stereo.1 = self.rms_stereo.1 * smoothing + right * (1.0 - smoothing);
+
+        // Silence has no phase; leave the reading where it was
+        let energy = (stereo.left_sq * stereo.right_sq).sqrt();
+        if energy > f32::EPSILON {
+            let correlation = (stereo.cross / energy).clamp(-1.0, 1.0);
+            self.correlation = self.correlation * smoothing + correlation * (1.0 - smoothing);
+        }
+    }
+
+    /// Raise the held peak, or let it decay once the hold time has passed.
+    fn update_peak(&mut self, raw_peak: f32) {
+        if raw_peak >= self.peak {
+            self.peak = raw_peak.min(1.0);
+            self.peak_hold_timer = Instant::now();
+        } else if self.peak_hold_timer.elapsed() > PEAK_HOLD {
+            self.peak = (self.peak * 0.95).max(raw_peak);
         }
     }
 
     /// Perform FFT analysis on the sample buffer.
     fn process_fft(&mut self) {
-        let samples = &self.sample_buffer[..FFT_SIZE];
+        let fft_size = self.config.fft_size;
+        let samples = &self.sample_buffer[..fft_size];
 
         // Compute RMS
         let sum_squares: f32 = samples.iter().map(|s| s * s).sum();
-        let new_rms = (sum_squares / FFT_SIZE as f32).sqrt();
+        let new_rms = (sum_squares / fft_size as f32).sqrt();
 
         // Apply Hann window and copy to FFT input
-        for (i, &sample) in samples.iter().enumerate() {
-            let window = 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / (FFT_SIZE - 1) as f32).cos());
-            self.fft_input[i] = Complex::new(sample * window, 0.0);
+        for ((input, &sample), &window) in self.fft_input.iter_mut().zip(samples).zip(&self.window) {
+            *input = Complex::new(sample * window, 0.0);
         }
 
         // Perform FFT
@@ -128,54 +387,252 @@ impl AudioAnalyzer {
         // Extract frequency bands
         let new_bands = self.extract_bands();
 
+        self.peak_frequency = self.frequency_at_peak_bin();
+
+        // Track bass energy for tempo estimation
+        self.record_bass_energy();
+
+        // Look for swells and hits
+        self.detect_onset();
+
         // Smooth values
-        self.rms = self.rms * self.smoothing + new_rms * (1.0 - self.smoothing);
+        let smoothing = self.config.smoothing;
+        self.rms = self.rms * smoothing + new_rms * (1.0 - smoothing);
+        self.record_rms();
         for (i, &new_band) in new_bands.iter().enumerate() {
-            self.bands[i] = self.bands[i] * self.smoothing + new_band * (1.0 - self.smoothing);
+            self.bands[i] = self.bands[i] * smoothing + new_band * (1.0 - smoothing);
         }
     }
 
     /// Extract frequency bands from FFT output.
+    ///
+    /// Each band takes its strongest bin, converted to dBFS, tilted, and
+    /// mapped linearly from `db_floor` (0.0) to 0 dB (1.0).
     fn extract_bands(&self) -> Vec<f32> {
-        let mut bands = vec![0.0; NUM_BANDS];
-
-        // Only use first half of FFT output (positive frequencies)
-        let useful_bins = FFT_SIZE / 2;
-
-        // Logarithmic band distribution for better visual representation
-        // Each band covers a range of FFT bins, with higher bands covering more bins
-        for band_idx in 0..NUM_BANDS {
-            // Logarithmic frequency mapping
-            let low_freq = (band_idx as f32 / NUM_BANDS as f32).powf(2.0);
-            let high_freq = ((band_idx + 1) as f32 / NUM_BANDS as f32).powf(2.0);
-
-            let low_bin = (low_freq * useful_bins as f32) as usize;
-            let high_bin = ((high_freq * useful_bins as f32) as usize).max(low_bin + 1);
-
-            // Average magnitude in this frequency range
-            let mut sum = 0.0;
-            let mut count = 0;
-            for bin in low_bin..high_bin.min(useful_bins) {
-                let magnitude = self.fft_output[bin].norm();
-                sum += magnitude;
-                count += 1;
-            }
+        // A full-scale sine through the Hann window peaks at N/4
+        let full_scale = self.config.fft_size as f32 / 4.0;
+
+        self.band_bins
+            .iter()
+            .zip(&self.band_gain_db)
+            .map(|(&(start, end), gain_db)| {
+                let magnitude = self.fft_output[start..end]
+                    .iter()
+                    .map(|c| c.norm())
+                    .fold(0.0, f32::max);
+                let db = 20.0 * (magnitude / full_scale).max(1e-9).log10() + gain_db;
+                ((db - self.db_floor) / -self.db_floor).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+
+    /// Remember the current RMS, forgetting readings older than `RMS_WINDOW`.
+    fn record_rms(&mut self) {
+        let now = Instant::now();
+        while self
+            .rms_history
+            .front()
+            .is_some_and(|&(at, _)| now - at > RMS_WINDOW || self.rms_history.len() >= RMS_HISTORY_LEN)
+        {
+            self.rms_history.pop_front();
+        }
+        self.rms_history.push_back((now, self.rms));
+    }
+
+    /// Compare this frame's spectral flux against the recent average.
+    ///
+    /// Flux is the summed rise in magnitude across bins since the previous
+    /// frame, so steady tones score low and new sounds score high.
+    fn detect_onset(&mut self) {
+        let full_scale = self.config.fft_size as f32 / 4.0;
+        let mut flux = 0.0;
+        for (prev, c) in self.prev_magnitudes.iter_mut().zip(&self.fft_output) {
+            let magnitude = c.norm() / full_scale;
+            flux += (magnitude - *prev).max(0.0);
+            *prev = magnitude;
+        }
 
-            if count > 0 {
-                // Normalize and scale for visualization
-                let avg = sum / count as f32;
-                // Scale to roughly 0-1 range (adjust multiplier as needed)
-                bands[band_idx] = (avg / FFT_SIZE as f32 * 40.0).min(1.0);
+        let mean = if self.flux_history.is_empty() {
+            0.0
+        } else {
+            self.flux_history.iter().sum::<f32>() / self.flux_history.len() as f32
+        };
+        let threshold = mean * ONSET_THRESHOLD_RATIO;
+        let settled = self.flux_history.len() >= ONSET_HISTORY_LEN / 4;
+        let rested = self
+            .last_onset
+            .is_none_or(|(at, _)| at.elapsed() >= ONSET_MIN_GAP);
+
+        self.beat = settled && rested && flux > threshold && threshold > f32::EPSILON;
+        if self.beat {
+            // Twice the threshold or more is a full-strength onset
+            let strength = (flux / threshold - 1.0).clamp(0.0, 1.0);
+            self.last_onset = Some((Instant::now(), strength));
+        }
+
+        if self.flux_history.len() == ONSET_HISTORY_LEN {
+            self.flux_history.pop_front();
+        }
+        self.flux_history.push_back(flux);
+    }
+
+    /// Append the current bass energy to the history and refresh the BPM estimate.
+    fn record_bass_energy(&mut self) {
+        let bin_hz = SAMPLE_RATE as f32 / self.config.fft_size as f32;
+        let low_bin = (BASS_LOW_HZ / bin_hz) as usize;
+        let high_bin = (BASS_HIGH_HZ / bin_hz).ceil() as usize;
+        let energy: f32 = self.fft_output[low_bin..high_bin]
+            .iter()
+            .map(|c| c.norm_sqr())
+            .sum();
+
+        if self.bass_history.len() == BPM_HISTORY_LEN {
+            self.bass_history.pop_front();
+        }
+        self.bass_history.push_back((Instant::now(), energy));
+        self.bpm = self.estimate_bpm();
+    }
+
+    /// Estimate tempo by autocorrelating the bass onset envelope.
+    fn estimate_bpm(&self) -> Option<f32> {
+        // Need a few seconds of history before the estimate means anything
+        if self.bass_history.len() < BPM_HISTORY_LEN / 2 {
+            return None;
+        }
+
+        // Frames arrive at the UI tick rate, so measure the actual spacing
+        let first = self.bass_history.front()?.0;
+        let last = self.bass_history.back()?.0;
+        let frame_secs = (last - first).as_secs_f32() / (self.bass_history.len() - 1) as f32;
+        if frame_secs <= 0.0 {
+            return None;
+        }
+
+        // Onset envelope: positive energy changes, mean removed
+        let mut onsets: Vec<f32> = self
+            .bass_history
+            .iter()
+            .zip(self.bass_history.iter().skip(1))
+            .map(|((_, prev), (_, cur))| (cur - prev).max(0.0))
+            .collect();
+        let mean = onsets.iter().sum::<f32>() / onsets.len() as f32;
+        for onset in &mut onsets {
+            *onset -= mean;
+        }
+
+        let energy: f32 = onsets.iter().map(|o| o * o).sum();
+        if energy <= f32::EPSILON {
+            return None;
+        }
+
+        let autocorr = |lag: usize| -> f32 {
+            onsets.iter().zip(&onsets[lag..]).map(|(a, b)| a * b).sum::<f32>() / energy
+        };
+
+        // Lags (in frames) corresponding to the tempo range
+        let min_lag = ((60.0 / BPM_MAX) / frame_secs).floor().max(1.0) as usize;
+        let max_lag = (((60.0 / BPM_MIN) / frame_secs).ceil() as usize).min(onsets.len() / 2);
+        if min_lag >= max_lag {
+            return None;
+        }
+
+        let (best_lag, best_corr) = (min_lag..=max_lag)
+            .map(|lag| (lag, autocorr(lag)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if best_corr < BPM_MIN_CONFIDENCE {
+            return None;
+        }
+
+        // Parabolic interpolation for sub-frame lag precision
+        let mut lag = best_lag as f32;
+        if best_lag > 1 && best_lag + 1 < onsets.len() {
+            let (prev, next) = (autocorr(best_lag - 1), autocorr(best_lag + 1));
+            let denom = prev - 2.0 * best_corr + next;
+            if denom.abs() > f32::EPSILON {
+                lag += (0.5 * (prev - next) / denom).clamp(-0.5, 0.5);
             }
         }
 
-        bands
+        let bpm = 60.0 / (lag * frame_secs);
+        (BPM_MIN..=BPM_MAX).contains(&bpm).then_some(bpm)
+    }
+
+    /// Frequency of the loudest bin of the FFT output, skipping DC, or 0
+    /// when the frame is silent.
+    fn frequency_at_peak_bin(&self) -> f32 {
+        let fft_size = self.config.fft_size;
+        let peak = self.fft_output[1..fft_size / 2]
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i + 1, c.norm_sqr()))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match peak {
+            Some((bin, power)) if power > f32::EPSILON => bin as f32 * SAMPLE_RATE as f32 / fft_size as f32,
+            _ => 0.0,
+        }
+    }
+
+    /// Whether the latest FFT frame was an onset.
+    pub fn beat(&self) -> bool {
+        self.beat
+    }
+
+    /// Strength of the last onset (0.0 - 1.0), fading to 0 over a short pulse.
+    pub fn onset_strength(&self) -> f32 {
+        let Some((at, strength)) = self.last_onset else {
+            return 0.0;
+        };
+        let t = at.elapsed().as_secs_f32() / ONSET_PULSE.as_secs_f32();
+        strength * (1.0 - t).max(0.0)
+    }
+
+    /// Dominant frequency of the latest frame (Hz), or 0 when silent.
+    pub fn peak_frequency(&self) -> f32 {
+        self.peak_frequency
+    }
+
+    /// Get the current BPM estimate, or `None` if confidence is low.
+    pub fn bpm(&self) -> Option<f32> {
+        self.bpm
     }
 
     /// Get current RMS level (0.0 - 1.0).
+    ///
+    /// Normalized against the loudest level of the last 10 seconds, so quiet
+    /// tracks use the full range too.
     pub fn rms(&self) -> f32 {
-        // Scale RMS for better visualization (music is often quieter than peak)
-        (self.rms * 3.0).min(1.0)
+        let loudest = self
+            .rms_history
+            .iter()
+            .map(|&(_, rms)| rms)
+            .fold(RMS_NORMALIZE_FLOOR, f32::max);
+        (self.rms / loudest).min(1.0)
+    }
+
+    /// Get current left and right RMS levels (0.0 - 1.0), scaled by 3 so
+    /// typical music reads mid-scale.
+    pub fn rms_stereo(&self) -> (f32, f32) {
+        ((self.rms_stereo.0 * 3.0).min(1.0), (self.rms_stereo.1 * 3.0).min(1.0))
+    }
+
+    /// Get the L/R correlation: 1 for mono, 0 for unrelated channels,
+    /// -1 for channels out of phase.
+    pub fn correlation(&self) -> f32 {
+        self.correlation
+    }
+
+    /// Get held peak sample level (0.0 - 1.0).
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+
+    /// Center frequency of each band (Hz), from its FFT bin range.
+    pub fn band_center_freqs(&self) -> Vec<f32> {
+        let bin_hz = SAMPLE_RATE as f32 / self.config.fft_size as f32;
+        self.band_bins
+            .iter()
+            .map(|&(start, end)| (start + end) as f32 / 2.0 * bin_hz)
+            .collect()
     }
 
     /// Get current frequency bands.
@@ -184,8 +641,345 @@ impl AudioAnalyzer {
     }
 }
 
+/// Running sums over stereo frames for channel levels and correlation.
+#[derive(Default)]
+struct StereoSums {
+    left_sq: f32,
+    right_sq: f32,
+    /// Sum of left × right
+    cross: f32,
+    frames: usize,
+}
+
+impl StereoSums {
+    fn add(&mut self, left: f32, right: f32) {
+        self.left_sq += left * left;
+        self.right_sq += right * right;
+        self.cross += left * right;
+        self.frames += 1;
+    }
+}
+
+/// FFT bin ranges for `num_bands` bands spaced evenly on a log frequency
+/// axis from `BAND_LOW_HZ` to `BAND_HIGH_HZ`.
+///
+/// Bass bands narrower than a bin share it, so every band has at least one.
+fn band_bins(sample_rate: f32, fft_size: usize, num_bands: usize) -> Vec<(usize, usize)> {
+    let bin_hz = sample_rate / fft_size as f32;
+    let high_hz = BAND_HIGH_HZ.min(sample_rate / 2.0);
+    let ratio = high_hz / BAND_LOW_HZ;
+    let edge_bin = |i: usize| {
+        let hz = BAND_LOW_HZ * ratio.powf(i as f32 / num_bands as f32);
+        ((hz / bin_hz).round() as usize).clamp(1, fft_size / 2 - 1)
+    };
+
+    (0..num_bands)
+        .map(|i| {
+            let start = edge_bin(i);
+            let end = edge_bin(i + 1).max(start + 1);
+            (start, end)
+        })
+        .collect()
+}
+
+/// Spectral tilt per band, from the band's center frequency.
+fn band_gains(num_bands: usize) -> Vec<f32> {
+    let ratio = BAND_HIGH_HZ / BAND_LOW_HZ;
+    (0..num_bands)
+        .map(|i| {
+            let center_hz = BAND_LOW_HZ * ratio.powf((i as f32 + 0.5) / num_bands as f32);
+            TILT_DB_PER_OCTAVE * (center_hz / 1000.0).log2()
+        })
+        .collect()
+}
+
 impl Default for AudioAnalyzer {
     fn default() -> Self {
-        Self::new()
+        Self::new(AnalyzerConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use ringbuf::{HeapProd, HeapRb};
+
+    /// An analyzer reading from a fresh ring of `capacity` samples.
+    fn analyzer_with_ring(config: AnalyzerConfig, capacity: usize) -> (AudioAnalyzer, HeapProd<f32>) {
+        let (producer, consumer) = HeapRb::new(capacity).split();
+        let mut analyzer = AudioAnalyzer::new(config);
+        analyzer.set_consumer(consumer);
+        (analyzer, producer)
+    }
+
+    #[test]
+    fn fast_producer_never_blocks_and_every_sample_is_accounted_for() {
+        let config = AnalyzerConfig::default();
+        let per_update = config.buffer_size();
+        let (mut analyzer, mut producer) = analyzer_with_ring(config, per_update * 8);
+        // Three updates' worth arrives between each update
+        let burst = vec![0.25; per_update * 3];
+
+        for round in 1..=50 {
+            // The producer side never waits: a full burst is taken every time
+            assert_eq!(producer.push_slice(&burst), burst.len());
+            analyzer.update(Duration::ZERO);
+
+            // The backlog is skipped and counted rather than left to pile up
+            assert_eq!(producer.occupied_len(), 0);
+            assert_eq!(analyzer.skipped_samples(), (round * (burst.len() - per_update)) as u64);
+            assert!(analyzer.sample_buffer.len() <= config.max_sample_buffer());
+        }
+    }
+
+    #[test]
+    fn sample_buffer_is_capped_between_ffts() {
+        // One FFT a second, so samples keep arriving with no frame due
+        let config = AnalyzerConfig {
+            update_rate: 1,
+            ..AnalyzerConfig::default()
+        };
+        let per_update = config.buffer_size();
+        let (mut analyzer, mut producer) = analyzer_with_ring(config, per_update * 2);
+        let burst = vec![0.25; per_update];
+
+        for _ in 0..100 {
+            producer.push_slice(&burst);
+            analyzer.update(Duration::ZERO);
+            assert!(analyzer.sample_buffer.len() <= config.max_sample_buffer());
+        }
+        assert_eq!(analyzer.skipped_samples(), 0);
+    }
+
+    /// Raw per-frame readings: no smoothing and no limit on the frame rate.
+    fn raw_config() -> AnalyzerConfig {
+        AnalyzerConfig {
+            smoothing: 0.0,
+            update_rate: 1_000_000,
+            ..AnalyzerConfig::default()
+        }
+    }
+
+    /// `len` samples of a sine at `freq` Hz.
+    fn sine(freq: f32, amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    /// Push `left` and `right` as stereo frames and run one update.
+    fn play(analyzer: &mut AudioAnalyzer, producer: &mut HeapProd<f32>, left: &[f32], right: &[f32]) {
+        let frames: Vec<f32> = left.iter().zip(right).flat_map(|(&l, &r)| [l, r]).collect();
+        assert_eq!(producer.push_slice(&frames), frames.len());
+        analyzer.update(Duration::ZERO);
+    }
+
+    /// A band's level back in dB, undoing the floor-to-0 dB mapping.
+    fn band_db(analyzer: &AudioAnalyzer, band: usize) -> f32 {
+        analyzer.bands()[band] * -analyzer.db_floor + analyzer.db_floor
+    }
+
+    #[test]
+    fn sine_lands_in_its_band_at_its_level() {
+        let config = raw_config();
+        let bin_hz = SAMPLE_RATE as f32 / config.fft_size as f32;
+        // Bin-centered tones: bass, mids and treble at different levels
+        for (bin, amplitude) in [(5, 0.1), (46, 0.5), (464, 0.05)] {
+            let freq = bin as f32 * bin_hz;
+            let (mut analyzer, mut producer) = analyzer_with_ring(config, config.buffer_size() * 2);
+            let tone = sine(freq, amplitude, config.fft_size);
+            play(&mut analyzer, &mut producer, &tone, &tone);
+
+            let loudest = (0..config.num_bands)
+                .max_by(|&a, &b| analyzer.bands()[a].total_cmp(&analyzer.bands()[b]))
+                .unwrap();
+            let (start, end) = analyzer.band_bins[loudest];
+            assert!((start..end).contains(&bin), "{} Hz peaked in band {} ({}..{})", freq, loudest, start, end);
+
+            // Full scale reads 0 dB, before the band's tilt
+            let expected = 20.0 * amplitude.log10() + analyzer.band_gain_db[loudest];
+            let measured = band_db(&analyzer, loudest);
+            assert!((measured - expected).abs() < 0.5, "{} Hz read {} dB, expected {}", freq, measured, expected);
+
+            // Bands well away from the tone stay far below it
+            for (band, &(start, end)) in analyzer.band_bins.iter().enumerate() {
+                if end + 3 <= bin || start >= bin + 4 {
+                    assert!(band_db(&analyzer, band) < measured - 30.0, "{} Hz leaked into band {}", freq, band);
+                }
+            }
+            assert!((analyzer.peak_frequency() - freq).abs() < bin_hz / 2.0);
+        }
+    }
+
+    #[test]
+    fn band_level_follows_the_db_floor() {
+        let config = raw_config();
+        let freq = 46.0 * SAMPLE_RATE as f32 / config.fft_size as f32;
+        // -40 dBFS before tilt: near the bottom of a -60 dB range, gone at -30
+        let tone = sine(freq, 0.01, config.fft_size);
+        let level = |floor: f32| {
+            let (mut analyzer, mut producer) = analyzer_with_ring(config, config.buffer_size() * 2);
+            analyzer.set_db_floor(floor);
+            play(&mut analyzer, &mut producer, &tone, &tone);
+            analyzer.bands().iter().copied().fold(0.0, f32::max)
+        };
+        let at_60 = level(-60.0);
+        assert!(at_60 > 0.25 && at_60 < 0.4, "read {}", at_60);
+        assert_eq!(level(-30.0), 0.0);
+    }
+
+    #[test]
+    fn silence_reads_empty_bands() {
+        let config = raw_config();
+        let (mut analyzer, mut producer) = analyzer_with_ring(config, config.buffer_size() * 2);
+        let silence = vec![0.0; config.fft_size];
+        play(&mut analyzer, &mut producer, &silence, &silence);
+        assert!(analyzer.bands().iter().all(|&band| band == 0.0));
+        assert_eq!(analyzer.peak_frequency(), 0.0);
+    }
+
+    /// Feeds the analyzer one FFT frame per update: each chunk tops the
+    /// quarter kept for overlap back up to a full window.
+    struct OnsetRig {
+        analyzer: AudioAnalyzer,
+        producer: HeapProd<f32>,
+        rng: StdRng,
+    }
+
+    impl OnsetRig {
+        fn new() -> Self {
+            let config = raw_config();
+            let (mut analyzer, mut producer) = analyzer_with_ring(config, config.buffer_size() * 2);
+            let mut rng = StdRng::seed_from_u64(75);
+            // Prime the window so every later chunk completes exactly one frame
+            let noise: Vec<f32> = (0..config.fft_size / 4).map(|_| rng.gen_range(-0.01..0.01)).collect();
+            let frames: Vec<f32> = noise.iter().flat_map(|&s| [s, s]).collect();
+            producer.push_slice(&frames);
+            analyzer.update(Duration::ZERO);
+            Self { analyzer, producer, rng }
+        }
+
+        /// One frame of quiet noise, with a click in the middle of the window
+        /// if `click`. Returns whether the frame was an onset.
+        fn frame(&mut self, click: bool) -> bool {
+            let fft_size = self.analyzer.config.fft_size;
+            let mut chunk: Vec<f32> = (0..fft_size - fft_size / 4).map(|_| self.rng.gen_range(-0.01..0.01)).collect();
+            if click {
+                chunk[fft_size / 4] = 0.9;
+            }
+            play(&mut self.analyzer, &mut self.producer, &chunk, &chunk);
+            assert_eq!(self.analyzer.sample_buffer.len(), fft_size / 4);
+            self.analyzer.beat()
+        }
+    }
+
+    #[test]
+    fn steady_noise_has_no_onsets() {
+        let mut rig = OnsetRig::new();
+        for i in 0..40 {
+            assert!(!rig.frame(false), "onset in frame {}", i);
+        }
+        assert_eq!(rig.analyzer.onset_strength(), 0.0);
+    }
+
+    #[test]
+    fn click_after_steady_noise_is_an_onset() {
+        let mut rig = OnsetRig::new();
+        for _ in 0..ONSET_HISTORY_LEN / 4 {
+            rig.frame(false);
+        }
+        assert!(rig.frame(true));
+        assert!(rig.analyzer.onset_strength() > 0.9);
+        // The click has passed; the frame after isn't an onset
+        assert!(!rig.frame(false));
+    }
+
+    #[test]
+    fn no_onsets_before_the_history_settles() {
+        let mut rig = OnsetRig::new();
+        rig.frame(false);
+        rig.frame(false);
+        assert!(!rig.frame(true));
+    }
+
+    #[test]
+    fn onsets_closer_than_the_minimum_gap_count_once() {
+        let mut rig = OnsetRig::new();
+        for _ in 0..ONSET_HISTORY_LEN / 4 {
+            rig.frame(false);
+        }
+        assert!(rig.frame(true));
+        rig.frame(false);
+        assert!(!rig.frame(true), "second click within {:?}", ONSET_MIN_GAP);
+
+        std::thread::sleep(ONSET_MIN_GAP);
+        rig.frame(false);
+        assert!(rig.frame(true));
+    }
+
+    #[test]
+    fn onset_pulse_fades_out() {
+        let mut rig = OnsetRig::new();
+        for _ in 0..ONSET_HISTORY_LEN / 4 {
+            rig.frame(false);
+        }
+        assert!(rig.frame(true));
+        std::thread::sleep(ONSET_PULSE);
+        assert_eq!(rig.analyzer.onset_strength(), 0.0);
+    }
+
+    #[test]
+    fn hard_panned_input_reads_on_its_own_channel() {
+        let config = raw_config();
+        let tone = sine(440.0, 0.2, config.fft_size);
+        let silence = vec![0.0; config.fft_size];
+        // 0.2 peak is 0.141 RMS, read ×3
+        let level = 0.2 / std::f32::consts::SQRT_2 * 3.0;
+
+        for (left, right, expected) in [(&tone, &silence, (level, 0.0)), (&silence, &tone, (0.0, level))] {
+            let (mut analyzer, mut producer) = analyzer_with_ring(config, config.buffer_size() * 2);
+            play(&mut analyzer, &mut producer, left, right);
+            let (l, r) = analyzer.rms_stereo();
+            assert!((l - expected.0).abs() < 0.01 && (r - expected.1).abs() < 0.01, "read ({}, {})", l, r);
+            // One silent channel has no phase to compare
+            assert_eq!(analyzer.correlation(), 0.0);
+            // The mono mix is half of the one channel playing
+            let channel_rms = 0.2 / std::f32::consts::SQRT_2;
+            assert!((analyzer.rms - channel_rms / 2.0).abs() < 0.002, "mono RMS {}", analyzer.rms);
+        }
+    }
+
+    #[test]
+    fn partial_frames_never_swap_the_channels() {
+        let config = raw_config();
+        let (mut analyzer, mut producer) = analyzer_with_ring(config, config.buffer_size() * 2);
+        let tone = sine(440.0, 0.2, config.fft_size);
+        let frames: Vec<f32> = tone.iter().flat_map(|&s| [s, 0.0]).collect();
+
+        // A push that stops mid-frame leaves the odd sample for the next update
+        let split = 1001;
+        producer.push_slice(&frames[..split]);
+        analyzer.update(Duration::ZERO);
+        assert_eq!(producer.occupied_len(), 1);
+        producer.push_slice(&frames[split..]);
+        analyzer.update(Duration::ZERO);
+
+        let (left, right) = analyzer.rms_stereo();
+        assert!(left > 0.4, "left read {}", left);
+        assert_eq!(right, 0.0);
+    }
+
+    #[test]
+    fn correlation_tells_mono_from_out_of_phase() {
+        let config = raw_config();
+        let tone = sine(440.0, 0.2, config.fft_size);
+        let inverted: Vec<f32> = tone.iter().map(|s| -s).collect();
+        for (right, expected) in [(&tone, 1.0), (&inverted, -1.0)] {
+            let (mut analyzer, mut producer) = analyzer_with_ring(config, config.buffer_size() * 2);
+            play(&mut analyzer, &mut producer, &tone, right);
+            assert!((analyzer.correlation() - expected).abs() < 1e-3);
+        }
     }
 }
@@ -0,0 +1,184 @@
+//! Outputs the decoder writes to.
+//!
+//! The decoder hands interleaved stereo samples to an `AudioBackend`
+//! without knowing what plays them: `CpalBackend` queues them for the cpal
+//! stream's callback, `NullBackend` plays them to nowhere in real time so
+//! fomu runs without a sound card (`--backend null`).
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use ringbuf::{traits::*, HeapProd};
+
+use super::player::RING_BUFFER_SIZE;
+
+/// Name of the backend that discards audio, for `--backend`.
+pub const NULL_BACKEND: &str = "null";
+
+/// Where played samples are copied while recording.
+pub(crate) type RecordSink = Arc<Mutex<Option<Sender<Vec<f32>>>>>;
+
+/// Somewhere to play decoded audio.
+pub trait AudioBackend: Send {
+    /// Take as many of `buf`'s interleaved samples as there's room for,
+    /// returning how many. Never blocks; the decoder waits and retries
+    /// the rest.
+    fn write_samples(&mut self, buf: &[f32]) -> usize;
+
+    /// Frames per second the samples play at.
+    fn sample_rate(&self) -> u32;
+
+    /// Samples per frame.
+    fn channels(&self) -> u16;
+}
+
+/// Queues samples in the ring buffer the cpal output callback mixes from.
+pub struct CpalBackend {
+    producer: HeapProd<f32>,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl CpalBackend {
+    pub(crate) fn new(producer: HeapProd<f32>, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            producer,
+            sample_rate,
+            channels,
+        }
+    }
+}
+
+impl AudioBackend for CpalBackend {
+    fn write_samples(&mut self, buf: &[f32]) -> usize {
+        self.producer.push_slice(buf)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+}
+
+/// Plays samples to nowhere at the speed a device would.
+///
+/// Takes samples up to a ring buffer's worth ahead of the clock and none
+/// while paused, so tracks last as long as they would on a sound card and
+/// the position moves with them. The position counts samples taken rather
+/// than heard, so it runs up to half a second ahead. Recordings get the
+/// samples as they're taken.
+pub struct NullBackend {
+    sample_rate: u32,
+    channels: u16,
+    /// When the samples taken so far will have played
+    due: Instant,
+    paused: Arc<AtomicBool>,
+    played: Arc<AtomicU64>,
+    record: RecordSink,
+}
+
+impl NullBackend {
+    pub(crate) fn new(
+        sample_rate: u32,
+        channels: u16,
+        paused: Arc<AtomicBool>,
+        played: Arc<AtomicU64>,
+        record: RecordSink,
+    ) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            due: Instant::now(),
+            paused,
+            played,
+            record,
+        }
+    }
+
+    fn duration_of(&self, samples: usize) -> Duration {
+        Duration::from_secs_f64(samples as f64 / (self.sample_rate as f64 * self.channels as f64))
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn write_samples(&mut self, buf: &[f32]) -> usize {
+        if self.paused.load(Ordering::Relaxed) {
+            return 0;
+        }
+        // Time stands still while paused, and a late writer doesn't get
+        // to catch up by playing faster
+        let now = Instant::now();
+        self.due = self.due.max(now);
+        if self.due - now >= self.duration_of(RING_BUFFER_SIZE) {
+            return 0;
+        }
+        self.due += self.duration_of(buf.len());
+        self.played.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        if let Some(sink) = self.record.lock().unwrap().as_ref() {
+            let _ = sink.send(buf.to_vec());
+        }
+        buf.len()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::player::{CHANNELS, SAMPLE_RATE};
+
+    fn null_backend() -> NullBackend {
+        let paused = Arc::new(AtomicBool::new(false));
+        NullBackend::new(SAMPLE_RATE, CHANNELS, paused, Arc::new(AtomicU64::new(0)), RecordSink::default())
+    }
+
+    #[test]
+    fn null_backend_stays_a_buffer_ahead_of_the_clock() {
+        let mut backend = null_backend();
+        let chunk = [0.0; 1024];
+        let mut taken = 0;
+        while backend.write_samples(&chunk) > 0 {
+            taken += chunk.len();
+            assert!(taken < RING_BUFFER_SIZE * 2, "never fills up");
+        }
+        assert!(taken >= RING_BUFFER_SIZE && taken < RING_BUFFER_SIZE + chunk.len(), "{}", taken);
+        assert_eq!(backend.played.load(Ordering::Relaxed), taken as u64);
+
+        // A few milliseconds later there's room again
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(backend.write_samples(&chunk), chunk.len());
+    }
+
+    #[test]
+    fn null_backend_takes_nothing_while_paused() {
+        let mut backend = null_backend();
+        backend.paused.store(true, Ordering::Relaxed);
+        assert_eq!(backend.write_samples(&[0.0; 64]), 0);
+        assert_eq!(backend.played.load(Ordering::Relaxed), 0);
+
+        backend.paused.store(false, Ordering::Relaxed);
+        assert_eq!(backend.write_samples(&[0.0; 64]), 64);
+    }
+
+    #[test]
+    fn cpal_backend_takes_what_fits_in_the_ring() {
+        let (producer, consumer) = ringbuf::HeapRb::<f32>::new(8).split();
+        let mut backend = CpalBackend::new(producer, SAMPLE_RATE, CHANNELS);
+        assert_eq!(backend.write_samples(&[0.5; 6]), 6);
+        assert_eq!(backend.write_samples(&[0.5; 6]), 2);
+        assert_eq!(consumer.occupied_len(), 8);
+        assert_eq!((backend.sample_rate(), backend.channels()), (SAMPLE_RATE, CHANNELS));
+    }
+}
@@ -1,25 +1,80 @@
 //! MP3 decoder using symphonia.
 //!
-//! Decodes MP3 files to PCM samples and pushes them to a ring buffer
-//! for the audio thread to consume.
+//! Decodes MP3 files to PCM samples and writes them to an `AudioBackend`
+//! for playback.
 
 use std::fs::File;
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use ringbuf::traits::*;
 use symphonia::core::audio::{AudioBufferRef, Signal};
 use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::formats::{FormatOptions, SeekMode, SeekTo};
 use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
 use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
 
-use super::player::SAMPLE_RATE;
+use super::backend::AudioBackend;
+use super::player::{CHANNELS, SAMPLE_RATE};
+
+/// Title and artist read from the file's tags (ID3v2 for MP3).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackMeta {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+impl TrackMeta {
+    fn from_revision(revision: &MetadataRevision) -> Option<Self> {
+        let mut meta = TrackMeta::default();
+        for tag in revision.tags() {
+            let value = tag.value.to_string().trim().to_string();
+            if value.is_empty() {
+                continue;
+            }
+            match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => meta.title = Some(value),
+                Some(StandardTagKey::Artist) => meta.artist = Some(value),
+                _ => {}
+            }
+        }
+        (meta != TrackMeta::default()).then_some(meta)
+    }
+}
+
+/// Backend that takes and discards everything, so the decoder runs flat out.
+#[derive(Default)]
+struct Discard;
+
+impl AudioBackend for Discard {
+    fn write_samples(&mut self, buf: &[f32]) -> usize {
+        buf.len()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn channels(&self) -> u16 {
+        CHANNELS
+    }
+}
+
+/// Decode all of `path` as fast as possible and throw the samples away,
+/// to time the decoder alone (`fomu bench`).
+pub fn decode_to_null(path: &Path) -> Result<()> {
+    let no_meta = Mutex::new(None);
+    decode_file(path, &mut Discard, &AtomicBool::new(false), &no_meta, false, Duration::ZERO)
+}
+
+/// Identifies one decode job, so a job that finishes after it was replaced
+/// can't be taken for the one playing now. Never 0.
+pub type DecodeToken = u64;
 
 /// Audio decoder for MP3 files.
 pub struct AudioDecoder {
@@ -27,6 +82,10 @@ pub struct AudioDecoder {
     should_stop: Arc<AtomicBool>,
     /// Decoder thread handle
     thread_handle: Option<thread::JoinHandle<()>>,
+    /// Tags of the file being decoded, filled in once it has been probed
+    meta: Arc<Mutex<Option<TrackMeta>>>,
+    /// Token of the last job started
+    generation: DecodeToken,
 }
 
 impl AudioDecoder {
@@ -35,38 +94,61 @@ impl AudioDecoder {
         Self {
             should_stop: Arc::new(AtomicBool::new(false)),
             thread_handle: None,
+            meta: Arc::new(Mutex::new(None)),
+            generation: 0,
         }
     }
 
     /// Start decoding a file in a background thread.
     ///
-    /// Samples are pushed to the provided ring buffer producer.
-    /// Optionally, samples are also pushed to an analysis buffer for visualization.
-    /// The decoder will signal `finished` when the file is complete.
+    /// Samples are written to `backend`. Returns the job's token, which the
+    /// decoder stores in `finished` when the file is complete. A stopped job
+    /// that exits late stores its own, older token.
     pub fn start(
         &mut self,
         path: &Path,
-        mut producer: ringbuf::HeapProd<f32>,
-        finished: Arc<AtomicBool>,
-        analysis_producer: Option<ringbuf::HeapProd<f32>>,
-    ) -> Result<()> {
+        backend: Box<dyn AudioBackend>,
+        finished: Arc<AtomicU64>,
+    ) -> Result<DecodeToken> {
+        self.start_at(path, backend, finished, Duration::ZERO)
+    }
+
+    /// Like `start`, but from `position` into the file. A file that can't
+    /// seek plays from the beginning instead.
+    pub fn start_at(
+        &mut self,
+        path: &Path,
+        mut backend: Box<dyn AudioBackend>,
+        finished: Arc<AtomicU64>,
+        position: Duration,
+    ) -> Result<DecodeToken> {
         // Stop any existing decode
         self.stop();
 
+        self.generation += 1;
+        let token = self.generation;
+
         let should_stop = Arc::new(AtomicBool::new(false));
         self.should_stop = Arc::clone(&should_stop);
 
+        // Fresh slot per decode so a detached old thread can't write stale tags
+        let meta = Arc::new(Mutex::new(None));
+        self.meta = Arc::clone(&meta);
+
         let path = path.to_path_buf();
+        log::debug!("Decoder starting: {:?}", path);
 
         let handle = thread::spawn(move || {
-            if let Err(e) = decode_file(&path, &mut producer, &should_stop, analysis_producer) {
-                eprintln!("Decoder error: {}", e);
+            let result = decode_file(&path, backend.as_mut(), &should_stop, &meta, false, position);
+            if let Err(e) = result {
+                log::error!("Decoder error for {:?}: {:#}", path, e);
             }
-            finished.store(true, Ordering::SeqCst);
+            log::debug!("Decoder finished: {:?}", path);
+            finished.store(token, Ordering::SeqCst);
         });
 
         self.thread_handle = Some(handle);
-        Ok(())
+        Ok(token)
     }
 
     /// Stop the current decode operation.
@@ -74,6 +156,7 @@ impl AudioDecoder {
         self.should_stop.store(true, Ordering::SeqCst);
 
         if let Some(handle) = self.thread_handle.take() {
+            log::debug!("Decoder stopping");
             // Wait for thread to finish with a reasonable timeout
             // If thread doesn't finish, just detach it (it will exit on its own)
             let start = std::time::Instant::now();
@@ -86,11 +169,18 @@ impl AudioDecoder {
             }
             if handle.is_finished() {
                 let _ = handle.join();
+            } else {
+                log::warn!("Decoder thread did not stop within 500ms, detaching");
             }
             // If not finished, the handle will be dropped and thread detached
         }
     }
 
+    /// Tags of the current file, if it has any.
+    pub fn meta(&self) -> Option<TrackMeta> {
+        self.meta.lock().unwrap().clone()
+    }
+
     /// Check if decoder is currently running.
     pub fn is_running(&self) -> bool {
         self.thread_handle
@@ -112,12 +202,79 @@ impl Drop for AudioDecoder {
     }
 }
 
-/// Decode an MP3 file and push samples to the ring buffer.
-fn decode_file(
+/// Probe an audio file for its duration without decoding it.
+///
+/// Returns `None` if the container doesn't report a frame count.
+pub fn probe_duration(path: &Path) -> Option<Duration> {
+    let file = File::open(path).ok()?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)?;
+
+    let n_frames = track.codec_params.n_frames?;
+    let sample_rate = track.codec_params.sample_rate?;
+    Some(Duration::from_secs_f64(n_frames as f64 / sample_rate as f64))
+}
+
+/// Check that `path` opens and its first packet decodes.
+pub fn check_decodable(path: &Path) -> Result<()> {
+    let file = File::open(path).context("Failed to open audio file")?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let mut probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .context("Unrecognized audio format")?;
+
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .context("No audio track found")?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context("Unsupported codec")?;
+
+    loop {
+        let packet = probed.format.next_packet().context("No audio packets")?;
+        if packet.track_id() == track_id {
+            decoder.decode(&packet).context("Failed to decode audio")?;
+            return Ok(());
+        }
+    }
+}
+
+/// Decode an MP3 file and write its samples to `backend`.
+///
+/// `gapless` trims the encoder's padding, so a file played back to back
+/// with itself loops without a click. Decoding starts `start_at` into the
+/// file, or at the beginning if seeking there fails.
+pub(crate) fn decode_file(
     path: &Path,
-    producer: &mut ringbuf::HeapProd<f32>,
+    backend: &mut dyn AudioBackend,
     should_stop: &AtomicBool,
-    mut analysis_producer: Option<ringbuf::HeapProd<f32>>,
+    meta: &Mutex<Option<TrackMeta>>,
+    gapless: bool,
+    start_at: Duration,
 ) -> Result<()> {
     let file = File::open(path).context("Failed to open audio file")?;
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
@@ -127,16 +284,31 @@ fn decode_file(
         hint.with_extension(ext);
     }
 
-    let format_opts = FormatOptions::default();
+    let format_opts = FormatOptions {
+        enable_gapless: gapless,
+        ..Default::default()
+    };
     let metadata_opts = MetadataOptions::default();
     let decoder_opts = DecoderOptions::default();
 
-    let probed = symphonia::default::get_probe()
+    let mut probed = symphonia::default::get_probe()
         .format(&hint, mss, &format_opts, &metadata_opts)
         .context("Failed to probe audio format")?;
 
     let mut format = probed.format;
 
+    // ID3v2 tags are read during probing; other containers report them
+    // through the format reader
+    let tags = probed
+        .metadata
+        .get()
+        .and_then(|m| m.current().and_then(TrackMeta::from_revision))
+        .or_else(|| format.metadata().current().and_then(TrackMeta::from_revision));
+    if let Some(tags) = &tags {
+        log::debug!("Tags for {:?}: {:?}", path, tags);
+    }
+    *meta.lock().unwrap() = tags;
+
     // Find the first audio track
     let track = format
         .tracks()
@@ -157,6 +329,17 @@ fn decode_file(
         .sample_rate
         .unwrap_or(SAMPLE_RATE);
 
+    if !start_at.is_zero() {
+        let to = SeekTo::Time {
+            time: Time::from(start_at.as_secs_f64()),
+            track_id: Some(track_id),
+        };
+        match format.seek(SeekMode::Accurate, to) {
+            Ok(_) => decoder.reset(),
+            Err(e) => log::warn!("Could not seek {:?} to {:?}, playing from the start: {}", path, start_at, e),
+        }
+    }
+
     // Decode packets
     loop {
         if should_stop.load(Ordering::Relaxed) {
@@ -172,7 +355,7 @@ fn decode_file(
                 break;
             }
             Err(e) => {
-                eprintln!("Packet read error: {}", e);
+                log::warn!("Packet read error: {}", e);
                 break;
             }
         };
@@ -186,29 +369,33 @@ fn decode_file(
         let decoded = match decoder.decode(&packet) {
             Ok(decoded) => decoded,
             Err(symphonia::core::errors::Error::DecodeError(e)) => {
-                eprintln!("Decode error: {}", e);
+                log::warn!("Decode error (skipping packet): {}", e);
                 continue;
             }
             Err(e) => {
-                eprintln!("Decode error: {}", e);
+                log::error!("Decode error: {}", e);
                 break;
             }
         };
 
-        // Convert to f32 samples and push to ring buffer
-        push_samples_to_buffer(decoded, producer, should_stop, source_sample_rate, &mut analysis_producer)?;
+        // Convert to f32 samples and write them to the backend
+        push_samples_to_buffer(
+            decoded,
+            backend,
+            should_stop,
+            source_sample_rate,
+        )?;
     }
 
     Ok(())
 }
 
-/// Convert decoded audio to f32 stereo and push to ring buffer.
+/// Convert decoded audio to f32 stereo and write it to the backend.
 fn push_samples_to_buffer(
     decoded: AudioBufferRef,
-    producer: &mut ringbuf::HeapProd<f32>,
+    backend: &mut dyn AudioBackend,
     should_stop: &AtomicBool,
     _source_sample_rate: u32,
-    analysis_producer: &mut Option<ringbuf::HeapProd<f32>>,
 ) -> Result<()> {
     // Convert to f32 samples
     let samples: Vec<f32> = match decoded {
@@ -268,6 +455,25 @@ fn push_samples_to_buffer(
             }
             output
         }
+        AudioBufferRef::S24(buf) => {
+            let channels = buf.spec().channels.count();
+            let frames = buf.frames();
+
+            let mut output = Vec::with_capacity(frames * 2);
+
+            // i24 holds the sample in the low 24 bits of an i32
+            for frame in 0..frames {
+                if channels == 1 {
+                    let sample = buf.chan(0)[frame].inner() as f32 / 8388608.0;
+                    output.push(sample);
+                    output.push(sample);
+                } else if channels >= 2 {
+                    output.push(buf.chan(0)[frame].inner() as f32 / 8388608.0);
+                    output.push(buf.chan(1)[frame].inner() as f32 / 8388608.0);
+                }
+            }
+            output
+        }
         AudioBufferRef::U8(buf) => {
             let channels = buf.spec().channels.count();
             let frames = buf.frames();
@@ -292,27 +498,21 @@ fn push_samples_to_buffer(
         }
     };
 
-    // Push samples to ring buffer with backpressure
+    // Write samples to the backend with backpressure
     let mut offset = 0;
     while offset < samples.len() {
         if should_stop.load(Ordering::Relaxed) {
             break;
         }
 
-        let written = producer.push_slice(&samples[offset..]);
+        let written = backend.write_samples(&samples[offset..]);
         offset += written;
 
         if written == 0 {
-            // Buffer is full, wait a bit for consumer to catch up
+            // Backend is full, wait a bit for playback to catch up
             thread::sleep(Duration::from_millis(5));
         }
     }
 
-    // Also push to analysis buffer (non-blocking, OK to drop samples)
-    if let Some(ref mut analysis) = analysis_producer {
-        // Just push what we can, don't wait - analysis is non-critical
-        let _ = analysis.push_slice(&samples);
-    }
-
     Ok(())
 }
@@ -1,7 +1,13 @@
 pub mod analyzer;
+pub mod backend;
 pub mod decoder;
 pub mod player;
+pub mod recorder;
+pub mod soundscape;
 
-pub use analyzer::AudioAnalyzer;
+pub use analyzer::{AnalyzerConfig, AudioAnalyzer};
+pub use backend::{AudioBackend, CpalBackend, NullBackend};
 pub use decoder::AudioDecoder;
 pub use player::AudioPlayer;
+pub use recorder::{Recorder, WavFormat};
+pub use soundscape::SoundscapeDecoder;
@@ -2,14 +2,23 @@
 //!
 //! This is the most critical module for audio stability. The audio callback
 //! MUST NEVER allocate, lock mutexes, or block in any way.
+//!
+//! With the null backend there's no device or callback: the decoder writes
+//! to a `NullBackend` that keeps time on its own.
 
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, SampleRate, Stream, StreamConfig};
-use ringbuf::{traits::*, HeapRb};
+use cpal::{Device, HostId, SampleFormat, SampleRate, Stream, StreamConfig, SupportedBufferSize};
+use crossbeam_channel::Sender;
+use ringbuf::{traits::*, HeapCons, HeapProd, HeapRb};
+
+use super::backend::{AudioBackend, CpalBackend, NullBackend, RecordSink, NULL_BACKEND};
+use super::decoder::DecodeToken;
 
 /// Atomic f32 for lock-free volume control.
 /// Stores f32 bits as u32 for atomic operations.
@@ -37,82 +46,608 @@ pub const SAMPLE_RATE: u32 = 44100;
 pub const CHANNELS: u16 = 2;
 pub const BUFFER_SIZE: u32 = 512;
 
-/// Audio player with real-time playback using cpal.
-pub struct AudioPlayer {
+/// Consecutive empty callbacks (about two seconds of silence) after which
+/// playback counts as stalled
+const STALL_CALLBACKS: u32 = SAMPLE_RATE * 2 / BUFFER_SIZE;
+
+/// Recorded samples the callback can queue, two seconds' worth, before
+/// the forwarding thread has to pick them up
+const RECORD_RING_SIZE: usize = SAMPLE_RATE as usize * CHANNELS as usize * 2;
+
+/// How often recorded samples are handed on to the recorder
+const RECORD_POLL: Duration = Duration::from_millis(50);
+
+/// How long `init_buffer` waits for the running callback to empty the
+/// ring before building a new stream instead
+const FLUSH_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Volume change for a key press or scroll step
+pub const VOLUME_STEP: f32 = 0.02;
+/// Volume change for Shift+Up/Down
+pub const VOLUME_STEP_LARGE: f32 = 0.1;
+
+/// Amplitude gain for a user-facing volume (0.0-1.0).
+///
+/// Loudness is heard roughly logarithmically, so a linear gain crams the
+/// quiet settings ambient listening uses into the bottom few percent. A
+/// cubic curve spreads them out: 50% is about -18 dB rather than -6 dB.
+///
+/// ```
+/// use fomu::audio::player::volume_gain;
+///
+/// assert_eq!(volume_gain(0.0), 0.0);
+/// assert_eq!(volume_gain(1.0), 1.0);
+/// let steps: Vec<f32> = (0..=50).map(|i| volume_gain(i as f32 / 50.0)).collect();
+/// assert!(steps.windows(2).all(|pair| pair[0] < pair[1]));
+/// ```
+pub fn volume_gain(volume: f32) -> f32 {
+    volume.clamp(0.0, 1.0).powi(3)
+}
+
+/// Level of a user-facing volume in dB relative to full scale;
+/// negative infinity at zero.
+pub fn volume_db(volume: f32) -> f32 {
+    20.0 * volume_gain(volume).log10()
+}
+
+/// How long the output takes to move between gains, so muting or a
+/// volume step doesn't click
+const GAIN_RAMP: Duration = Duration::from_millis(20);
+
+/// Snap to whole percent, so repeated steps don't drift to 79.99%.
+fn round_volume(volume: f32) -> f32 {
+    (volume * 100.0).round() / 100.0
+}
+
+/// Samples below this level pass through the limiter untouched
+const LIMITER_KNEE: f32 = 0.8;
+
+/// Soft-knee limiter so loud peaks at high volume bend instead of clipping.
+///
+/// Above the knee the curve `x - (x - 0.8)² / 0.4` flattens out, reaching
+/// its maximum of 0.9 at full scale; anything louder is held there. Safe
+/// for the audio callback: no allocation, only comparisons and arithmetic.
+#[inline]
+fn soft_limit(sample: f32) -> f32 {
+    let level = sample.abs();
+    if level <= LIMITER_KNEE {
+        return sample;
+    }
+    let level = level.min(1.0);
+    let over = level - LIMITER_KNEE;
+    (level - over * over / (2.0 * (1.0 - LIMITER_KNEE))).copysign(sample)
+}
+
+/// Whether the job in `decoding` is the one that stored its token in
+/// `finished`. Safe for the audio callback.
+#[inline]
+fn decode_finished(finished: &AtomicU64, decoding: &AtomicU64) -> bool {
+    let token = decoding.load(Ordering::Relaxed);
+    token != 0 && finished.load(Ordering::Relaxed) == token
+}
+
+/// What the output callback mixes from, apart from the stream so it can
+/// run without an audio device.
+struct Mixer {
+    consumer: HeapCons<f32>,
+    soundscape: Option<HeapCons<f32>>,
+    analysis: Option<HeapProd<f32>>,
+    gain: Arc<AtomicF32>,
+    muted: Arc<AtomicBool>,
+    /// Gain actually applied, moved toward the target a frame at a time
+    applied: f32,
+    ramp_step: f32,
+    paused: Arc<AtomicBool>,
+    flush: Arc<AtomicBool>,
+    played: Arc<AtomicU64>,
+    underruns: Arc<AtomicU64>,
+    starved_callbacks: Arc<AtomicU32>,
+    finished: Arc<AtomicU64>,
+    decoding: Arc<AtomicU64>,
+    soundscape_level: Arc<AtomicF32>,
+    soundscape_flush: Arc<AtomicBool>,
+    analysis_dropped: Arc<AtomicU64>,
+    /// What's heard, before the master volume, while `recording` is set
+    record: Option<HeapProd<f32>>,
+    recording: Arc<AtomicBool>,
+}
+
+impl Mixer {
+    /// Fill one output buffer. Runs in the real-time audio thread: no
+    /// allocation, locks or blocking.
+    fn fill(&mut self, output: &mut [f32]) {
+        let target = if self.muted.load(Ordering::Relaxed) { 0.0 } else { self.gain.load() };
+        let is_paused = self.paused.load(Ordering::Relaxed);
+
+        // Discard stale samples in place (no allocation)
+        if self.flush.load(Ordering::Relaxed) {
+            self.consumer.clear();
+            self.flush.store(false, Ordering::Release);
+        }
+
+        let level = self.soundscape_level.load();
+        let recording = self.recording.load(Ordering::Relaxed);
+        if let Some(soundscape) = self.soundscape.as_mut() {
+            if self.soundscape_flush.swap(false, Ordering::Relaxed) {
+                soundscape.clear();
+            }
+        }
+
+        let mut popped = 0;
+        for frame in output.chunks_mut(CHANNELS as usize) {
+            if is_paused {
+                frame.fill(0.0);
+                continue;
+            }
+            self.applied += (target - self.applied).clamp(-self.ramp_step, self.ramp_step);
+            let mut music = [0.0; CHANNELS as usize];
+            let mut mix = [0.0; CHANNELS as usize];
+            let mut whole = true;
+            for ((sample, music), mix) in frame.iter_mut().zip(music.iter_mut()).zip(mix.iter_mut()) {
+                let ambience = self.soundscape.as_mut().and_then(|c| c.try_pop()).unwrap_or(0.0);
+                match self.consumer.try_pop() {
+                    Some(s) => {
+                        popped += 1;
+                        *music = s;
+                    }
+                    None => whole = false,
+                }
+                *mix = *music + ambience * level;
+                *sample = soft_limit(*mix * self.applied);
+            }
+            // Recordings get the music and soundscape at full volume, so a
+            // quiet listening level doesn't make a quiet file
+            if let Some(record) = self.record.as_mut().filter(|_| recording) {
+                if record.vacant_len() >= mix.len() {
+                    mix.iter_mut().for_each(|s| *s = soft_limit(*s));
+                    record.push_slice(&mix);
+                }
+            }
+            // Tee what's played to the analyzer, whole frames only
+            // and never waiting on it
+            if let Some(analysis) = self.analysis.as_mut().filter(|_| whole) {
+                if analysis.vacant_len() >= music.len() {
+                    analysis.push_slice(&music);
+                } else {
+                    self.analysis_dropped.fetch_add(music.len() as u64, Ordering::Relaxed);
+                }
+            }
+        }
+        let total = self.played.fetch_add(popped, Ordering::Relaxed) + popped;
+
+        // Running dry after playback started but before the
+        // decoder finished is an underrun
+        let starved = !is_paused && popped < output.len() as u64;
+        let mid_track = total > 0 && !decode_finished(&self.finished, &self.decoding);
+        if starved && mid_track {
+            self.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+
+        // A run of completely silent callbacks means the decoder
+        // has stopped feeding us
+        if popped > 0 {
+            self.starved_callbacks.store(0, Ordering::Relaxed);
+        } else if !is_paused && mid_track {
+            self.starved_callbacks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Names of the backends usable on this machine: the cpal hosts, e.g.
+/// `ALSA` or `JACK`, then `null`.
+pub fn available_backends() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = cpal::available_hosts().iter().map(|id| id.name()).collect();
+    names.push(NULL_BACKEND);
+    names
+}
+
+/// Whether `backend` names the backend that discards audio.
+fn is_null(backend: Option<&str>) -> bool {
+    backend.is_some_and(|name| name.eq_ignore_ascii_case(NULL_BACKEND))
+}
+
+/// Find an available host by name, ignoring case.
+fn find_host(name: &str) -> Result<HostId> {
+    cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            // There's no PulseAudio host, but ALSA's pulse plugin reaches it
+            let hint = if name.to_ascii_lowercase().starts_with("pulse") {
+                " (PulseAudio plays through the ALSA backend)"
+            } else {
+                ""
+            };
+            anyhow::anyhow!(
+                "Unknown audio backend '{}'{}. Available backends: {}",
+                name,
+                hint,
+                available_backends().join(", ")
+            )
+        })
+}
+
+/// The default output device on the named backend, or the platform default.
+fn default_output(backend: Option<&str>) -> Result<(cpal::Host, Device)> {
+    let host = match backend {
+        Some(name) => cpal::host_from_id(find_host(name)?)?,
+        None => cpal::default_host(),
+    };
+    let device = host
+        .default_output_device()
+        .ok_or_else(|| anyhow::anyhow!("No output device available"))?;
+    Ok((host, device))
+}
+
+/// Name and host of the output device `AudioPlayer::new` would use.
+pub fn output_device_name(backend: Option<&str>) -> Result<String> {
+    if is_null(backend) {
+        return Ok("no output device (null)".to_string());
+    }
+    let (host, device) = default_output(backend)?;
+    let name = device.name().unwrap_or_else(|_| "<unknown>".to_string());
+    Ok(format!("{} ({})", name, host.id().name()))
+}
+
+/// Sample rates the default output device supports in stereo, as
+/// sorted `(min, max)` ranges.
+pub fn output_sample_rates(backend: Option<&str>) -> Result<Vec<(u32, u32)>> {
+    if is_null(backend) {
+        return Ok(vec![(SAMPLE_RATE, SAMPLE_RATE)]);
+    }
+    let (_, device) = default_output(backend)?;
+    let mut ranges: Vec<(u32, u32)> = device
+        .supported_output_configs()
+        .context("Failed to query output configs")?
+        .filter(|c| c.channels() == CHANNELS)
+        .map(|c| (c.min_sample_rate().0, c.max_sample_rate().0))
+        .collect();
+    ranges.sort_unstable();
+    ranges.dedup();
+    Ok(ranges)
+}
+
+/// Whether streams on `host` should ask for `BUFFER_SIZE`. WASAPI in
+/// shared mode runs at the engine's own period and fails to build a stream
+/// with a different fixed size, so it gets the default there.
+fn fixed_buffer_size(host: HostId) -> bool {
+    host.name() != "WASAPI"
+}
+
+/// Stream config used when the device doesn't advertise native f32 output.
+fn fallback_config(host: HostId) -> StreamConfig {
+    StreamConfig {
+        channels: CHANNELS,
+        sample_rate: SampleRate(SAMPLE_RATE),
+        buffer_size: if fixed_buffer_size(host) {
+            cpal::BufferSize::Fixed(BUFFER_SIZE)
+        } else {
+            cpal::BufferSize::Default
+        },
+    }
+}
+
+/// The cpal device a player outputs to.
+struct CpalOutput {
+    /// Audio host the device belongs to, reused when the stream is rebuilt
+    host_id: HostId,
     device: Device,
     config: StreamConfig,
+}
+
+/// Audio player with real-time playback using cpal.
+pub struct AudioPlayer {
+    /// Output device, or `None` for the null backend
+    output: Option<CpalOutput>,
     stream: Option<Stream>,
-    volume: Arc<AtomicF32>,
+    /// Current ring buffer, kept so the stream can be rebuilt around it
+    ring: Option<Arc<HeapRb<f32>>>,
+    /// Set by the stream error callback when the device fails
+    stream_error: Arc<AtomicBool>,
+    /// User-facing volume (0.0-1.0)
+    volume: AtomicF32,
+    /// Amplitude the callback scales by, `volume_gain(volume)`
+    gain: Arc<AtomicF32>,
+    /// Silences the output without touching the volume
+    muted: Arc<AtomicBool>,
     paused: Arc<AtomicBool>,
-    finished: Arc<AtomicBool>,
+    /// Token of the last decode job that reached the end of its file
+    finished: Arc<AtomicU64>,
+    /// Token of the decode job feeding the current buffer, 0 until it starts
+    decoding: Arc<AtomicU64>,
+    /// Set to request the audio callback discard all pending samples
+    flush: Arc<AtomicBool>,
+    /// Samples played from the current buffer
+    played: Arc<AtomicU64>,
+    /// Callbacks that ran out of samples mid-track
+    underruns: Arc<AtomicU64>,
+    /// Consecutive mid-track callbacks that got no samples at all
+    starved_callbacks: Arc<AtomicU32>,
+    /// Soundscape samples, mixed under the music for the whole session
+    soundscape_ring: Arc<HeapRb<f32>>,
+    /// Soundscape level relative to the music, before the master volume
+    soundscape_level: Arc<AtomicF32>,
+    /// Set to request the audio callback discard queued soundscape samples
+    soundscape_flush: Arc<AtomicBool>,
+    /// Music samples as the callback plays them, for the analyzer
+    analysis_ring: Arc<HeapRb<f32>>,
+    /// Microseconds from a callback until the end of its buffer is heard
+    output_latency: Arc<AtomicU64>,
+    /// Samples the analyzer missed because its buffer was full
+    analysis_dropped: Arc<AtomicU64>,
+    /// What the callback plays while recording, on its way to the recorder
+    record_ring: Arc<HeapRb<f32>>,
+    /// Set while the callback should feed `record_ring`
+    recording: Arc<AtomicBool>,
+    /// Thread moving `record_ring` to the recorder, and its stop flag
+    record_forwarder: Option<(Arc<AtomicBool>, thread::JoinHandle<()>)>,
+    /// The recorder the null backend sends to
+    record: RecordSink,
 }
 
 impl AudioPlayer {
-    /// Create a new audio player.
-    pub fn new() -> Result<Self> {
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .ok_or_else(|| anyhow::anyhow!("No output device available"))?;
-
-        let config = StreamConfig {
-            channels: CHANNELS,
-            sample_rate: SampleRate(SAMPLE_RATE),
-            buffer_size: cpal::BufferSize::Fixed(BUFFER_SIZE),
+    /// Create a new audio player on the named backend, or the platform default.
+    ///
+    /// The `null` backend opens no device, so it works without a sound card.
+    pub fn new(backend: Option<&str>) -> Result<Self> {
+        let output = if is_null(backend) {
+            log::info!("Using the null backend, audio is discarded");
+            None
+        } else {
+            let (host, device) = default_output(backend)?;
+            log::info!(
+                "Using output device: {} ({})",
+                device.name().unwrap_or_else(|_| "<unknown>".to_string()),
+                host.id().name()
+            );
+            Some(CpalOutput {
+                host_id: host.id(),
+                device,
+                config: fallback_config(host.id()),
+            })
         };
 
-        Ok(Self {
-            device,
-            config,
+        let mut player = Self {
+            output,
             stream: None,
-            volume: Arc::new(AtomicF32::new(0.8)),
+            ring: None,
+            stream_error: Arc::new(AtomicBool::new(false)),
+            volume: AtomicF32::new(0.8),
+            gain: Arc::new(AtomicF32::new(volume_gain(0.8))),
+            muted: Arc::new(AtomicBool::new(false)),
             paused: Arc::new(AtomicBool::new(false)),
-            finished: Arc::new(AtomicBool::new(false)),
-        })
+            finished: Arc::new(AtomicU64::new(0)),
+            decoding: Arc::new(AtomicU64::new(0)),
+            flush: Arc::new(AtomicBool::new(false)),
+            played: Arc::new(AtomicU64::new(0)),
+            underruns: Arc::new(AtomicU64::new(0)),
+            starved_callbacks: Arc::new(AtomicU32::new(0)),
+            soundscape_ring: Arc::new(HeapRb::new(RING_BUFFER_SIZE)),
+            soundscape_level: Arc::new(AtomicF32::new(0.5)),
+            soundscape_flush: Arc::new(AtomicBool::new(false)),
+            analysis_ring: Arc::new(HeapRb::new(RING_BUFFER_SIZE)),
+            output_latency: Arc::new(AtomicU64::new(0)),
+            analysis_dropped: Arc::new(AtomicU64::new(0)),
+            record_ring: Arc::new(HeapRb::new(RECORD_RING_SIZE)),
+            recording: Arc::new(AtomicBool::new(false)),
+            record_forwarder: None,
+            record: RecordSink::default(),
+        };
+        player.set_output_format();
+        Ok(player)
     }
 
-    /// Initialize the ring buffer and return the producer.
-    pub fn init_buffer(&mut self) -> ringbuf::HeapProd<f32> {
-        let ring = HeapRb::<f32>::new(RING_BUFFER_SIZE);
-        let (producer, consumer) = ring.split();
+    /// Pick the stream config for the current device.
+    ///
+    /// Prefers a config the device advertises as native 32-bit float at
+    /// SAMPLE_RATE, so the backend doesn't convert every sample on the way
+    /// out. Devices that don't list one get the hardcoded config.
+    pub fn set_output_format(&mut self) {
+        let Some(output) = self.output.as_mut() else {
+            return;
+        };
+        let native = output.device.supported_output_configs().ok().and_then(|mut configs| {
+            configs.find(|c| {
+                c.channels() == CHANNELS
+                    && c.sample_format() == SampleFormat::F32
+                    && c.min_sample_rate().0 <= SAMPLE_RATE
+                    && c.max_sample_rate().0 >= SAMPLE_RATE
+            })
+        });
+
+        output.config = match native {
+            Some(supported) => {
+                let buffer_size = match *supported.buffer_size() {
+                    _ if !fixed_buffer_size(output.host_id) => cpal::BufferSize::Default,
+                    SupportedBufferSize::Range { min, max } => {
+                        cpal::BufferSize::Fixed(BUFFER_SIZE.clamp(min, max))
+                    }
+                    SupportedBufferSize::Unknown => cpal::BufferSize::Fixed(BUFFER_SIZE),
+                };
+                log::info!("Device supports native f32 output at {} Hz", SAMPLE_RATE);
+                StreamConfig {
+                    buffer_size,
+                    ..supported.with_sample_rate(SampleRate(SAMPLE_RATE)).config()
+                }
+            }
+            None => {
+                log::info!("No native f32 output config found, using the default config");
+                fallback_config(output.host_id)
+            }
+        };
+    }
+
+    /// Start a new track's output and return the backend to decode into.
+    ///
+    /// The running stream is kept when it can be: its callback discards
+    /// whatever the last track left queued before the new producer is
+    /// handed out, so nothing stale plays after a switch, even one made
+    /// while paused. A stream that doesn't respond is replaced, which fails
+    /// if the device has gone away.
+    pub fn init_buffer(&mut self) -> Result<Box<dyn AudioBackend>> {
+        let reused = self.reuse_buffer();
 
-        self.finished.store(false, Ordering::SeqCst);
+        self.decoding.store(0, Ordering::SeqCst);
         self.paused.store(false, Ordering::SeqCst);
+        self.played.store(0, Ordering::SeqCst);
+        self.starved_callbacks.store(0, Ordering::SeqCst);
+        let Some(output) = &self.output else {
+            let paused = Arc::clone(&self.paused);
+            let played = Arc::clone(&self.played);
+            let record = Arc::clone(&self.record);
+            return Ok(Box::new(NullBackend::new(SAMPLE_RATE, CHANNELS, paused, played, record)));
+        };
+        let (sample_rate, channels) = (output.config.sample_rate.0, output.config.channels);
+        if let Some(producer) = reused {
+            return Ok(Box::new(CpalBackend::new(producer, sample_rate, channels)));
+        }
+
+        let ring = Arc::new(HeapRb::<f32>::new(RING_BUFFER_SIZE));
+        self.ring = Some(Arc::clone(&ring));
+        let (producer, consumer) = ring.split();
+
+        // Drop the old stream first so only one callback ever runs
+        self.stop();
+        self.start_stream(consumer)?;
+        Ok(Box::new(CpalBackend::new(producer, sample_rate, channels)))
+    }
+
+    /// Empty the current ring through the running stream and return a new
+    /// producer for it, or `None` if there's no stream, the old producer is
+    /// still held (a decoder that didn't stop in time), or the callback
+    /// didn't get to the flush within `FLUSH_TIMEOUT`.
+    fn reuse_buffer(&self) -> Option<HeapProd<f32>> {
+        let ring = self.ring.as_ref().filter(|ring| self.stream.is_some() && !ring.write_is_held())?;
+        self.flush_buffer();
+        let start = Instant::now();
+        while self.flush.load(Ordering::Acquire) {
+            if start.elapsed() > FLUSH_TIMEOUT {
+                log::debug!("Audio callback didn't flush within {:?}, starting a new stream", FLUSH_TIMEOUT);
+                self.flush.store(false, Ordering::SeqCst);
+                return None;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        Some(HeapProd::new(Arc::clone(ring)))
+    }
+
+    /// Rebuild the output stream on the current default device.
+    ///
+    /// Used when the old stream died (suspend, device unplugged). Queued
+    /// samples and the position counter carry over to the new stream.
+    /// Nothing to do for the null backend.
+    pub fn rebuild_stream(&mut self) -> Result<()> {
+        let Some(host_id) = self.output.as_ref().map(|output| output.host_id) else {
+            return Ok(());
+        };
+        self.stop();
+
+        let ring = self
+            .ring
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("No buffer to play"))?;
+        if ring.read_is_held() {
+            anyhow::bail!("Old audio stream still holds the buffer");
+        }
+
+        let device = cpal::host_from_id(host_id)?
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No output device available"))?;
+        log::info!(
+            "Rebuilding stream on output device: {}",
+            device.name().unwrap_or_else(|_| "<unknown>".to_string())
+        );
+        if let Some(output) = self.output.as_mut() {
+            output.device = device;
+        }
+        self.set_output_format();
 
-        self.start_stream(consumer);
-        producer
+        self.stream_error.store(false, Ordering::SeqCst);
+        self.start_stream(HeapCons::new(ring))
+    }
+
+    /// Check and clear the stream error flag.
+    pub fn take_stream_error(&self) -> bool {
+        self.stream_error.swap(false, Ordering::SeqCst)
     }
 
     /// Start the audio output stream.
-    fn start_stream(&mut self, mut consumer: ringbuf::HeapCons<f32>) {
-        let volume = Arc::clone(&self.volume);
-        let paused = Arc::clone(&self.paused);
+    fn start_stream(&mut self, consumer: HeapCons<f32>) -> Result<()> {
+        let output = self
+            .output
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("The null backend has no stream"))?;
+        let output_latency = Arc::clone(&self.output_latency);
+        let stream_error = Arc::clone(&self.stream_error);
+        let samples_per_sec = output.config.sample_rate.0 as u64 * output.config.channels as u64;
+        // Only one consumer may exist; the old stream has been dropped by now
+        let soundscape = if self.soundscape_ring.read_is_held() {
+            log::warn!("Old audio stream still holds the soundscape buffer");
+            None
+        } else {
+            Some(HeapCons::new(Arc::clone(&self.soundscape_ring)))
+        };
+        let analysis = if self.analysis_ring.write_is_held() {
+            log::warn!("Old audio stream still holds the analysis buffer");
+            None
+        } else {
+            Some(HeapProd::new(Arc::clone(&self.analysis_ring)))
+        };
+        let record = if self.record_ring.write_is_held() {
+            log::warn!("Old audio stream still holds the recording buffer");
+            None
+        } else {
+            Some(HeapProd::new(Arc::clone(&self.record_ring)))
+        };
+        let mut mixer = Mixer {
+            consumer,
+            soundscape,
+            analysis,
+            gain: Arc::clone(&self.gain),
+            muted: Arc::clone(&self.muted),
+            applied: if self.is_muted() { 0.0 } else { self.gain.load() },
+            ramp_step: 1.0 / (output.config.sample_rate.0 as f32 * GAIN_RAMP.as_secs_f32()),
+            paused: Arc::clone(&self.paused),
+            flush: Arc::clone(&self.flush),
+            played: Arc::clone(&self.played),
+            underruns: Arc::clone(&self.underruns),
+            starved_callbacks: Arc::clone(&self.starved_callbacks),
+            finished: Arc::clone(&self.finished),
+            decoding: Arc::clone(&self.decoding),
+            soundscape_level: Arc::clone(&self.soundscape_level),
+            soundscape_flush: Arc::clone(&self.soundscape_flush),
+            analysis_dropped: Arc::clone(&self.analysis_dropped),
+            record,
+            recording: Arc::clone(&self.recording),
+        };
 
         // CRITICAL: This callback runs in a real-time audio thread.
         // It MUST NEVER: allocate, lock mutexes, println!, panic, or block.
-        let stream = self
+        let stream = output
             .device
             .build_output_stream(
-                &self.config,
-                move |output: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                    let vol = volume.load();
-                    let is_paused = paused.load(Ordering::Relaxed);
-
-                    for sample in output.iter_mut() {
-                        if is_paused {
-                            *sample = 0.0;
-                        } else {
-                            *sample = consumer.try_pop().unwrap_or(0.0) * vol;
-                        }
+                &output.config,
+                move |output: &mut [f32], info: &cpal::OutputCallbackInfo| {
+                    // The device's own delay plus this buffer's length; not
+                    // every backend reports the former
+                    let timestamp = info.timestamp();
+                    if let Some(delay) = timestamp.playback.duration_since(&timestamp.callback) {
+                        let buffer = output.len() as u64 * 1_000_000 / samples_per_sec;
+                        output_latency.store(delay.as_micros() as u64 + buffer, Ordering::Relaxed);
                     }
+                    mixer.fill(output);
+                },
+                move |err| {
+                    log::error!("Audio stream error: {}", err);
+                    stream_error.store(true, Ordering::SeqCst);
                 },
-                |err| eprintln!("Audio stream error: {}", err),
                 None,
             )
-            .expect("Failed to build output stream");
+            .context("Failed to build output stream")?;
 
-        stream.play().expect("Failed to start audio stream");
+        stream.play().context("Failed to start audio stream")?;
         self.stream = Some(stream);
+        Ok(())
     }
 
     pub fn volume(&self) -> f32 {
@@ -120,17 +655,115 @@ impl AudioPlayer {
     }
 
     pub fn set_volume(&self, vol: f32) {
-        self.volume.store(vol.clamp(0.0, 1.0));
+        let vol = vol.clamp(0.0, 1.0);
+        self.volume.store(vol);
+        self.gain.store(volume_gain(vol));
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    /// Silence the output, or bring it back at the volume it had. The
+    /// change is ramped rather than instant.
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
+    /// The volume in dB relative to full scale.
+    pub fn volume_db(&self) -> f32 {
+        volume_db(self.volume())
+    }
+
+    /// Producer for the soundscape layer. There's only one per player;
+    /// later calls return `None`.
+    pub fn take_soundscape_producer(&self) -> Option<HeapProd<f32>> {
+        (!self.soundscape_ring.write_is_held()).then(|| HeapProd::new(Arc::clone(&self.soundscape_ring)))
+    }
+
+    /// Consumer of the music samples the output callback plays, before the
+    /// volume and soundscape are applied. Nothing is fed while paused, so
+    /// the analysis follows what's audible rather than the decoder.
+    pub fn take_analysis_consumer(&self) -> Option<HeapCons<f32>> {
+        (!self.analysis_ring.read_is_held()).then(|| HeapCons::new(Arc::clone(&self.analysis_ring)))
+    }
+
+    /// Samples the analyzer missed because it fell behind and its buffer
+    /// filled up.
+    pub fn analysis_dropped(&self) -> u64 {
+        self.analysis_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Send what plays, music and soundscape mixed, to `sink` as
+    /// interleaved stereo chunks, or stop with `None`. Samples recorded
+    /// before a stop are sent before it returns, so dropping the sink then
+    /// lets the recorder finish the file.
+    pub fn set_record_sink(&mut self, sink: Option<Sender<Vec<f32>>>) {
+        self.recording.store(false, Ordering::SeqCst);
+        if let Some((stop, handle)) = self.record_forwarder.take() {
+            stop.store(true, Ordering::SeqCst);
+            let _ = handle.join();
+        }
+        *self.record.lock().unwrap() = None;
+
+        let Some(sink) = sink else {
+            return;
+        };
+        if self.output.is_none() {
+            *self.record.lock().unwrap() = Some(sink);
+            return;
+        }
+        let mut consumer = HeapCons::new(Arc::clone(&self.record_ring));
+        // Whatever the callback recorded after the last stop
+        consumer.clear();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || forward_recording(consumer, sink, &thread_stop));
+        self.record_forwarder = Some((stop, handle));
+        self.recording.store(true, Ordering::SeqCst);
+    }
+
+    /// Samples queued in the playback buffer, and its capacity.
+    pub fn buffer_fill(&self) -> (usize, usize) {
+        match &self.ring {
+            Some(ring) => (ring.occupied_len(), ring.capacity().get()),
+            None => (0, RING_BUFFER_SIZE),
+        }
+    }
+
+    /// Samples waiting in the analysis buffer, and its capacity.
+    pub fn analysis_fill(&self) -> (usize, usize) {
+        (self.analysis_ring.occupied_len(), self.analysis_ring.capacity().get())
+    }
+
+    /// How long a sample handed to the analyzer takes to reach the speakers,
+    /// as last reported by the device. Zero until it reports one.
+    pub fn output_latency(&self) -> Duration {
+        Duration::from_micros(self.output_latency.load(Ordering::Relaxed))
     }
 
-    pub fn volume_up(&self) -> f32 {
-        let new_vol = (self.volume() + 0.05).min(1.0);
+    /// Flag that makes the audio callback drop queued soundscape samples.
+    pub fn soundscape_flush(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.soundscape_flush)
+    }
+
+    /// Soundscape level relative to the music (0.0-1.0).
+    pub fn soundscape_level(&self) -> f32 {
+        self.soundscape_level.load()
+    }
+
+    pub fn set_soundscape_level(&self, level: f32) {
+        self.soundscape_level.store(level.clamp(0.0, 1.0));
+    }
+
+    pub fn volume_up(&self, step: f32) -> f32 {
+        let new_vol = round_volume(self.volume() + step).min(1.0);
         self.set_volume(new_vol);
         new_vol
     }
 
-    pub fn volume_down(&self) -> f32 {
-        let new_vol = (self.volume() - 0.05).max(0.0);
+    pub fn volume_down(&self, step: f32) -> f32 {
+        let new_vol = round_volume(self.volume() - step).max(0.0);
         self.set_volume(new_vol);
         new_vol
     }
@@ -143,19 +776,56 @@ impl AudioPlayer {
         !self.is_paused()
     }
 
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
     pub fn toggle_pause(&self) -> bool {
         let was_paused = self.paused.fetch_xor(true, Ordering::SeqCst);
         !was_paused
     }
 
+    /// Discard all samples queued for playback.
+    ///
+    /// The drain happens on the next audio callback, paused or not, and the
+    /// flag clears once it has; `init_buffer` waits for that on a track
+    /// switch.
+    pub fn flush_buffer(&self) {
+        self.flush.store(true, Ordering::SeqCst);
+    }
+
+    /// Total number of buffer underruns since the player was created.
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Whether the output has been starved mid-track for about two seconds.
+    pub fn is_stalled(&self) -> bool {
+        self.starved_callbacks.load(Ordering::Relaxed) >= STALL_CALLBACKS
+    }
+
+    /// Playback position in the current track, based on samples actually output.
+    pub fn position(&self) -> Duration {
+        let frames = self.played.load(Ordering::Relaxed) / CHANNELS as u64;
+        Duration::from_secs_f64(frames as f64 / SAMPLE_RATE as f64)
+    }
+
+    /// Whether the decode job feeding the current buffer reached the end
+    /// of its file. A replaced job finishing late doesn't count.
     pub fn is_finished(&self) -> bool {
-        self.finished.load(Ordering::Relaxed)
+        decode_finished(&self.finished, &self.decoding)
     }
 
-    pub fn finished_flag(&self) -> Arc<AtomicBool> {
+    /// Where decode jobs store their token once they reach the end of the file.
+    pub fn finished_flag(&self) -> Arc<AtomicU64> {
         Arc::clone(&self.finished)
     }
 
+    /// Mark `token` as the decode job feeding the buffer from `init_buffer`.
+    pub fn set_decode_token(&self, token: DecodeToken) {
+        self.decoding.store(token, Ordering::SeqCst);
+    }
+
     pub fn stop(&mut self) {
         if let Some(stream) = self.stream.take() {
             drop(stream);
@@ -163,8 +833,188 @@ impl AudioPlayer {
     }
 }
 
+impl Drop for AudioPlayer {
+    fn drop(&mut self) {
+        // The forwarding thread holds the recorder's sink
+        self.set_record_sink(None);
+    }
+}
+
+/// Hand what the callback recorded to `sink` every `RECORD_POLL` until
+/// `stop` is set, then once more.
+fn forward_recording(mut consumer: HeapCons<f32>, sink: Sender<Vec<f32>>, stop: &AtomicBool) {
+    loop {
+        let stopping = stop.load(Ordering::SeqCst);
+        if !consumer.is_empty() && sink.send(consumer.pop_iter().collect()).is_err() {
+            return;
+        }
+        if stopping {
+            return;
+        }
+        thread::sleep(RECORD_POLL);
+    }
+}
+
 impl Default for AudioPlayer {
     fn default() -> Self {
-        Self::new().expect("Failed to create audio player")
+        Self::new(None).expect("Failed to create audio player")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mixer at full gain over `ring`, with no soundscape or analyzer.
+    fn mixer(ring: &Arc<HeapRb<f32>>) -> Mixer {
+        Mixer {
+            consumer: HeapCons::new(Arc::clone(ring)),
+            soundscape: None,
+            analysis: None,
+            gain: Arc::new(AtomicF32::new(1.0)),
+            muted: Arc::new(AtomicBool::new(false)),
+            applied: 1.0,
+            ramp_step: 1.0,
+            paused: Arc::new(AtomicBool::new(false)),
+            flush: Arc::new(AtomicBool::new(false)),
+            played: Arc::new(AtomicU64::new(0)),
+            underruns: Arc::new(AtomicU64::new(0)),
+            starved_callbacks: Arc::new(AtomicU32::new(0)),
+            finished: Arc::new(AtomicU64::new(0)),
+            decoding: Arc::new(AtomicU64::new(0)),
+            soundscape_level: Arc::new(AtomicF32::new(0.0)),
+            soundscape_flush: Arc::new(AtomicBool::new(false)),
+            analysis_dropped: Arc::new(AtomicU64::new(0)),
+            record: None,
+            recording: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn fill_ring(ring: &Arc<HeapRb<f32>>) {
+        let mut producer = HeapProd::new(Arc::clone(ring));
+        while producer.try_push(0.5).is_ok() {}
+    }
+
+    #[test]
+    fn queued_samples_play() {
+        let ring = Arc::new(HeapRb::new(1024));
+        let mut mixer = mixer(&ring);
+        fill_ring(&ring);
+
+        let mut output = [0.0; 64];
+        mixer.fill(&mut output);
+        assert!(output.iter().all(|&s| s > 0.0));
+        assert_eq!(mixer.played.load(Ordering::Relaxed), 64);
+    }
+
+    #[test]
+    fn flush_outputs_silence_and_empties_the_ring() {
+        let ring = Arc::new(HeapRb::new(1024));
+        let mut mixer = mixer(&ring);
+        fill_ring(&ring);
+
+        mixer.flush.store(true, Ordering::SeqCst);
+        let mut output = [1.0; 64];
+        mixer.fill(&mut output);
+
+        assert!(output.iter().all(|&s| s == 0.0));
+        assert!(ring.is_empty());
+        assert!(!mixer.flush.load(Ordering::SeqCst), "flag clears once the ring is empty");
+        assert_eq!(mixer.played.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn flush_while_paused_drops_the_backlog() {
+        let ring = Arc::new(HeapRb::new(1024));
+        let mut mixer = mixer(&ring);
+        fill_ring(&ring);
+
+        mixer.paused.store(true, Ordering::SeqCst);
+        mixer.flush.store(true, Ordering::SeqCst);
+        let mut output = [1.0; 64];
+        mixer.fill(&mut output);
+        assert!(output.iter().all(|&s| s == 0.0));
+
+        // Resuming plays nothing that was queued before the flush
+        mixer.paused.store(false, Ordering::SeqCst);
+        mixer.fill(&mut output);
+        assert!(output.iter().all(|&s| s == 0.0));
+        assert_eq!(mixer.played.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn recordings_get_the_soundscape_but_not_the_volume() {
+        let ring = Arc::new(HeapRb::new(1024));
+        let ambience = Arc::new(HeapRb::new(1024));
+        let record = Arc::new(HeapRb::new(1024));
+        let mut mixer = mixer(&ring);
+        mixer.soundscape = Some(HeapCons::new(Arc::clone(&ambience)));
+        mixer.soundscape_level.store(1.0);
+        mixer.record = Some(HeapProd::new(Arc::clone(&record)));
+        mixer.applied = 0.5;
+        mixer.gain.store(0.5);
+        let mut recorded = HeapCons::new(Arc::clone(&record));
+        let mut output = [0.0; 64];
+
+        // Nothing until recording starts
+        HeapProd::new(Arc::clone(&ambience)).push_slice(&[0.25; 64]);
+        mixer.fill(&mut output);
+        assert!(recorded.is_empty());
+
+        mixer.recording.store(true, Ordering::SeqCst);
+        HeapProd::new(Arc::clone(&ambience)).push_slice(&[0.25; 64]);
+        mixer.fill(&mut output);
+        let samples: Vec<f32> = recorded.pop_iter().collect();
+        assert_eq!(samples.len(), 64);
+        assert!(samples.iter().all(|&s| s == soft_limit(0.25)), "{:?}", samples);
+        assert!(output.iter().all(|&s| s == soft_limit(0.125)), "{:?}", output);
+
+        // Nor while paused
+        mixer.paused.store(true, Ordering::SeqCst);
+        mixer.fill(&mut output);
+        assert!(recorded.is_empty());
+    }
+
+    #[test]
+    fn null_backend_plays_without_a_device() {
+        assert!(available_backends().contains(&"null"));
+        let mut player = AudioPlayer::new(Some("NULL")).unwrap();
+        let mut backend = player.init_buffer().unwrap();
+        assert_eq!((backend.sample_rate(), backend.channels()), (SAMPLE_RATE, CHANNELS));
+
+        assert_eq!(backend.write_samples(&[0.5; 64]), 64);
+        assert_eq!(player.played.load(Ordering::Relaxed), 64);
+
+        let (sink, recorded) = crossbeam_channel::unbounded();
+        player.set_record_sink(Some(sink));
+        assert_eq!(backend.write_samples(&[0.5; 64]), 64);
+        player.set_record_sink(None);
+        assert_eq!(recorded.try_iter().flatten().count(), 64);
+        assert!(recorded.recv().is_err(), "the sink is dropped once recording stops");
+
+        player.set_paused(true);
+        assert_eq!(backend.write_samples(&[0.5; 64]), 0);
+        assert!(player.rebuild_stream().is_ok());
+    }
+
+    #[test]
+    fn pulse_points_to_alsa() {
+        let err = find_host("pulse").unwrap_err().to_string();
+        assert!(err.contains("plays through the ALSA backend"), "{}", err);
+        assert!(err.contains("null"), "{}", err);
+    }
+
+    #[test]
+    fn only_wasapi_leaves_the_buffer_size_to_the_host() {
+        for &host in cpal::ALL_HOSTS {
+            let fallback = fallback_config(host);
+            assert_eq!((fallback.channels, fallback.sample_rate), (CHANNELS, SampleRate(SAMPLE_RATE)));
+            let expected = if host.name() == "WASAPI" {
+                cpal::BufferSize::Default
+            } else {
+                cpal::BufferSize::Fixed(BUFFER_SIZE)
+            };
+            assert_eq!(fallback.buffer_size, expected, "{}", host.name());
+        }
     }
 }
@@ -0,0 +1,200 @@
+//! Records what plays to a WAV file, as 16-bit PCM or 32-bit float.
+//!
+//! The player sends copies of the music and soundscape as it mixes them;
+//! a writer thread encodes them and rewrites the header every few seconds,
+//! so a crash loses at most the last few seconds of the file.
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+
+use super::player::{CHANNELS, SAMPLE_RATE};
+
+/// How often the WAV header is brought up to date on disk
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How samples are stored in the WAV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WavFormat {
+    /// 16-bit integer PCM, which every player opens
+    #[default]
+    Pcm16,
+    /// 32-bit IEEE float, the decoder's samples unchanged
+    Float32,
+}
+
+impl WavFormat {
+    /// Bytes per interleaved sample
+    fn bytes_per_sample(self) -> u32 {
+        match self {
+            WavFormat::Pcm16 => 2,
+            WavFormat::Float32 => 4,
+        }
+    }
+
+    /// `wFormatTag` in the fmt chunk
+    fn format_tag(self) -> u16 {
+        match self {
+            WavFormat::Pcm16 => 1,
+            WavFormat::Float32 => 3,
+        }
+    }
+}
+
+/// A recording in progress.
+pub struct Recorder {
+    path: PathBuf,
+    /// Stereo frames written so far
+    frames: Arc<AtomicU64>,
+    handle: Option<thread::JoinHandle<Result<()>>>,
+}
+
+impl Recorder {
+    /// Create `path` and start the writer thread.
+    ///
+    /// Returns the recorder and the sender to feed interleaved stereo
+    /// samples into. The file is finished once every sender is dropped.
+    pub fn start(path: &Path, format: WavFormat) -> Result<(Self, Sender<Vec<f32>>)> {
+        let writer = WavWriter::create(path, format)?;
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let frames = Arc::new(AtomicU64::new(0));
+
+        let thread_frames = Arc::clone(&frames);
+        let handle = thread::spawn(move || write_loop(writer, receiver, &thread_frames));
+        log::info!("Recording to {:?}", path);
+
+        let recorder = Self {
+            path: path.to_path_buf(),
+            frames,
+            handle: Some(handle),
+        };
+        Ok((recorder, sender))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Length of audio recorded so far.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64(self.frames.load(Ordering::Relaxed) as f64 / SAMPLE_RATE as f64)
+    }
+
+    /// The write error, once the writer thread has given up.
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        match self.handle.take()?.join() {
+            Ok(result) => result.err(),
+            Err(_) => Some(anyhow::anyhow!("Recording thread panicked")),
+        }
+    }
+
+    /// Wait for the writer to finish the file. Drop the sender first.
+    pub fn finish(mut self) -> Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("Recording thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Write samples as they arrive, keeping the header current.
+fn write_loop(mut writer: WavWriter, receiver: Receiver<Vec<f32>>, frames: &AtomicU64) -> Result<()> {
+    let mut last_flush = Instant::now();
+    loop {
+        match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(samples) => {
+                writer.write_samples(&samples)?;
+                frames.fetch_add(samples.len() as u64 / CHANNELS as u64, Ordering::Relaxed);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if last_flush.elapsed() >= FLUSH_INTERVAL {
+            writer.update_header()?;
+            last_flush = Instant::now();
+        }
+    }
+    writer.update_header()
+}
+
+/// Minimal WAV writer.
+struct WavWriter {
+    file: BufWriter<File>,
+    format: WavFormat,
+    /// Bytes in the data chunk so far
+    data_len: u32,
+}
+
+impl WavWriter {
+    /// Create the file with a header for an empty data chunk.
+    fn create(path: &Path, format: WavFormat) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            format,
+            data_len: 0,
+        };
+
+        let channels = CHANNELS as u32;
+        let bytes_per_sample = format.bytes_per_sample();
+        let block_align = channels * bytes_per_sample;
+        let mut header = Vec::with_capacity(44);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&36u32.to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&format.format_tag().to_le_bytes());
+        header.extend_from_slice(&(CHANNELS).to_le_bytes());
+        header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        header.extend_from_slice(&(SAMPLE_RATE * block_align).to_le_bytes());
+        header.extend_from_slice(&(block_align as u16).to_le_bytes());
+        header.extend_from_slice(&((bytes_per_sample * 8) as u16).to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        writer.file.write_all(&header).context("Failed to write WAV header")?;
+        Ok(writer)
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> Result<()> {
+        let bytes = samples.len() as u32 * self.format.bytes_per_sample();
+        self.data_len = self
+            .data_len
+            .checked_add(bytes)
+            .filter(|len| *len <= u32::MAX - 36)
+            .ok_or_else(|| anyhow::anyhow!("WAV file size limit reached"))?;
+
+        for &sample in samples {
+            let sample = sample.clamp(-1.0, 1.0);
+            let written = match self.format {
+                WavFormat::Pcm16 => self.file.write_all(&((sample * i16::MAX as f32) as i16).to_le_bytes()),
+                WavFormat::Float32 => self.file.write_all(&sample.to_le_bytes()),
+            };
+            written.context("Failed to write recording")?;
+        }
+        Ok(())
+    }
+
+    /// Write the current sizes into the header and flush to disk.
+    fn update_header(&mut self) -> Result<()> {
+        let file = &mut self.file;
+        file.seek(SeekFrom::Start(4))?;
+        file.write_all(&(36 + self.data_len).to_le_bytes())?;
+        file.seek(SeekFrom::Start(40))?;
+        file.write_all(&self.data_len.to_le_bytes())?;
+        file.seek(SeekFrom::End(0))?;
+        file.flush().context("Failed to flush recording")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+//! Looping soundscape decoded under the music.
+//!
+//! One thread owns the player's soundscape producer for the whole session
+//! and decodes the chosen file over and over, so the layer keeps going
+//! through track changes and preset switches. Switching files or turning
+//! the layer off interrupts the current pass and flushes what's queued.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ringbuf::HeapProd;
+
+use super::backend::CpalBackend;
+use super::decoder::decode_file;
+use super::player::{CHANNELS, SAMPLE_RATE};
+
+/// A pass shorter than this means the file is too short (or empty) to loop
+const MIN_PASS: Duration = Duration::from_secs(1);
+
+/// How long a switch waits for the audio callback to drop old samples
+const FLUSH_WAIT: Duration = Duration::from_millis(50);
+
+/// Decodes the soundscape file in a background thread.
+pub struct SoundscapeDecoder {
+    commands: Sender<Option<PathBuf>>,
+    /// Set to cut the current pass short when a command is sent
+    interrupt: Arc<AtomicBool>,
+}
+
+impl SoundscapeDecoder {
+    /// Start the decoder thread feeding `producer`. `flush` is the
+    /// player's request to discard queued soundscape samples.
+    pub fn new(producer: HeapProd<f32>, flush: Arc<AtomicBool>) -> Self {
+        let (commands, rx) = mpsc::channel();
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let thread_interrupt = Arc::clone(&interrupt);
+        thread::Builder::new()
+            .name("soundscape".to_string())
+            .spawn(move || run(producer, rx, thread_interrupt, flush))
+            .expect("Failed to spawn soundscape thread");
+        Self { commands, interrupt }
+    }
+
+    /// Loop `path` under the music, or stop the layer with `None`.
+    pub fn play(&self, path: Option<PathBuf>) {
+        // Interrupt first: an idle thread woken by the command clears the
+        // flag in `switch_to`, and a busy one that stops before the command
+        // arrives waits for it rather than taking the pass as its last
+        self.interrupt.store(true, Ordering::SeqCst);
+        let _ = self.commands.send(path);
+    }
+}
+
+impl Drop for SoundscapeDecoder {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread after its current pass
+        self.interrupt.store(true, Ordering::SeqCst);
+    }
+}
+
+fn run(
+    producer: HeapProd<f32>,
+    commands: Receiver<Option<PathBuf>>,
+    interrupt: Arc<AtomicBool>,
+    flush: Arc<AtomicBool>,
+) {
+    let mut backend = CpalBackend::new(producer, SAMPLE_RATE, CHANNELS);
+    let no_meta = Mutex::new(None);
+    let mut current: Option<PathBuf> = None;
+
+    loop {
+        let Some(path) = current.clone() else {
+            // Idle until there's something to play
+            match commands.recv() {
+                Ok(next) => {
+                    current = switch_to(next, &interrupt, &flush);
+                    continue;
+                }
+                Err(_) => return,
+            }
+        };
+
+        let started = Instant::now();
+        let result = decode_file(&path, &mut backend, &interrupt, &no_meta, true, Duration::ZERO);
+
+        // Take the latest command, if any; otherwise the pass ended and loops
+        let mut next = None;
+        loop {
+            match commands.try_recv() {
+                Ok(command) => next = Some(command),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+        if next.is_none() && interrupt.load(Ordering::SeqCst) {
+            // Interrupted with nothing queued: the decoder is being dropped
+            match commands.recv() {
+                Ok(command) => next = Some(command),
+                Err(_) => return,
+            }
+        }
+        if let Some(next) = next {
+            current = switch_to(next, &interrupt, &flush);
+            continue;
+        }
+
+        if let Err(e) = result {
+            log::error!("Soundscape error for {:?}: {:#}", path, e);
+            current = None;
+        } else if started.elapsed() < MIN_PASS {
+            log::warn!("Soundscape {:?} is too short to loop, stopping", path);
+            current = None;
+        }
+    }
+}
+
+/// Drop queued samples of the old file before the next one starts.
+fn switch_to(next: Option<PathBuf>, interrupt: &AtomicBool, flush: &AtomicBool) -> Option<PathBuf> {
+    log::debug!("Soundscape switching to {:?}", next);
+    interrupt.store(false, Ordering::SeqCst);
+    flush.store(true, Ordering::SeqCst);
+    // The callback clears the flag once it has flushed; a stopped stream
+    // never will, so don't wait on it for long
+    let start = Instant::now();
+    while flush.load(Ordering::SeqCst) && start.elapsed() < FLUSH_WAIT {
+        thread::sleep(Duration::from_millis(5));
+    }
+    next
+}
@@ -0,0 +1,57 @@
+//! `fomu bench`: how fast this machine decodes a track.
+//!
+//! Decodes the file straight to a sink that discards the samples, so
+//! neither the audio device nor the playback buffer's pacing is involved.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use fomu::audio::decoder::{decode_to_null, probe_duration};
+
+/// Decode `path` `iterations` times and print the time, throughput and
+/// speed relative to playback for each run, then the best run.
+pub fn run(path: &Path, iterations: u32) -> Result<()> {
+    let bytes = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read {:?}", path))?
+        .len();
+    let length = probe_duration(path).with_context(|| format!("{:?} isn't a playable audio file", path))?;
+
+    println!(
+        "{}: {:.1} MB, {}:{:02} of audio",
+        path.display(),
+        megabytes(bytes),
+        length.as_secs() / 60,
+        length.as_secs() % 60
+    );
+
+    let mut best = Duration::MAX;
+    for i in 1..=iterations {
+        let started = Instant::now();
+        decode_to_null(path)?;
+        let took = started.elapsed();
+        best = best.min(took);
+        println!("  run {}: {}", i, describe(took, bytes, length));
+    }
+    println!("  best:  {}", describe(best, bytes, length));
+    Ok(())
+}
+
+/// Decode time, MB/s and real-time ratio for one run.
+fn describe(took: Duration, bytes: u64, length: Duration) -> String {
+    let secs = took.as_secs_f64().max(1e-6);
+    let ratio = length.as_secs_f64() / secs;
+    let pace = if ratio >= 1.0 { "faster" } else { "slower" };
+    format!(
+        "{:.3}s, {:.1} MB/s, {:.1}x real-time ({} than playback)",
+        secs,
+        megabytes(bytes) / secs,
+        ratio,
+        pace
+    )
+}
+
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
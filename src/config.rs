@@ -0,0 +1,372 @@
+//! Config file at `~/.config/fomu/config.toml`.
+//!
+//! Keys are the long option names with `_` for `-`, and only options that
+//! have a `FOMU_*` variable can be set. Values are handed to clap through
+//! those variables, so a flag or an exported variable still wins and the
+//! file is parsed exactly like the command line. `--generate-config` walks
+//! the same clap definitions, so the template can't drift from the parser.
+//! The `[keys]` section holds key bindings, see `keys`, and
+//! `[presets.<name>]` tables give a preset its own `volume`,
+//! `visualizer` and pool weights. `[[schedule]]` entries replace the
+//! times of day `--preset auto` follows.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::NaiveTime;
+use clap::{Arg, ArgAction, Command};
+use directories::ProjectDirs;
+use serde::Deserialize;
+
+use crate::presets::{self, PresetSettings, ScheduleEntry, CUSTOM_PRESET, PRESETS};
+use crate::tracks::loader::write_atomic;
+use crate::tracks::TrackPool;
+use crate::ui::visualizers::VisualizerStyle;
+
+/// Config file location, overridable with FOMU_CONFIG.
+pub fn default_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("FOMU_CONFIG").filter(|path| !path.is_empty()) {
+        PathBuf::from(path)
+    } else if let Some(proj_dirs) = ProjectDirs::from("", "", "fomu") {
+        proj_dirs.config_dir().join("config.toml")
+    } else {
+        let home = std::env::var("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("."));
+        home.join(".config").join("fomu").join("config.toml")
+    }
+}
+
+/// Options of `cmd` that can be set from the config file, with their keys.
+fn config_args(cmd: &Command) -> impl Iterator<Item = (String, &Arg)> {
+    cmd.get_arguments().filter_map(|arg| {
+        arg.get_env()?;
+        Some((arg.get_long()?.replace('-', "_"), arg))
+    })
+}
+
+/// Apply the config file at `path` to options not already set in the
+/// environment, and return its `[keys]` table. A missing file is fine;
+/// unknown keys are an error.
+pub fn load(cmd: &Command, path: &Path) -> Result<toml::Table> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(toml::Table::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let mut table: toml::Table =
+        toml::from_str(&text).with_context(|| format!("Invalid config file {}", path.display()))?;
+    let keys = match table.remove("keys") {
+        Some(toml::Value::Table(keys)) => keys,
+        Some(_) => anyhow::bail!("[keys] in {} must be a table", path.display()),
+        None => toml::Table::new(),
+    };
+    if let Some(value) = table.remove("presets") {
+        let settings = preset_settings(value).with_context(|| format!("Invalid [presets] in {}", path.display()))?;
+        presets::register_preset_settings(settings);
+    }
+    if let Some(value) = table.remove("schedule") {
+        let entries = schedule(value).with_context(|| format!("Invalid [[schedule]] in {}", path.display()))?;
+        presets::register_schedule(entries);
+    }
+
+    for (key, value) in table {
+        let Some((_, arg)) = config_args(cmd).find(|(name, _)| *name == key) else {
+            anyhow::bail!(
+                "Unknown option '{}' in {} (run `fomu --generate-config` to list them)",
+                key,
+                path.display()
+            );
+        };
+        let value = match value {
+            toml::Value::String(s) => s,
+            toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                value.to_string()
+            }
+            other => anyhow::bail!(
+                "Option '{}' in {} must be a string, number or boolean, not {}",
+                key,
+                path.display(),
+                other.type_str()
+            ),
+        };
+        let var = arg.get_env().expect("config args have an env variable");
+        if std::env::var_os(var).is_none() {
+            std::env::set_var(var, value);
+        }
+    }
+    Ok(keys)
+}
+
+/// Set the top-level `key` to `value` in the config file at `path`,
+/// creating the file if needed and leaving every other line as written.
+pub fn set_value(path: &Path, key: &str, value: toml::Value) -> Result<()> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let line = format!("{} = {}", key, value);
+
+    // Top-level keys must come before the first table header
+    let tables = lines
+        .iter()
+        .position(|l| l.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+    let existing = lines[..tables].iter().position(|l| {
+        l.trim_start()
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    });
+    match existing {
+        Some(i) => lines[i] = line,
+        None => {
+            // After the last top-level setting, or else after the comment
+            // block opening the file, so commented-out tables stay below
+            let active = |l: &String| !l.trim().is_empty() && !l.trim_start().starts_with('#');
+            let at = match lines[..tables].iter().rposition(active) {
+                Some(last) => last + 1,
+                None => lines[..tables]
+                    .iter()
+                    .position(|l| !l.trim_start().starts_with('#'))
+                    .unwrap_or(tables),
+            };
+            lines.insert(at, line);
+        }
+    }
+
+    write_atomic(path, lines.join("\n") + "\n")
+}
+
+/// Parse and check the `[presets.<name>]` tables.
+fn preset_settings(value: toml::Value) -> Result<Vec<(String, PresetSettings)>> {
+    let toml::Value::Table(table) = value else {
+        anyhow::bail!("[presets] must hold one table per preset");
+    };
+    let mut settings = Vec::new();
+    for (name, value) in table {
+        if !PRESETS.iter().chain([&CUSTOM_PRESET]).any(|p| p.name == name) {
+            anyhow::bail!("Unknown preset '{}'", name);
+        }
+        let preset: PresetSettings = value.try_into().with_context(|| format!("[presets.{}]", name))?;
+        if let Some(volume) = preset.volume {
+            if !(0.0..=1.0).contains(&volume) {
+                anyhow::bail!("[presets.{}] volume must be between 0.0 and 1.0", name);
+            }
+        }
+        if let Some(style) = &preset.visualizer {
+            if VisualizerStyle::from_name(style).is_none() {
+                anyhow::bail!(
+                    "[presets.{}] unknown visualizer '{}' (use {})",
+                    name,
+                    style,
+                    VisualizerStyle::NAMES.join(", ")
+                );
+            }
+        }
+        if let Some(pools) = &preset.pools {
+            if pools.is_empty() {
+                anyhow::bail!("[presets.{}] pools must list at least one pool", name);
+            }
+            if let Some(entry) = pools.iter().find(|p| p.pool == TrackPool::Soundscape) {
+                anyhow::bail!("[presets.{}] pool {} can't be played as music", name, entry.pool);
+            }
+            if pools.iter().any(|p| !p.weight.is_finite() || p.weight < 0.0) {
+                anyhow::bail!("[presets.{}] pool weights must be 0 or more", name);
+            }
+            if pools.iter().all(|p| p.weight == 0.0) {
+                anyhow::bail!("[presets.{}] at least one pool needs a weight above 0", name);
+            }
+        }
+        settings.push((name, preset));
+    }
+    Ok(settings)
+}
+
+/// One `[[schedule]]` entry as written in the file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleValue {
+    from: String,
+    preset: String,
+}
+
+/// Parse and check the `[[schedule]]` entries.
+fn schedule(value: toml::Value) -> Result<Vec<ScheduleEntry>> {
+    let values: Vec<ScheduleValue> = value.try_into()?;
+    if values.is_empty() {
+        anyhow::bail!("the schedule needs at least one entry");
+    }
+    let mut entries: Vec<ScheduleEntry> = Vec::new();
+    for value in values {
+        let from = NaiveTime::parse_from_str(&value.from, "%H:%M")
+            .with_context(|| format!("'{}' is not a time like \"06:00\"", value.from))?;
+        let Some(preset) = PRESETS.iter().chain([&CUSTOM_PRESET]).find(|p| p.name == value.preset) else {
+            anyhow::bail!("Unknown preset '{}'", value.preset);
+        };
+        if entries.iter().any(|e| e.from == from) {
+            anyhow::bail!("two entries start at {}", value.from);
+        }
+        entries.push(ScheduleEntry {
+            from,
+            preset: preset.name,
+        });
+    }
+    Ok(entries)
+}
+
+/// A commented config file listing every option with its default.
+pub fn generate(cmd: &Command) -> String {
+    let mut out = String::from(
+        "# fomu config file\n\
+         #\n\
+         # Uncomment a line to change that option. Command-line flags and\n\
+         # FOMU_* environment variables take precedence over this file.\n",
+    );
+
+    for (key, arg) in config_args(cmd) {
+        out.push('\n');
+        let help = arg.get_long_help().or(arg.get_help()).map(|h| h.to_string());
+        for line in help.iter().flat_map(|h| h.lines()) {
+            out.push_str(&format!("# {}\n", line.trim()).replace("# \n", "#\n"));
+        }
+        out.push_str(&format!("# {} = {}\n", key, example_value(arg)));
+    }
+    out.push_str(
+        "\n# A preset can switch to its own volume and visualizer (bars, meter\n\
+         # or radial). Switching to a preset without a volume restores the\n\
+         # one you had before. `pools` replaces the preset's pools; a pool\n\
+         # with weight 3 comes up three times as often as one with weight 1.\n\
+         # [presets.relax]\n\
+         # volume = 0.4\n\
+         # visualizer = \"radial\"\n\
+         # [presets.focus]\n\
+         # pools = [{ pool = \"CalmFocus\", weight = 3 }, { pool = \"Atmospheric\", weight = 1 }]\n\
+         \n\
+         # `--preset auto` picks the preset from the time of day. Each entry\n\
+         # runs until the next one; the last wraps past midnight. These\n\
+         # replace the built-in schedule below.\n\
+         # [[schedule]]\n\
+         # from = \"06:00\"\n\
+         # preset = \"morning\"\n\
+         # [[schedule]]\n\
+         # from = \"11:00\"\n\
+         # preset = \"focus\"\n\
+         # [[schedule]]\n\
+         # from = \"21:00\"\n\
+         # preset = \"relax\"\n",
+    );
+    out.push_str(&crate::keys::template());
+    out
+}
+
+/// The default of `arg` as a TOML value, or a placeholder if it has none.
+fn example_value(arg: &Arg) -> String {
+    if matches!(arg.get_action(), ArgAction::SetTrue) {
+        return "false".to_string();
+    }
+    match arg.get_default_values().first() {
+        Some(default) => {
+            let default = default.to_string_lossy();
+            if default.parse::<f64>().is_ok() {
+                default.into_owned()
+            } else {
+                format!("{:?}", default)
+            }
+        }
+        None => {
+            let name = arg
+                .get_value_names()
+                .and_then(|names| names.first())
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| arg.get_id().to_string().to_uppercase());
+            format!("\"<{}>\"", name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Vec<(String, PresetSettings)>> {
+        let table: toml::Table = toml::from_str(text).unwrap();
+        preset_settings(table["presets"].clone())
+    }
+
+    #[test]
+    fn preset_pools_with_weights() {
+        let settings = parse(
+            r#"
+            [presets.focus]
+            pools = [{ pool = "CalmFocus", weight = 3 }, { pool = "Atmospheric" }]
+            "#,
+        )
+        .unwrap();
+        let pools = settings[0].1.pools.as_ref().unwrap();
+        assert_eq!(pools[0].pool, TrackPool::CalmFocus);
+        assert_eq!(pools[0].weight, 3.0);
+        assert_eq!(pools[1].pool, TrackPool::Atmospheric);
+        assert_eq!(pools[1].weight, 1.0);
+    }
+
+    #[test]
+    fn bad_preset_pools_are_rejected() {
+        for pools in [
+            "[]",
+            r#"[{ pool = "Rain" }]"#,
+            r#"[{ pool = "Soundscape" }]"#,
+            r#"[{ pool = "CalmFocus", weight = -1 }]"#,
+            r#"[{ pool = "CalmFocus", weight = 0 }]"#,
+            r#"[{ pool = "CalmFocus", volume = 1 }]"#,
+        ] {
+            let text = format!("[presets.focus]\npools = {}", pools);
+            assert!(parse(&text).is_err(), "accepted pools = {}", pools);
+        }
+    }
+
+    fn parse_schedule(text: &str) -> Result<Vec<ScheduleEntry>> {
+        let mut table: toml::Table = toml::from_str(text).unwrap();
+        schedule(table.remove("schedule").unwrap())
+    }
+
+    #[test]
+    fn schedule_entries() {
+        let entries = parse_schedule(
+            r#"
+            [[schedule]]
+            from = "07:30"
+            preset = "morning"
+            [[schedule]]
+            from = "22:00"
+            preset = "relax"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].from, NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+        assert_eq!(entries[0].preset, "morning");
+        assert_eq!(entries[1].preset, "relax");
+
+        let inline = parse_schedule(r#"schedule = [{ from = "06:00", preset = "morning" }]"#).unwrap();
+        assert_eq!(inline[0].preset, "morning");
+    }
+
+    #[test]
+    fn bad_schedules_are_rejected() {
+        for text in [
+            "schedule = []",
+            r#"schedule = [{ from = "6am", preset = "morning" }]"#,
+            r#"schedule = [{ from = "25:00", preset = "morning" }]"#,
+            r#"schedule = [{ from = "06:00", preset = "auto" }]"#,
+            r#"schedule = [{ from = "06:00", preset = "nope" }]"#,
+            r#"schedule = [{ from = "06:00" }]"#,
+            r#"schedule = [{ from = "06:00", preset = "morning", volume = 1 }]"#,
+            r#"schedule = [{ from = "06:00", preset = "morning" }, { from = "06:00", preset = "focus" }]"#,
+            r#"schedule = { from = "06:00", preset = "morning" }"#,
+        ] {
+            assert!(parse_schedule(text).is_err(), "accepted {}", text);
+        }
+    }
+}
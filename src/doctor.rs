@@ -0,0 +1,293 @@
+//! `fomu --doctor`: checks for the usual reasons fomu can't play.
+
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use crossterm::style::Stylize;
+
+use fomu::audio;
+use fomu::tracks::{self, catalog::TRACK_CATALOG, TrackLoader, TrackPool, TrackSizes};
+
+/// How long the catalog host gets to answer
+const REACH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a local check may take before it counts as hung
+const LOCAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Decoding every downloaded track takes a while on slow disks
+const TRACKS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Free space below this fails when the catalog's size isn't known yet
+const MIN_FREE_SPACE: u64 = 200 * 1024 * 1024;
+
+/// One check, run on its own thread so a hung device or resolver can't
+/// hang the whole report.
+struct Check {
+    name: &'static str,
+    /// What to try when the check fails
+    hint: &'static str,
+    /// Whether fomu can't play without it
+    required: bool,
+    timeout: Duration,
+    run: Box<dyn FnOnce() -> Result<String> + Send>,
+}
+
+/// Run every check and print the results. Returns whether all required
+/// checks passed. `config_error` is why the config file failed to load.
+pub fn run(tracks_dir: Option<PathBuf>, backend: Option<String>, config_error: Option<anyhow::Error>) -> bool {
+    let checks = [
+        Check {
+            name: "Audio output",
+            hint: "Check that an output device is connected and not held by another program; \
+                   `fomu --backend list` shows other audio backends",
+            required: true,
+            timeout: LOCAL_TIMEOUT,
+            run: Box::new(move || check_audio(backend.as_deref())),
+        },
+        Check {
+            name: "Tracks directory",
+            hint: "Free up space, or point --tracks-dir (FOMU_TRACKS_DIR) at a writable directory",
+            required: true,
+            timeout: LOCAL_TIMEOUT,
+            run: Box::new(move || check_tracks_dir(tracks_dir)),
+        },
+        Check {
+            name: "Track server",
+            hint: "Check your connection; behind a proxy pass --proxy (or set HTTPS_PROXY), \
+                   and --ca-cert if it inspects TLS",
+            required: true,
+            timeout: REACH_TIMEOUT + LOCAL_TIMEOUT,
+            run: Box::new(check_server),
+        },
+        Check {
+            name: "Downloaded tracks",
+            hint: "Run `fomu --verify-cache --repair` to download broken tracks again",
+            required: true,
+            timeout: TRACKS_TIMEOUT,
+            run: Box::new(check_tracks),
+        },
+        Check {
+            name: "Config file",
+            hint: "Fix or remove the file; `fomu --generate-config` lists the valid options",
+            required: true,
+            timeout: LOCAL_TIMEOUT,
+            run: Box::new(move || check_config(config_error)),
+        },
+        Check {
+            name: "Terminal",
+            hint: "Use a UTF-8 locale (e.g. LANG=en_US.UTF-8) and a color terminal; \
+                   fomu still plays without them",
+            required: false,
+            timeout: LOCAL_TIMEOUT,
+            run: Box::new(check_terminal),
+        },
+    ];
+
+    let color = std::io::stdout().is_terminal();
+    let paint = |mark: &str, ok: bool, required: bool| match (color, ok, required) {
+        (false, ..) => mark.to_string(),
+        (true, true, _) => mark.green().to_string(),
+        (true, false, true) => mark.red().to_string(),
+        (true, false, false) => mark.yellow().to_string(),
+    };
+
+    let mut all_passed = true;
+    for check in checks {
+        let (name, hint, required) = (check.name, check.hint, check.required);
+        match run_with_timeout(check) {
+            Ok(detail) => println!("{} {:<18} {}", paint("✓", true, required), name, detail),
+            Err(e) => {
+                all_passed &= !required;
+                println!("{} {:<18} {:#}", paint("✗", false, required), name, e);
+                println!("  {:<18} {}", "", hint);
+            }
+        }
+    }
+    all_passed
+}
+
+/// Run `check` on a thread and give up on it after its timeout. A hung
+/// thread is left behind; the process exits right after the report.
+fn run_with_timeout(check: Check) -> Result<String> {
+    let (tx, rx) = mpsc::channel();
+    let run = check.run;
+    thread::Builder::new()
+        .name(format!("doctor: {}", check.name))
+        .spawn(move || {
+            let _ = tx.send(run());
+        })
+        .context("Failed to spawn check")?;
+    match rx.recv_timeout(check.timeout) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            Err(anyhow!("no answer within {}s", check.timeout.as_secs()))
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(anyhow!("check crashed")),
+    }
+}
+
+/// Output device, its host, and the stereo sample rates it supports.
+fn check_audio(backend: Option<&str>) -> Result<String> {
+    let device = audio::player::output_device_name(backend)?;
+    let ranges = audio::player::output_sample_rates(backend)?;
+    if ranges.is_empty() {
+        return Err(anyhow!("{} has no stereo output", device));
+    }
+    let rates: Vec<String> = ranges
+        .iter()
+        .map(|&(min, max)| if min == max { format!("{}", min) } else { format!("{}-{}", min, max) })
+        .collect();
+    Ok(format!("{}, {} Hz", device, rates.join(", ")))
+}
+
+fn check_tracks_dir(dir: Option<PathBuf>) -> Result<String> {
+    match dir {
+        Some(dir) => tracks::loader::set_tracks_dir(dir)?,
+        None => tracks::loader::check_tracks_dir()?,
+    }
+    let dir = tracks::loader::get_tracks_dir();
+    let Some(free) = free_space(&dir) else {
+        return Ok(format!("{} is writable", dir.display()));
+    };
+
+    // What the rest of the catalog needs, if HEAD requests have told us;
+    // soundscapes only download when picked
+    let loader = TrackLoader::new();
+    let sizes = TrackSizes::load(&TrackSizes::default_path());
+    let needed = sizes.total(
+        tracks::catalog::all_tracks()
+            .filter(|track| track.pool != TrackPool::Soundscape && !loader.track_exists(track)),
+    );
+    match needed {
+        Some(needed) if free < needed => Err(anyhow!(
+            "{} has {} free, but the tracks not downloaded yet need {}",
+            dir.display(),
+            format_mb(free),
+            format_mb(needed)
+        )),
+        None if free < MIN_FREE_SPACE => Err(anyhow!("{} has only {} free", dir.display(), format_mb(free))),
+        _ => Ok(format!("{} is writable, {} free", dir.display(), format_mb(free))),
+    }
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `dir`.
+#[cfg(unix)]
+fn free_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `path` is NUL-terminated and `stat` is a plain C struct that
+    // statvfs fills in
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The field types differ between platforms
+    #[allow(clippy::unnecessary_cast, clippy::useless_conversion)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+fn format_mb(bytes: u64) -> String {
+    format!("{} MB", bytes.div_ceil(1024 * 1024))
+}
+
+/// HEAD the first catalog track, through the same proxy settings as downloads.
+fn check_server() -> Result<String> {
+    let url = TRACK_CATALOG
+        .first()
+        .map(|track| track.resolved_url())
+        .ok_or_else(|| anyhow!("Track catalog is empty"))?;
+    let client = tracks::downloader::http_client()?;
+    let response = client
+        .head(&url)
+        .timeout(REACH_TIMEOUT)
+        .send()
+        .map_err(|e| tracks::downloader::request_error(e, &url))?;
+    tracks::downloader::check_status(&response).with_context(|| url.clone())?;
+    let host = response.url().host_str().unwrap_or_default().to_string();
+    Ok(format!("{} is reachable", host))
+}
+
+/// Decode the start of every downloaded track.
+fn check_tracks() -> Result<String> {
+    let loader = TrackLoader::new();
+    let downloaded: Vec<_> = tracks::catalog::all_tracks()
+        .filter(|track| loader.track_exists(track))
+        .collect();
+    if downloaded.is_empty() {
+        return Ok("none downloaded yet".to_string());
+    }
+
+    let broken: Vec<String> = downloaded
+        .iter()
+        .filter_map(|track| {
+            let path = loader.get_track_path(track);
+            audio::decoder::check_decodable(&path)
+                .err()
+                .map(|e| format!("{} ({:#})", path.display(), e))
+        })
+        .collect();
+    if !broken.is_empty() {
+        return Err(anyhow!(
+            "{} of {} unreadable: {}",
+            broken.len(),
+            downloaded.len(),
+            broken.join(", ")
+        ));
+    }
+    Ok(format!("all {} readable", downloaded.len()))
+}
+
+/// The config file was already loaded on startup; report how that went.
+fn check_config(error: Option<anyhow::Error>) -> Result<String> {
+    if let Some(e) = error {
+        return Err(e);
+    }
+    let path = crate::config::default_path();
+    if path.exists() {
+        Ok(format!("{} is valid", path.display()))
+    } else {
+        Ok(format!("none at {}, using defaults", path.display()))
+    }
+}
+
+/// Color and Unicode support, judged from the environment.
+fn check_terminal() -> Result<String> {
+    let term = std::env::var("TERM").unwrap_or_default();
+    let colors = if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        "colors off (NO_COLOR)"
+    } else if std::env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit") {
+        "24-bit color"
+    } else if term.contains("256color") {
+        "256 colors"
+    } else if term.is_empty() || term == "dumb" {
+        return Err(anyhow!("TERM is {:?}, so colors and cursor control may not work", term));
+    } else {
+        "basic colors"
+    };
+
+    // The first locale variable that's set decides the character set
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_default();
+    let lower = locale.to_lowercase();
+    if !cfg!(windows) && !lower.contains("utf-8") && !lower.contains("utf8") {
+        let locale = if locale.is_empty() {
+            "no locale is set".to_string()
+        } else {
+            format!("locale {:?} isn't UTF-8", locale)
+        };
+        return Err(anyhow!("{}, but {}, so the bars and icons may be garbled", colors, locale));
+    }
+    Ok(format!("{}, UTF-8", colors))
+}
@@ -0,0 +1,139 @@
+//! User programs run on playback events: `--on-track-change`, `--on-pause`
+//! and `--on-resume`.
+//!
+//! Each hook gets the event and what's playing in `FOMU_*` variables.
+//! Programs are spawned and reaped on a background thread, so a slow or
+//! hung hook never holds up the UI or the audio.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// How often finished hooks are reaped while others still run
+const REAP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A playback event with a hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HookEvent {
+    TrackChange,
+    Pause,
+    Resume,
+}
+
+impl HookEvent {
+    /// Value of `FOMU_EVENT`, so one program can serve several hooks.
+    fn name(&self) -> &'static str {
+        match self {
+            HookEvent::TrackChange => "track_change",
+            HookEvent::Pause => "pause",
+            HookEvent::Resume => "resume",
+        }
+    }
+}
+
+/// What's playing when an event fires.
+pub struct HookContext<'a> {
+    pub track: &'a str,
+    pub slug: &'a str,
+    pub preset: &'a str,
+    pub position: Duration,
+}
+
+/// A program to run, with its environment.
+struct Job {
+    event: HookEvent,
+    program: PathBuf,
+    env: Vec<(&'static str, String)>,
+}
+
+/// The configured hooks and the thread that runs them.
+pub struct Hooks {
+    on_track_change: Option<PathBuf>,
+    on_pause: Option<PathBuf>,
+    on_resume: Option<PathBuf>,
+    jobs: Option<Sender<Job>>,
+}
+
+impl Hooks {
+    /// Hooks for the given programs; `~/` expands to the home directory.
+    /// The runner thread starts only if at least one is set.
+    pub fn new(on_track_change: Option<String>, on_pause: Option<String>, on_resume: Option<String>) -> Self {
+        let mut hooks = Self {
+            on_track_change: on_track_change.map(expand_home),
+            on_pause: on_pause.map(expand_home),
+            on_resume: on_resume.map(expand_home),
+            jobs: None,
+        };
+        if hooks.on_track_change.is_some() || hooks.on_pause.is_some() || hooks.on_resume.is_some() {
+            let (tx, rx) = mpsc::channel();
+            match thread::Builder::new().name("hooks".to_string()).spawn(move || run_hooks(rx)) {
+                Ok(_) => hooks.jobs = Some(tx),
+                Err(e) => log::warn!("Could not start the hook thread, hooks are off: {}", e),
+            }
+        }
+        hooks
+    }
+
+    /// Run the hook for `event`, if one is set. Never waits for it.
+    pub fn fire(&self, event: HookEvent, context: &HookContext) {
+        let program = match event {
+            HookEvent::TrackChange => &self.on_track_change,
+            HookEvent::Pause => &self.on_pause,
+            HookEvent::Resume => &self.on_resume,
+        };
+        let (Some(program), Some(jobs)) = (program, &self.jobs) else {
+            return;
+        };
+        let _ = jobs.send(Job {
+            event,
+            program: program.clone(),
+            env: vec![
+                ("FOMU_EVENT", event.name().to_string()),
+                ("FOMU_TRACK", context.track.to_string()),
+                ("FOMU_SLUG", context.slug.to_string()),
+                ("FOMU_PRESET", context.preset.to_string()),
+                ("FOMU_POSITION", context.position.as_secs().to_string()),
+            ],
+        });
+    }
+}
+
+/// Spawn queued hooks and reap the ones that exit, until fomu quits.
+/// A hook that can't be started is warned about once.
+fn run_hooks(jobs: Receiver<Job>) {
+    let mut running: Vec<Child> = Vec::new();
+    let mut warned: HashSet<HookEvent> = HashSet::new();
+    loop {
+        match jobs.recv_timeout(REAP_INTERVAL) {
+            Ok(job) => {
+                let spawned = Command::new(&job.program)
+                    .envs(job.env)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn();
+                match spawned {
+                    Ok(child) => running.push(child),
+                    Err(e) if warned.insert(job.event) => {
+                        log::warn!("Could not run {} hook {:?}: {}", job.event.name(), job.program, e);
+                    }
+                    Err(_) => {}
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        running.retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+    }
+    // Hooks still running when fomu quits are left to finish on their own
+}
+
+fn expand_home(path: String) -> PathBuf {
+    match (path.strip_prefix("~/"), directories::BaseDirs::new()) {
+        (Some(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,239 @@
+//! Unix socket IPC for external control and status.
+//!
+//! Clients send newline-delimited JSON requests such as `{"cmd":"next"}` or
+//! `{"cmd":"volume","value":0.5}` and get one JSON response line back.
+//! Control requests are forwarded to the app as `Command`s; status is served
+//! from a snapshot the app refreshes every tick.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::app::Command;
+use crate::tracks::TrackLoader;
+
+/// Request sent by `fomu ctl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum Request {
+    Play,
+    Pause,
+    Toggle,
+    Next,
+    Volume { value: f32 },
+    /// Toggle mute
+    Mute,
+    /// Play a track next, by slug
+    Queue { slug: String },
+    Status,
+}
+
+/// Playback state reported by the `status` request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Status {
+    pub preset: String,
+    pub track: Option<String>,
+    /// Position in the current track (seconds)
+    pub position: f64,
+    /// Length of the current track (seconds), if known
+    pub duration: Option<f64>,
+    pub volume: f32,
+    /// Output silenced; `volume` is the level unmuting returns to
+    #[serde(default)]
+    pub muted: bool,
+    pub paused: bool,
+}
+
+/// Response to a single request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+}
+
+impl Response {
+    fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Default::default()
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            status: None,
+        }
+    }
+}
+
+/// Location of the control socket.
+pub fn socket_path() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("fomu.sock")
+}
+
+/// Control socket server running on a background thread, with a thread
+/// per connected client.
+pub struct IpcServer {
+    path: PathBuf,
+    status: Arc<Mutex<Status>>,
+    should_stop: Arc<AtomicBool>,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl IpcServer {
+    /// Bind the control socket at `path`, forwarding control requests to `commands`.
+    pub fn start(commands: Sender<Command>, path: PathBuf) -> Result<Self> {
+        // A leftover socket from a crashed run refuses connections; replace it.
+        // A live one means another fomu is running, so leave it alone.
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                anyhow::bail!("Another fomu instance is listening on {:?}", path);
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to bind control socket {:?}", path))?;
+        listener.set_nonblocking(true)?;
+
+        let status = Arc::new(Mutex::new(Status::default()));
+        let should_stop = Arc::new(AtomicBool::new(false));
+
+        let thread_status = Arc::clone(&status);
+        let thread_stop = Arc::clone(&should_stop);
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let commands = commands.clone();
+                        let status = Arc::clone(&thread_status);
+                        let stop = Arc::clone(&thread_stop);
+                        // Detached: a client doesn't hold up the next one
+                        // or shutdown, and leaves within a read timeout
+                        thread::spawn(move || handle_client(stream, &commands, &status, &stop));
+                    }
+                    Err(_) => thread::sleep(Duration::from_millis(50)),
+                }
+            }
+        });
+
+        Ok(Self {
+            path,
+            status,
+            should_stop,
+            thread_handle: Some(handle),
+        })
+    }
+
+    /// Publish the latest playback state for `status` requests.
+    pub fn update(&self, status: Status) {
+        *self.status.lock().unwrap() = status;
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.should_stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Serve requests from one client until it disconnects, goes quiet or the
+/// server stops.
+fn handle_client(stream: UnixStream, commands: &Sender<Command>, status: &Mutex<Status>, should_stop: &AtomicBool) {
+    // A stalled client gives up its thread after a second
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let reader = BufReader::new(stream);
+
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if should_stop.load(Ordering::Relaxed) {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle_request(request, commands, status),
+            Err(e) => Response::error(format!("Invalid request: {}", e)),
+        };
+
+        let Ok(mut json) = serde_json::to_string(&response) else {
+            break;
+        };
+        json.push('\n');
+        if writer.write_all(json.as_bytes()).is_err() {
+            break;
+        }
+    }
+}
+
+fn handle_request(request: Request, commands: &Sender<Command>, status: &Mutex<Status>) -> Response {
+    let command = match request {
+        Request::Status => {
+            return Response {
+                status: Some(status.lock().unwrap().clone()),
+                ..Response::ok()
+            };
+        }
+        Request::Play => Command::Play,
+        Request::Pause => Command::Pause,
+        Request::Toggle => Command::TogglePause,
+        Request::Next => Command::Next,
+        Request::Volume { value } => Command::SetVolume(value),
+        Request::Mute => Command::ToggleMute,
+        Request::Queue { slug } => match TrackLoader::get_track_by_slug(&slug) {
+            Some(track) => Command::Queue(track.slug),
+            None => return Response::error(format!("Unknown track '{}'", slug)),
+        },
+    };
+
+    match commands.send(command) {
+        Ok(()) => Response::ok(),
+        Err(_) => Response::error("Player is shutting down"),
+    }
+}
+
+/// Send one request to a running fomu and return its response.
+pub fn send_request(path: &Path, request: &Request) -> Result<Response> {
+    let mut stream = UnixStream::connect(path)
+        .with_context(|| format!("Could not connect to fomu at {:?} (is it running?)", path))?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
+    let mut json = serde_json::to_string(request)?;
+    json.push('\n');
+    stream.write_all(json.as_bytes())?;
+
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .context("No response from fomu")?;
+    serde_json::from_str(&line).context("Invalid response from fomu")
+}
@@ -0,0 +1,691 @@
+//! Key bindings.
+//!
+//! Each action has default keys. The `[keys]` section of the config file
+//! replaces them per action, e.g. `next = "ctrl+n"` or
+//! `volume_up = ["+", "up"]`; actions it doesn't mention keep their
+//! defaults. Esc, Enter, Ctrl+C and the picker's 1-9, Home and End are
+//! fixed so there's always a way out.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::ui::glyphs::{Glyphs, UNICODE};
+
+/// Something a key can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Pause,
+    Next,
+    VolumeUp,
+    VolumeDown,
+    VolumeUpLarge,
+    VolumeDownLarge,
+    Mute,
+    PresetMenu,
+    ClearQueue,
+    Downloads,
+    DownloadQueue,
+    Retry,
+    CancelDownload,
+    Bpm,
+    PeakFrequency,
+    Favorite,
+    Visualizer,
+    Radial,
+    Record,
+    Rescan,
+    Debug,
+    Support,
+    Help,
+    Soundscape,
+    SoundscapeUp,
+    SoundscapeDown,
+    MenuUp,
+    MenuDown,
+    Prefetch,
+}
+
+/// Where an action's keys are live.
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+    Main,
+    Picker,
+    Both,
+}
+
+struct ActionInfo {
+    action: Action,
+    name: &'static str,
+    description: &'static str,
+    defaults: &'static [&'static str],
+    context: Context,
+}
+
+/// Every action in help order, with its config name and default keys.
+const ACTIONS: &[ActionInfo] = &[
+    ActionInfo {
+        action: Action::Pause,
+        name: "pause",
+        description: "Pause/resume",
+        defaults: &["space"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::VolumeUp,
+        name: "volume_up",
+        description: "Volume up 2%",
+        defaults: &["+", "=", "]", "up"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::VolumeDown,
+        name: "volume_down",
+        description: "Volume down 2%",
+        defaults: &["-", "_", "[", "down"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::VolumeUpLarge,
+        name: "volume_up_large",
+        description: "Volume up 10%",
+        defaults: &["shift+up"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::VolumeDownLarge,
+        name: "volume_down_large",
+        description: "Volume down 10%",
+        defaults: &["shift+down"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Mute,
+        name: "mute",
+        description: "Mute/unmute",
+        defaults: &["0", "M"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Next,
+        name: "next",
+        description: "Skip track",
+        defaults: &["n"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::ClearQueue,
+        name: "clear_queue",
+        description: "Clear the up-next queue",
+        defaults: &["N"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::PresetMenu,
+        name: "preset_menu",
+        description: "Select preset (switches while open)",
+        defaults: &["p"],
+        context: Context::Both,
+    },
+    ActionInfo {
+        action: Action::MenuUp,
+        name: "menu_up",
+        description: "Previous preset in the picker",
+        defaults: &["k", "up"],
+        context: Context::Picker,
+    },
+    ActionInfo {
+        action: Action::MenuDown,
+        name: "menu_down",
+        description: "Next preset in the picker",
+        defaults: &["j", "down"],
+        context: Context::Picker,
+    },
+    ActionInfo {
+        action: Action::Prefetch,
+        name: "prefetch",
+        description: "Download the picked preset without switching",
+        defaults: &["d"],
+        context: Context::Picker,
+    },
+    ActionInfo {
+        action: Action::Soundscape,
+        name: "soundscape",
+        description: "Cycle soundscapes (off, rain, forest, ...)",
+        defaults: &["g"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::SoundscapeUp,
+        name: "soundscape_up",
+        description: "Soundscape louder",
+        defaults: &["}"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::SoundscapeDown,
+        name: "soundscape_down",
+        description: "Soundscape quieter",
+        defaults: &["{"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Bpm,
+        name: "bpm",
+        description: "Toggle BPM estimate",
+        defaults: &["l"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Favorite,
+        name: "favorite",
+        description: "Favorite or unfavorite the current track",
+        defaults: &["F"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::PeakFrequency,
+        name: "peak_frequency",
+        description: "Toggle the dominant frequency",
+        defaults: &["f"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Visualizer,
+        name: "visualizer",
+        description: "Cycle visualizer",
+        defaults: &["v"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Radial,
+        name: "radial",
+        description: "Toggle the radial visualizer",
+        defaults: &["c"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Downloads,
+        name: "downloads",
+        description: "Show download history",
+        defaults: &["d"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::DownloadQueue,
+        name: "download_queue",
+        description: "Show the download queue",
+        defaults: &["D"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Retry,
+        name: "retry",
+        description: "Retry failed downloads (queue open)",
+        defaults: &["r"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::CancelDownload,
+        name: "cancel_download",
+        description: "Skip a download to the end of the queue (queue open)",
+        defaults: &["ctrl+x"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Record,
+        name: "record",
+        description: "Start/stop recording",
+        defaults: &["R"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Rescan,
+        name: "rescan",
+        description: "Rescan the tracks directory",
+        defaults: &["ctrl+l"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Debug,
+        name: "debug",
+        description: "Toggle the buffer debug overlay",
+        defaults: &["ctrl+d"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Support,
+        name: "support",
+        description: "Open the artist's support page",
+        defaults: &["s"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Help,
+        name: "help",
+        description: "Show this help",
+        defaults: &["?"],
+        context: Context::Main,
+    },
+    ActionInfo {
+        action: Action::Quit,
+        name: "quit",
+        description: "Quit (closes the picker)",
+        defaults: &["q"],
+        context: Context::Both,
+    },
+];
+
+fn info(action: Action) -> &'static ActionInfo {
+    ACTIONS
+        .iter()
+        .find(|info| info.action == action)
+        .expect("every action is listed")
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(info(*self).name)
+    }
+}
+
+/// The key event a binding matches: shifted letters are told apart by
+/// case, so SHIFT only counts for keys without a character.
+pub fn normalize(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    let mut modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+    if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    KeyEvent::new(code, modifiers)
+}
+
+/// Parse a key spec such as `q`, `space`, `ctrl+n`, `F5` or `shift+tab`.
+pub fn parse_key(spec: &str) -> Result<KeyEvent> {
+    let (mods, key) = if spec.len() > 1 && spec.ends_with("++") {
+        (&spec[..spec.len() - 2], "+")
+    } else {
+        match spec.rfind('+') {
+            Some(i) if i > 0 => (&spec[..i], &spec[i + 1..]),
+            _ => ("", spec),
+        }
+    };
+
+    let mut modifiers = KeyModifiers::NONE;
+    for part in mods.split('+').filter(|part| !part.is_empty()) {
+        modifiers |= match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => bail!("Unknown modifier '{}' in key '{}'", part, spec),
+        };
+    }
+
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match key.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=24) => KeyCode::F(n),
+                _ => bail!("Unknown key '{}'", spec),
+            },
+        },
+    };
+    Ok(normalize(code, modifiers))
+}
+
+/// Short label for a key, as shown in the footer and help.
+pub fn key_label(key: &KeyEvent, glyphs: &Glyphs) -> String {
+    let mut label = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        label.push_str("ctrl+");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        label.push_str("alt+");
+    }
+    if key.modifiers.contains(KeyModifiers::SHIFT) {
+        label.push_str("shift+");
+    }
+    match key.code {
+        KeyCode::Char(' ') => label.push_str("space"),
+        KeyCode::Char(c) => label.push(c),
+        KeyCode::F(n) => label.push_str(&format!("F{}", n)),
+        KeyCode::Up => label.push_str(glyphs.arrow_keys[0]),
+        KeyCode::Down => label.push_str(glyphs.arrow_keys[1]),
+        KeyCode::Left => label.push_str(glyphs.arrow_keys[2]),
+        KeyCode::Right => label.push_str(glyphs.arrow_keys[3]),
+        KeyCode::Enter => label.push_str("enter"),
+        KeyCode::Esc => label.push_str("esc"),
+        KeyCode::Tab => label.push_str("tab"),
+        KeyCode::BackTab => label.push_str("backtab"),
+        KeyCode::Backspace => label.push_str("backspace"),
+        KeyCode::Home => label.push_str("home"),
+        KeyCode::End => label.push_str("end"),
+        KeyCode::PageUp => label.push_str("pgup"),
+        KeyCode::PageDown => label.push_str("pgdn"),
+        KeyCode::Delete => label.push_str("del"),
+        KeyCode::Insert => label.push_str("ins"),
+        _ => label.push('?'),
+    }
+    label
+}
+
+/// Resolved key bindings for the main view and the preset picker.
+pub struct Keymap {
+    main: HashMap<KeyEvent, Action>,
+    picker: HashMap<KeyEvent, Action>,
+    /// Keys configured for each action, in order, including ones a
+    /// conflict took away
+    keys: HashMap<Action, Vec<KeyEvent>>,
+    /// Keys claimed by two actions, as messages naming both
+    conflicts: Vec<String>,
+    /// Characters key labels are drawn with
+    glyphs: &'static Glyphs,
+}
+
+impl Keymap {
+    /// Build the keymap from the config file's `[keys]` table.
+    ///
+    /// Unknown actions and unparseable keys are errors. A key bound to two
+    /// actions goes to the one set in the config, and shows up in
+    /// `conflicts()`. Labels are drawn with `glyphs`.
+    pub fn new(bindings: &toml::Table, glyphs: &'static Glyphs) -> Result<Self> {
+        let mut configured: HashMap<Action, Vec<KeyEvent>> = HashMap::new();
+        for (name, value) in bindings {
+            let action = ACTIONS
+                .iter()
+                .find(|info| info.name == name)
+                .ok_or_else(|| {
+                    let names: Vec<_> = ACTIONS.iter().map(|info| info.name).collect();
+                    anyhow!("Unknown action '{}' in [keys]. Actions: {}", name, names.join(", "))
+                })?
+                .action;
+            let specs = match value {
+                toml::Value::String(spec) => vec![spec.as_str()],
+                toml::Value::Array(specs) => specs
+                    .iter()
+                    .map(|spec| spec.as_str())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| anyhow!("Keys for '{}' must be strings", name))?,
+                _ => bail!("Keys for '{}' must be a string or a list of strings", name),
+            };
+            let keys = specs
+                .into_iter()
+                .map(|spec| parse_key(spec).map_err(|e| anyhow!("[keys] {}: {}", name, e)))
+                .collect::<Result<Vec<_>>>()?;
+            configured.insert(action, keys);
+        }
+
+        // Defaults go in first so configured keys override them
+        let defaults = ACTIONS.iter().filter(|info| !configured.contains_key(&info.action));
+        let custom = ACTIONS.iter().filter(|info| configured.contains_key(&info.action));
+        let mut keymap = Self {
+            main: HashMap::new(),
+            picker: HashMap::new(),
+            keys: HashMap::new(),
+            conflicts: Vec::new(),
+            glyphs,
+        };
+        for info in defaults.chain(custom) {
+            let keys = match configured.get(&info.action) {
+                Some(keys) => keys.clone(),
+                None => info.defaults.iter().map(|spec| parse_key(spec).expect("valid default key")).collect(),
+            };
+            for key in &keys {
+                keymap.bind(*key, info);
+            }
+            keymap.keys.insert(info.action, keys);
+        }
+        for conflict in &keymap.conflicts {
+            log::warn!("{}", conflict);
+        }
+        Ok(keymap)
+    }
+
+    fn bind(&mut self, key: KeyEvent, info: &ActionInfo) {
+        let maps = match info.context {
+            Context::Main => vec![&mut self.main],
+            Context::Picker => vec![&mut self.picker],
+            Context::Both => vec![&mut self.main, &mut self.picker],
+        };
+        let mut replaced = None;
+        for map in maps {
+            if let Some(old) = map.insert(key, info.action).filter(|old| *old != info.action) {
+                replaced = Some(old);
+            }
+        }
+        if let Some(old) = replaced {
+            self.conflicts.push(format!(
+                "Key '{}' is bound to both {} and {}; using it for {}",
+                key_label(&key, self.glyphs),
+                old,
+                info.action,
+                info.action
+            ));
+        }
+    }
+
+    /// Keys that still trigger `action`, in the picker or the main view.
+    fn live_keys(&self, action: Action, in_picker: bool) -> Vec<KeyEvent> {
+        let map = if in_picker { &self.picker } else { &self.main };
+        self.keys
+            .get(&action)
+            .into_iter()
+            .flatten()
+            .filter(|key| map.get(key) == Some(&action))
+            .copied()
+            .collect()
+    }
+
+    /// Action bound to `key` in the main view.
+    pub fn main_action(&self, key: &KeyEvent) -> Option<Action> {
+        self.main.get(key).copied()
+    }
+
+    /// Action bound to `key` in the preset picker.
+    pub fn picker_action(&self, key: &KeyEvent) -> Option<Action> {
+        self.picker.get(key).copied()
+    }
+
+    /// Label of the first key that triggers `action` in the main view
+    /// (the picker for picker-only actions), or `-` if none does.
+    pub fn label(&self, action: Action) -> String {
+        self.first_label(action, info(action).context == Context::Picker)
+    }
+
+    /// Like `label`, but for the key `action` has in the preset picker.
+    pub fn picker_label(&self, action: Action) -> String {
+        self.first_label(action, true)
+    }
+
+    fn first_label(&self, action: Action, in_picker: bool) -> String {
+        self.live_keys(action, in_picker)
+            .first()
+            .map(|key| key_label(key, self.glyphs))
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    /// Every action's keys and description, for the help overlay.
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        ACTIONS
+            .iter()
+            .map(|info| {
+                let keys = self.live_keys(info.action, info.context == Context::Picker);
+                let label = if keys.is_empty() {
+                    "-".to_string()
+                } else {
+                    keys.iter().map(|key| key_label(key, self.glyphs)).collect::<Vec<_>>().join(" ")
+                };
+                (label, info.description)
+            })
+            .collect()
+    }
+
+    /// Keys claimed by two actions.
+    pub fn conflicts(&self) -> &[String] {
+        &self.conflicts
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&toml::Table::new(), &UNICODE).expect("default keymap is valid")
+    }
+}
+
+/// Commented `[keys]` section with every action at its default keys.
+pub fn template() -> String {
+    let mut out = String::from(
+        "\n# Key bindings: an action name, then one key or a list of keys such as\n\
+         # \"space\", \"ctrl+n\", \"F5\" or [\"+\", \"up\"]. Actions left out keep their\n\
+         # default keys. Esc, Enter and Ctrl+C can't be remapped.\n\
+         [keys]\n",
+    );
+    for info in ACTIONS {
+        let defaults: Vec<String> = info.defaults.iter().map(|spec| format!("{:?}", spec)).collect();
+        let value = match defaults.as_slice() {
+            [single] => single.clone(),
+            _ => format!("[{}]", defaults.join(", ")),
+        };
+        out.push_str(&format!("# {}\n# {} = {}\n", info.description, info.name, value));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(spec: &str) -> KeyEvent {
+        parse_key(spec).unwrap()
+    }
+
+    fn keymap(text: &str) -> Keymap {
+        Keymap::new(&toml::from_str(text).unwrap(), &UNICODE).unwrap()
+    }
+
+    #[test]
+    fn parses_key_specs() {
+        assert_eq!(key("q"), KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert_eq!(key("space"), KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert_eq!(key("ctrl+n"), KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL));
+        assert_eq!(key("Ctrl+Alt+x"), KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL | KeyModifiers::ALT));
+        assert_eq!(key("F5"), KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE));
+        assert_eq!(key("shift+up"), KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT));
+        assert_eq!(key("shift+tab"), KeyEvent::new(KeyCode::BackTab, KeyModifiers::NONE));
+        assert_eq!(key("+"), KeyEvent::new(KeyCode::Char('+'), KeyModifiers::NONE));
+        assert_eq!(key("ctrl++"), KeyEvent::new(KeyCode::Char('+'), KeyModifiers::CONTROL));
+        for bad in ["", "hyper+q", "F25", "nope"] {
+            assert!(parse_key(bad).is_err(), "accepted {:?}", bad);
+        }
+    }
+
+    #[test]
+    fn shifted_letters_match_by_case() {
+        // Terminals report N as Char('N') with SHIFT; the binding is just "N"
+        assert_eq!(normalize(KeyCode::Char('N'), KeyModifiers::SHIFT), key("N"));
+        assert_eq!(key("shift+n"), key("N"));
+        assert_ne!(key("n"), key("N"));
+        // Keys without a character keep SHIFT
+        assert_ne!(normalize(KeyCode::Up, KeyModifiers::SHIFT), key("up"));
+    }
+
+    #[test]
+    fn default_keys() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.main_action(&key("space")), Some(Action::Pause));
+        assert_eq!(keymap.main_action(&key("n")), Some(Action::Next));
+        assert_eq!(keymap.main_action(&key("N")), Some(Action::ClearQueue));
+        assert_eq!(keymap.main_action(&key("up")), Some(Action::VolumeUp));
+        assert_eq!(keymap.main_action(&key("shift+up")), Some(Action::VolumeUpLarge));
+        assert_eq!(keymap.main_action(&key("p")), Some(Action::PresetMenu));
+        assert_eq!(keymap.main_action(&key("j")), None);
+        assert!(keymap.conflicts().is_empty());
+    }
+
+    #[test]
+    fn picker_and_main_view_have_their_own_keys() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.main_action(&key("d")), Some(Action::Downloads));
+        assert_eq!(keymap.picker_action(&key("d")), Some(Action::Prefetch));
+        assert_eq!(keymap.picker_action(&key("up")), Some(Action::MenuUp));
+        assert_eq!(keymap.picker_action(&key("k")), Some(Action::MenuUp));
+        assert_eq!(keymap.picker_action(&key("j")), Some(Action::MenuDown));
+        // Quit and the preset key work in both
+        assert_eq!(keymap.picker_action(&key("p")), Some(Action::PresetMenu));
+        assert_eq!(keymap.picker_action(&key("q")), Some(Action::Quit));
+        assert_eq!(keymap.picker_action(&key("n")), None);
+    }
+
+    #[test]
+    fn configured_keys_replace_the_defaults() {
+        let keymap = keymap(r#"next = "ctrl+n"
+volume_up = ["+", "F2"]"#);
+        assert_eq!(keymap.main_action(&key("ctrl+n")), Some(Action::Next));
+        assert_eq!(keymap.main_action(&key("n")), None);
+        assert_eq!(keymap.main_action(&key("F2")), Some(Action::VolumeUp));
+        assert_eq!(keymap.main_action(&key("up")), None);
+        assert_eq!(keymap.label(Action::Next), "ctrl+n");
+    }
+
+    #[test]
+    fn a_configured_key_wins_a_conflict() {
+        let keymap = keymap(r#"pause = "n""#);
+        assert_eq!(keymap.main_action(&key("n")), Some(Action::Pause));
+        assert_eq!(keymap.conflicts().len(), 1);
+        assert!(keymap.conflicts()[0].contains("next"));
+        // Next lost its only key
+        assert_eq!(keymap.label(Action::Next), "-");
+        let help = keymap.help();
+        let next = help.iter().find(|(_, description)| *description == "Skip track").unwrap();
+        assert_eq!(next.0, "-");
+    }
+
+    #[test]
+    fn arrow_labels_follow_the_glyph_set() {
+        let bindings = toml::from_str(r#"volume_up = "up""#).unwrap();
+        assert_eq!(Keymap::new(&bindings, &UNICODE).unwrap().label(Action::VolumeUp), "↑");
+        let ascii = Keymap::new(&bindings, &crate::ui::glyphs::ASCII).unwrap();
+        assert_eq!(ascii.label(Action::VolumeUp), "up");
+    }
+
+    #[test]
+    fn bad_bindings_are_rejected() {
+        for text in [r#"warp = "w""#, r#"next = "hyper+n""#, "next = 5", "next = [1]"] {
+            let table: toml::Table = toml::from_str(text).unwrap();
+            assert!(Keymap::new(&table, &UNICODE).is_err(), "accepted {}", text);
+        }
+    }
+
+    #[test]
+    fn template_lists_every_action() {
+        let template = template();
+        for info in ACTIONS {
+            assert!(template.contains(&format!("# {} = ", info.name)), "{} missing", info.name);
+        }
+        let table: toml::Table = toml::from_str(&template).unwrap();
+        assert!(table["keys"].as_table().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,29 @@
+//! Fomu core: audio playback, the track catalog and presets.
+//!
+//! The `fomu` binary adds the terminal UI and command line on top. To embed
+//! the player in another program, use [`Player`]:
+//!
+//! ```no_run
+//! use fomu::{Player, PlayerConfig, PlayerEvent};
+//!
+//! let mut player = Player::new(PlayerConfig::default())?;
+//! let events = player.events();
+//! player.play_preset("focus")?;
+//! loop {
+//!     player.update();
+//!     while let Ok(event) = events.try_recv() {
+//!         if let PlayerEvent::TrackChanged { name, .. } = event {
+//!             println!("Now playing {}", name);
+//!         }
+//!     }
+//!     std::thread::sleep(std::time::Duration::from_millis(200));
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod audio;
+pub mod player;
+pub mod presets;
+pub mod tracks;
+
+pub use player::{Player, PlayerConfig, PlayerEvent};
@@ -0,0 +1,64 @@
+//! File logging.
+//!
+//! The TUI owns the terminal, so diagnostics go to a log file instead of
+//! stderr. The file is rotated once at startup when it grows too large.
+//! A path of `-` logs to stderr, for redirecting or for commands that
+//! don't start the TUI.
+
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::LevelFilter;
+
+use crate::tracks::loader::get_data_dir;
+
+/// Rotate the log when it exceeds this size (bytes)
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Default log file location in the data directory.
+pub fn default_log_path() -> PathBuf {
+    get_data_dir().join("fomu.log")
+}
+
+/// Start logging at `level` to `path` (`-` for stderr). Does nothing when `level` is off.
+pub fn init(level: LevelFilter, path: &Path) -> Result<()> {
+    if level == LevelFilter::Off {
+        return Ok(());
+    }
+
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level);
+    if path == Path::new("-") {
+        builder.target(env_logger::Target::Stderr);
+    } else {
+        builder
+            .target(env_logger::Target::Pipe(Box::new(open_log_file(path)?)))
+            .write_style(env_logger::WriteStyle::Never);
+    }
+    builder.try_init().context("Failed to initialize logger")?;
+
+    log::info!("fomu {} starting", env!("CARGO_PKG_VERSION"));
+    Ok(())
+}
+
+/// Open `path` for appending, rotating it first if it's too large.
+fn open_log_file(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).ok();
+    }
+
+    // Keep one previous log around when rotating
+    let too_big = std::fs::metadata(path)
+        .map(|m| m.len() > MAX_LOG_SIZE)
+        .unwrap_or(false);
+    if too_big {
+        let _ = std::fs::rename(path, path.with_extension("log.1"));
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {:?}", path))
+}
@@ -0,0 +1,336 @@
+//! MPRIS2 D-Bus integration so media keys and playerctl can control fomu.
+//!
+//! Method calls arrive on zbus's internal executor thread and are forwarded
+//! to the app as `Command`s. The app pushes state back via `update()`, which
+//! emits `PropertiesChanged` only when something actually changed.
+
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use zbus::blocking::Connection;
+use zbus::interface;
+use zbus::names::BusName;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+use crate::app::Command;
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.fomu";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// Player state exposed over D-Bus.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MprisState {
+    pub playing: bool,
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub artist: Option<String>,
+    pub length: Option<Duration>,
+    pub volume: f32,
+}
+
+impl MprisState {
+    fn playback_status(&self) -> &'static str {
+        match (&self.title, self.playing) {
+            (None, _) => "Stopped",
+            (Some(_), true) => "Playing",
+            (Some(_), false) => "Paused",
+        }
+    }
+
+    /// Whether anything `metadata` reports differs from `other`.
+    fn metadata_differs(&self, other: &Self) -> bool {
+        self.slug != other.slug
+            || self.title != other.title
+            || self.artist != other.artist
+            || self.length != other.length
+    }
+
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut map = HashMap::new();
+        let track_id = match &self.slug {
+            // Object paths only allow [A-Za-z0-9_]
+            Some(slug) => format!("/org/fomu/track/{}", slug.replace('-', "_")),
+            None => "/org/mpris/MediaPlayer2/TrackList/NoTrack".to_string(),
+        };
+        if let Ok(path) = ObjectPath::try_from(track_id) {
+            insert(&mut map, "mpris:trackid", Value::from(path));
+        }
+        if let Some(title) = &self.title {
+            insert(&mut map, "xesam:title", Value::from(title.as_str()));
+        }
+        if let Some(artist) = &self.artist {
+            insert(&mut map, "xesam:artist", Value::from(vec![artist.as_str()]));
+        }
+        if let Some(length) = self.length {
+            insert(&mut map, "mpris:length", Value::from(length.as_micros() as i64));
+        }
+        map
+    }
+}
+
+fn insert(map: &mut HashMap<String, OwnedValue>, key: &str, value: Value<'_>) {
+    if let Ok(owned) = OwnedValue::try_from(value) {
+        map.insert(key.to_string(), owned);
+    }
+}
+
+/// `org.mpris.MediaPlayer2` root interface.
+struct Root {
+    commands: Sender<Command>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        let _ = self.commands.send(Command::Quit);
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "Fomu"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// `org.mpris.MediaPlayer2.Player` interface.
+struct Player {
+    commands: Sender<Command>,
+    state: Arc<Mutex<MprisState>>,
+}
+
+impl Player {
+    fn send(&self, command: Command) {
+        let _ = self.commands.send(command);
+    }
+
+    fn state(&self) -> MprisState {
+        self.state.lock().unwrap().clone()
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send(Command::Next);
+    }
+
+    fn previous(&self) {}
+
+    fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(Command::TogglePause);
+    }
+
+    fn stop(&self) {
+        // There is no separate stopped state; stopping just pauses
+        self.send(Command::Pause);
+    }
+
+    fn play(&self) {
+        self.send(Command::Play);
+    }
+
+    fn seek(&self, _offset: i64) {}
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, _position: i64) {}
+
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        self.state().playback_status()
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.state().metadata()
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.state().volume as f64
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        self.send(Command::SetVolume(volume as f32));
+    }
+
+    #[zbus(property)]
+    fn position(&self) -> i64 {
+        0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// Registered MPRIS service on the session bus.
+pub struct MprisServer {
+    connection: Connection,
+    state: Arc<Mutex<MprisState>>,
+}
+
+impl MprisServer {
+    /// Register on the session bus, forwarding control requests to `commands`.
+    pub fn start(commands: Sender<Command>) -> Result<Self> {
+        let state = Arc::new(Mutex::new(MprisState::default()));
+
+        let root = Root {
+            commands: commands.clone(),
+        };
+        let player = Player {
+            commands,
+            state: Arc::clone(&state),
+        };
+
+        let connection = zbus::blocking::connection::Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, root)?
+            .serve_at(OBJECT_PATH, player)?
+            .build()?;
+
+        Ok(Self { connection, state })
+    }
+
+    /// Publish new player state, emitting change signals for what differs.
+    pub fn update(&self, new: MprisState) {
+        let old = {
+            let mut state = self.state.lock().unwrap();
+            if *state == new {
+                return;
+            }
+            std::mem::replace(&mut *state, new.clone())
+        };
+
+        let mut changed: HashMap<&str, Value> = HashMap::new();
+        if old.playback_status() != new.playback_status() {
+            changed.insert("PlaybackStatus", Value::from(new.playback_status()));
+        }
+        if old.metadata_differs(&new) {
+            changed.insert("Metadata", Value::from(new.metadata()));
+        }
+        if old.volume != new.volume {
+            changed.insert("Volume", Value::from(new.volume as f64));
+        }
+        if changed.is_empty() {
+            return;
+        }
+
+        let _ = self.connection.emit_signal(
+            None::<BusName>,
+            OBJECT_PATH,
+            "org.freedesktop.DBus.Properties",
+            "PropertiesChanged",
+            &(PLAYER_INTERFACE, changed, Vec::<&str>::new()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> MprisState {
+        MprisState {
+            playing: true,
+            title: Some("Permafrost".to_string()),
+            slug: Some("permafrost".to_string()),
+            artist: Some("Scott Buckley".to_string()),
+            length: Some(Duration::from_secs(300)),
+            volume: 0.8,
+        }
+    }
+
+    #[test]
+    fn artist_change_updates_metadata() {
+        let old = state();
+        let new = MprisState {
+            artist: Some("Someone Else".to_string()),
+            ..state()
+        };
+        assert!(old.metadata_differs(&new));
+    }
+
+    #[test]
+    fn volume_change_leaves_metadata() {
+        let old = state();
+        let new = MprisState { volume: 0.5, ..state() };
+        assert!(!old.metadata_differs(&new));
+    }
+}
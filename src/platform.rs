@@ -0,0 +1,111 @@
+//! Differences between terminals and platforms that change what fomu draws
+//! or enables.
+//!
+//! The legacy Windows console (conhost) prints escape sequences it doesn't
+//! know as raw text and reports mouse events unreliably; Windows Terminal
+//! and the VS Code terminal handle both like a Unix terminal does. A plain
+//! Linux console or a non-UTF-8 locale can't show block elements, braille
+//! or symbols.
+//!
+//! Each check has a pure counterpart taking the platform and a variable
+//! lookup, so it can be tested for any environment.
+
+use std::ffi::OsString;
+
+/// Whether output goes to the legacy Windows console rather than a modern
+/// terminal. Always false outside Windows.
+pub fn is_legacy_console() -> bool {
+    legacy_console(cfg!(windows), |name| std::env::var_os(name))
+}
+
+/// Whether OSC 8 hyperlinks show as links rather than escape garbage.
+pub fn hyperlinks_supported() -> bool {
+    !is_legacy_console()
+}
+
+/// Whether to capture the mouse for clicks and scrolling.
+pub fn mouse_capture_supported() -> bool {
+    !is_legacy_console()
+}
+
+/// Guess from the environment whether the terminal can show Unicode
+/// glyphs: the Linux console can't, and elsewhere the locale must be
+/// UTF-8. Windows has no locale variables, so only modern terminals count
+/// there.
+pub fn unicode_supported() -> bool {
+    unicode_terminal(cfg!(windows), |name| std::env::var_os(name))
+}
+
+/// `is_legacy_console` with `var` in place of the environment.
+fn legacy_console(windows: bool, var: impl Fn(&str) -> Option<OsString>) -> bool {
+    // Windows Terminal sets WT_SESSION in every tab, VS Code and others TERM_PROGRAM
+    windows && var("WT_SESSION").is_none() && var("TERM_PROGRAM").is_none()
+}
+
+/// `unicode_supported` with `var` in place of the environment.
+fn unicode_terminal(windows: bool, var: impl Fn(&str) -> Option<OsString>) -> bool {
+    if var("TERM").is_some_and(|term| term == "linux") {
+        return false;
+    }
+    // The first one set wins, as it does for the C library
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| var(name).filter(|v| !v.is_empty()));
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_string_lossy().to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        None => windows && !legacy_console(windows, var),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A variable lookup over `vars` only.
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        move |name| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| OsString::from(v))
+    }
+
+    #[test]
+    fn legacy_console_is_windows_without_a_modern_terminal() {
+        assert!(legacy_console(true, env(&[])));
+        assert!(!legacy_console(true, env(&[("WT_SESSION", "1f2e")])));
+        assert!(!legacy_console(true, env(&[("TERM_PROGRAM", "vscode")])));
+        assert!(!legacy_console(false, env(&[])));
+    }
+
+    #[test]
+    fn utf8_locales_get_unicode() {
+        for locale in ["en_US.UTF-8", "de_DE.utf8", "C.UTF-8"] {
+            assert!(unicode_terminal(false, env(&[("LANG", locale)])), "{}", locale);
+        }
+        for locale in ["C", "POSIX", "en_US.ISO-8859-1"] {
+            assert!(!unicode_terminal(false, env(&[("LANG", locale)])), "{}", locale);
+        }
+    }
+
+    #[test]
+    fn lc_all_wins_over_lang_and_empty_ones_are_skipped() {
+        assert!(!unicode_terminal(false, env(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")])));
+        assert!(unicode_terminal(false, env(&[("LC_CTYPE", "en_US.UTF-8"), ("LANG", "C")])));
+        assert!(unicode_terminal(false, env(&[("LC_ALL", ""), ("LANG", "en_US.UTF-8")])));
+    }
+
+    #[test]
+    fn linux_console_gets_ascii_whatever_the_locale() {
+        assert!(!unicode_terminal(false, env(&[("TERM", "linux"), ("LANG", "en_US.UTF-8")])));
+        assert!(unicode_terminal(false, env(&[("TERM", "xterm-256color"), ("LANG", "en_US.UTF-8")])));
+    }
+
+    #[test]
+    fn without_a_locale_only_modern_windows_terminals_get_unicode() {
+        assert!(!unicode_terminal(false, env(&[])));
+        assert!(!unicode_terminal(true, env(&[])));
+        assert!(unicode_terminal(true, env(&[("WT_SESSION", "1f2e")])));
+        // A locale set under Windows still decides
+        assert!(!unicode_terminal(true, env(&[("WT_SESSION", "1f2e"), ("LANG", "C")])));
+    }
+}
@@ -0,0 +1,257 @@
+//! Headless playback for embedding fomu in other programs.
+//!
+//! `Player` plays presets without a terminal. It has no thread of its own:
+//! call `update` a few times a second from your loop to move on when a
+//! track ends and to hear about downloads through `events`. With the
+//! `null` backend it runs without a sound card too.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::audio::{AudioDecoder, AudioPlayer};
+use crate::presets::{get_preset, Preset};
+use crate::tracks::downloader::http_client;
+use crate::tracks::loader::get_tracks_dir;
+use crate::tracks::{DownloadState, Track, TrackDownloader, TrackLoader};
+
+/// Settings for a `Player`.
+#[derive(Debug, Clone)]
+pub struct PlayerConfig {
+    /// Output volume (0.0 - 1.0)
+    pub volume: f32,
+    /// Backend to play through (e.g. `ALSA`, or `null` for no sound
+    /// card), or `None` for the default
+    pub backend: Option<String>,
+    /// Tracks downloaded at once in the background
+    pub max_downloads: usize,
+    /// Where downloaded tracks live, or `None` for fomu's own tracks
+    /// directory
+    pub tracks_dir: Option<PathBuf>,
+    /// Host and path to download catalog tracks from instead of their own,
+    /// like `FOMU_TRACKS_BASE_URL`
+    pub tracks_base_url: Option<String>,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            volume: 0.8,
+            backend: None,
+            max_downloads: 1,
+            tracks_dir: None,
+            tracks_base_url: None,
+        }
+    }
+}
+
+/// Something that happened during playback.
+#[derive(Debug, Clone)]
+pub enum PlayerEvent {
+    /// A track started playing; `Player::current_track` has the rest
+    TrackChanged {
+        slug: String,
+        name: String,
+        preset: String,
+    },
+    /// A background download finished
+    Downloaded { track: String },
+    /// A background download failed
+    DownloadFailed { track: String, error: String },
+    /// Playback couldn't continue with the next track
+    Error(String),
+}
+
+/// Plays presets through the default output, without any UI.
+pub struct Player {
+    audio: AudioPlayer,
+    decoder: AudioDecoder,
+    loader: TrackLoader,
+    downloader: TrackDownloader,
+    preset: Option<&'static Preset>,
+    playlist: Vec<&'static Track>,
+    playlist_index: usize,
+    current: Option<&'static Track>,
+    events_tx: Sender<PlayerEvent>,
+    events_rx: Receiver<PlayerEvent>,
+    /// Download states already reported, by history index
+    reported: Vec<DownloadState>,
+}
+
+impl Player {
+    /// Open the audio output. Nothing plays until `play_preset`.
+    pub fn new(config: PlayerConfig) -> Result<Self> {
+        let audio = AudioPlayer::new(config.backend.as_deref())?;
+        audio.set_volume(config.volume.clamp(0.0, 1.0));
+        let tracks_dir = config.tracks_dir.unwrap_or_else(get_tracks_dir);
+        let mut downloader = TrackDownloader::with_base(tracks_dir.clone(), http_client()?);
+        downloader.set_max_downloads(config.max_downloads);
+        if let Some(url) = config.tracks_base_url {
+            downloader.set_base_url(url);
+        }
+        let (events_tx, events_rx) = crossbeam_channel::unbounded();
+
+        Ok(Self {
+            audio,
+            decoder: AudioDecoder::new(),
+            loader: TrackLoader::with_dir(tracks_dir),
+            downloader,
+            preset: None,
+            playlist: Vec::new(),
+            playlist_index: 0,
+            current: None,
+            events_tx,
+            events_rx,
+            reported: Vec::new(),
+        })
+    }
+
+    /// Receiver for playback events. Receivers share one queue, so each
+    /// event goes to exactly one of them.
+    pub fn events(&self) -> Receiver<PlayerEvent> {
+        self.events_rx.clone()
+    }
+
+    /// Switch to a preset and start playing it.
+    ///
+    /// Blocks to download one track if none of the preset's are cached;
+    /// the rest download in the background.
+    pub fn play_preset(&mut self, name: &str) -> Result<()> {
+        let preset = get_preset(name).ok_or_else(|| anyhow!("Unknown preset '{}'", name))?;
+        let pools = preset.pool_list();
+        if self.loader.get_available_tracks_from_pools(&pools).is_empty()
+            && self.downloader.download_one_track(&pools)?.is_none()
+        {
+            return Err(anyhow!("Preset '{}' has no tracks", name));
+        }
+
+        self.preset = Some(preset);
+        self.reshuffle();
+        self.downloader.start_background_download(pools);
+        self.next()
+    }
+
+    /// Skip to the next track of the current preset.
+    // Named for the media key, not an iterator
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<()> {
+        if self.preset.is_none() {
+            return Err(anyhow!("No preset is playing"));
+        }
+        if self.playlist_index >= self.playlist.len() {
+            self.reshuffle();
+        }
+        let track = *self
+            .playlist
+            .get(self.playlist_index)
+            .ok_or_else(|| anyhow!("No tracks available"))?;
+        self.playlist_index += 1;
+        self.play_track(track)
+    }
+
+    /// Advance past finished tracks and report download progress.
+    pub fn update(&mut self) {
+        if self.current.is_some() && self.audio.is_finished() && !self.decoder.is_running() {
+            if let Err(e) = self.next() {
+                self.emit(PlayerEvent::Error(format!("{:#}", e)));
+            }
+        }
+        self.report_downloads();
+    }
+
+    pub fn pause(&self) {
+        self.audio.set_paused(true);
+    }
+
+    pub fn resume(&self) {
+        self.audio.set_paused(false);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current.is_some() && self.audio.is_playing()
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.audio.volume()
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.audio.set_volume(volume.clamp(0.0, 1.0));
+    }
+
+    pub fn current_track(&self) -> Option<&'static Track> {
+        self.current
+    }
+
+    pub fn preset(&self) -> Option<&'static Preset> {
+        self.preset
+    }
+
+    /// New playlist from the cached tracks, starting away from the current one.
+    fn reshuffle(&mut self) {
+        let Some(preset) = self.preset else {
+            return;
+        };
+        let recent: Vec<&'static Track> = self.current.into_iter().collect();
+        self.playlist = self.loader.create_weighted_playlist(preset.pool_weights, &recent);
+        self.playlist_index = 0;
+    }
+
+    fn play_track(&mut self, track: &'static Track) -> Result<()> {
+        let path = self.loader.get_track_path(track);
+        let backend = match self.audio.init_buffer() {
+            Ok(backend) => backend,
+            Err(e) => {
+                // The old job's buffer is going nowhere
+                self.decoder.stop();
+                return Err(e);
+            }
+        };
+        let finished = self.audio.finished_flag();
+        let token = self.decoder.start(&path, backend, finished)?;
+        self.audio.set_decode_token(token);
+        self.current = Some(track);
+        log::info!("Now playing: {}", track.name);
+
+        self.emit(PlayerEvent::TrackChanged {
+            slug: track.slug.to_string(),
+            name: track.name.to_string(),
+            preset: self.preset.map(|p| p.name).unwrap_or_default().to_string(),
+        });
+        Ok(())
+    }
+
+    /// Emit an event for each download that finished since the last call.
+    fn report_downloads(&mut self) {
+        let history = self.downloader.history();
+        self.reported.resize(history.len(), DownloadState::InProgress);
+        for (entry, reported) in history.into_iter().zip(&mut self.reported) {
+            if *reported != DownloadState::InProgress || entry.state == DownloadState::InProgress {
+                continue;
+            }
+            *reported = entry.state.clone();
+            let event = match entry.state {
+                DownloadState::Completed => PlayerEvent::Downloaded { track: entry.track_name },
+                DownloadState::Failed(error) => PlayerEvent::DownloadFailed {
+                    track: entry.track_name,
+                    error,
+                },
+                DownloadState::InProgress | DownloadState::Interrupted => continue,
+            };
+            let _ = self.events_tx.send(event);
+        }
+    }
+
+    fn emit(&self, event: PlayerEvent) {
+        // Our own receiver keeps the channel open, so this can't fail
+        let _ = self.events_tx.send(event);
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.decoder.stop();
+        self.audio.stop();
+    }
+}
@@ -1,44 +1,329 @@
 //! Preset definitions for Fomu.
 
+use std::sync::OnceLock;
+
+use chrono::NaiveTime;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::tracks::catalog::custom_tracks;
 use crate::tracks::TrackPool;
 
 #[derive(Debug, Clone)]
 pub struct Preset {
     pub name: &'static str,
-    pub pools: &'static [TrackPool],
+    /// One-line summary shown in the preset picker
+    pub description: &'static str,
+    /// Pools to draw from, each with a relative weight
+    pub pool_weights: &'static [(TrackPool, f32)],
+    /// Volume to switch to with this preset, from the config file
+    pub volume: Option<f32>,
+    /// Visualizer style to switch to with this preset, from the config file
+    pub visualizer: Option<&'static str>,
+}
+
+impl Preset {
+    /// Pools without their weights.
+    pub fn pool_list(&self) -> Vec<TrackPool> {
+        self.pool_weights.iter().map(|(pool, _)| *pool).collect()
+    }
+
+    /// Whether every pool is equally likely, i.e. a plain shuffle.
+    pub fn has_equal_weights(&self) -> bool {
+        self.pool_weights.windows(2).all(|w| w[0].1 == w[1].1)
+    }
+
+    /// Pool names, with weights when they differ (e.g. `calm-focus ×2, atmospheric ×1`).
+    pub fn pools_label(&self) -> String {
+        let equal_weights = self.has_equal_weights();
+        let names: Vec<String> = self
+            .pool_weights
+            .iter()
+            .map(|(pool, weight)| match equal_weights {
+                true => pool.name().to_string(),
+                false => format!("{} ×{}", pool.name(), weight),
+            })
+            .collect();
+        names.join(", ")
+    }
 }
 
 pub static PRESETS: &[Preset] = &[
     Preset {
         name: "focus",
-        pools: &[TrackPool::Atmospheric, TrackPool::CalmFocus],
+        description: "atmospheric + calm, ideal for coding and writing",
+        pool_weights: &[(TrackPool::Atmospheric, 1.0), (TrackPool::CalmFocus, 1.0)],
+        volume: None,
+        visualizer: None,
     },
     Preset {
         name: "deep",
-        pools: &[TrackPool::CalmFocus, TrackPool::Atmospheric],
+        description: "calm first, for reading and research",
+        pool_weights: &[(TrackPool::CalmFocus, 1.0), (TrackPool::Atmospheric, 1.0)],
+        volume: None,
+        visualizer: None,
     },
     Preset {
         name: "creative",
-        pools: &[TrackPool::Atmospheric, TrackPool::GentleMovement],
+        description: "atmospheric + gentle movement, for brainstorming",
+        pool_weights: &[(TrackPool::Atmospheric, 1.0), (TrackPool::GentleMovement, 1.0)],
+        volume: None,
+        visualizer: None,
     },
     Preset {
         name: "flow",
-        pools: &[TrackPool::CalmFocus, TrackPool::Atmospheric],
+        description: "calm + atmospheric, for sustained creative work",
+        pool_weights: &[(TrackPool::CalmFocus, 1.0), (TrackPool::Atmospheric, 1.0)],
+        volume: None,
+        visualizer: None,
     },
     Preset {
         name: "relax",
-        pools: &[TrackPool::CalmFocus],
+        description: "calm only, for unwinding",
+        pool_weights: &[(TrackPool::CalmFocus, 1.0)],
+        volume: None,
+        visualizer: None,
     },
     Preset {
         name: "morning",
-        pools: &[TrackPool::GentleMovement, TrackPool::Atmospheric],
+        description: "gentle movement + atmospheric, for waking up",
+        pool_weights: &[(TrackPool::GentleMovement, 1.0), (TrackPool::Atmospheric, 1.0)],
+        volume: None,
+        visualizer: None,
     },
 ];
 
+/// Preset playing the tracks from `--pool-file`.
+pub static CUSTOM_PRESET: Preset = Preset {
+    name: "custom",
+    description: "your own tracks from --pool-file",
+    pool_weights: &[(TrackPool::Custom, 1.0)],
+    volume: None,
+    visualizer: None,
+};
+
+/// Per-preset settings from the config file's `[presets.<name>]` tables.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetSettings {
+    pub volume: Option<f32>,
+    pub visualizer: Option<String>,
+    /// Pools and weights replacing the preset's own
+    pub pools: Option<Vec<PoolWeight>>,
+}
+
+/// One entry of a preset's `pools` list, e.g. `{ pool = "CalmFocus", weight = 3 }`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoolWeight {
+    pub pool: TrackPool,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+static PRESET_SETTINGS: OnceLock<Vec<(String, PresetSettings)>> = OnceLock::new();
+
+/// Register per-preset settings. Must be called before `all_presets`;
+/// only the first call has an effect.
+pub fn register_preset_settings(settings: Vec<(String, PresetSettings)>) {
+    let _ = PRESET_SETTINGS.set(settings);
+}
+
+static ALL_PRESETS: OnceLock<&'static [Preset]> = OnceLock::new();
+
+/// Built-in presets, plus `custom` when custom tracks are registered, with
+/// any registered settings applied.
+pub fn all_presets() -> &'static [Preset] {
+    ALL_PRESETS.get_or_init(|| {
+        let settings = PRESET_SETTINGS.get().map(Vec::as_slice).unwrap_or_default();
+        if custom_tracks().is_empty() && settings.is_empty() {
+            return PRESETS;
+        }
+
+        let mut presets = PRESETS.to_vec();
+        if !custom_tracks().is_empty() {
+            presets.push(CUSTOM_PRESET.clone());
+        }
+        for (name, settings) in settings {
+            if let Some(preset) = presets.iter_mut().find(|p| p.name == name) {
+                preset.volume = settings.volume;
+                preset.visualizer = settings
+                    .visualizer
+                    .clone()
+                    .map(|style| &*Box::leak(style.into_boxed_str()));
+                if let Some(pools) = &settings.pools {
+                    let weights: Vec<(TrackPool, f32)> = pools.iter().map(|p| (p.pool, p.weight)).collect();
+                    preset.pool_weights = Box::leak(weights.into_boxed_slice());
+                }
+            }
+        }
+        Box::leak(presets.into_boxed_slice())
+    })
+}
+
 pub fn get_preset(name: &str) -> Option<&'static Preset> {
-    PRESETS.iter().find(|p| p.name == name)
+    all_presets().iter().find(|p| p.name == name)
 }
 
 pub fn get_preset_names() -> Vec<&'static str> {
-    PRESETS.iter().map(|p| p.name).collect()
+    all_presets().iter().map(|p| p.name).collect()
+}
+
+/// `--preset` value that picks a preset from the time of day.
+pub const AUTO_PRESET: &str = "auto";
+
+/// `--preset` value that picks a random preset each run.
+pub const RANDOM_PRESET: &str = "random";
+
+/// Preset for `--preset random`, drawn from `rng`.
+pub fn random_preset(rng: &mut impl Rng) -> &'static str {
+    all_presets().choose(rng).map_or(PRESETS[0].name, |p| p.name)
+}
+
+/// Preset to use from a time of day until the next entry.
+#[derive(Debug, Clone)]
+pub struct ScheduleEntry {
+    pub from: NaiveTime,
+    pub preset: &'static str,
+}
+
+/// Built-in `--preset auto` schedule.
+pub fn default_schedule() -> Vec<ScheduleEntry> {
+    let at = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or_default();
+    vec![
+        ScheduleEntry {
+            from: at(6),
+            preset: "morning",
+        },
+        ScheduleEntry {
+            from: at(11),
+            preset: "focus",
+        },
+        ScheduleEntry {
+            from: at(21),
+            preset: "relax",
+        },
+    ]
+}
+
+static SCHEDULE: OnceLock<Vec<ScheduleEntry>> = OnceLock::new();
+
+/// Replace the built-in schedule with the config file's `[[schedule]]`
+/// entries. Only the first call has an effect.
+pub fn register_schedule(schedule: Vec<ScheduleEntry>) {
+    let _ = SCHEDULE.set(schedule);
+}
+
+/// The `--preset auto` schedule: the config file's, or the built-in one.
+pub fn auto_schedule() -> Vec<ScheduleEntry> {
+    SCHEDULE.get().cloned().unwrap_or_else(default_schedule)
+}
+
+/// Preset whose schedule entry covers `now`.
+///
+/// Before the earliest entry, the latest one still applies (it wraps past
+/// midnight). An empty schedule falls back to the first preset.
+pub fn resolve_auto_preset(now: NaiveTime, schedule: &[ScheduleEntry]) -> &'static str {
+    let latest_before = schedule
+        .iter()
+        .filter(|e| e.from <= now)
+        .max_by_key(|e| e.from);
+    latest_before
+        .or_else(|| schedule.iter().max_by_key(|e| e.from))
+        .map(|e| e.preset)
+        .unwrap_or(PRESETS[0].name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn entry(hour: u32, minute: u32, preset: &'static str) -> ScheduleEntry {
+        ScheduleEntry {
+            from: at(hour, minute),
+            preset,
+        }
+    }
+
+    #[test]
+    fn entry_starts_at_its_time() {
+        let schedule = default_schedule();
+        assert_eq!(resolve_auto_preset(at(10, 59), &schedule), "morning");
+        assert_eq!(resolve_auto_preset(at(11, 0), &schedule), "focus");
+        assert_eq!(resolve_auto_preset(at(20, 59), &schedule), "focus");
+        assert_eq!(resolve_auto_preset(at(21, 0), &schedule), "relax");
+        assert_eq!(resolve_auto_preset(at(6, 0), &schedule), "morning");
+    }
+
+    #[test]
+    fn before_the_first_entry_the_last_one_still_applies() {
+        let schedule = default_schedule();
+        assert_eq!(resolve_auto_preset(at(5, 59), &schedule), "relax");
+        assert_eq!(resolve_auto_preset(at(3, 0), &schedule), "relax");
+    }
+
+    #[test]
+    fn wraps_around_midnight() {
+        let schedule = default_schedule();
+        assert_eq!(resolve_auto_preset(at(23, 59), &schedule), "relax");
+        assert_eq!(resolve_auto_preset(at(0, 0), &schedule), "relax");
+
+        // An entry starting after midnight ends the previous evening's one
+        let night = [entry(22, 0, "relax"), entry(2, 30, "deep"), entry(9, 0, "focus")];
+        assert_eq!(resolve_auto_preset(at(23, 0), &night), "relax");
+        assert_eq!(resolve_auto_preset(at(1, 0), &night), "relax");
+        assert_eq!(resolve_auto_preset(at(2, 30), &night), "deep");
+        assert_eq!(resolve_auto_preset(at(8, 59), &night), "deep");
+    }
+
+    #[test]
+    fn entries_need_not_be_sorted() {
+        let schedule = [entry(21, 0, "relax"), entry(6, 0, "morning"), entry(11, 0, "focus")];
+        assert_eq!(resolve_auto_preset(at(12, 0), &schedule), "focus");
+        assert_eq!(resolve_auto_preset(at(4, 0), &schedule), "relax");
+    }
+
+    #[test]
+    fn single_entry_covers_the_whole_day() {
+        let schedule = [entry(9, 0, "deep")];
+        assert_eq!(resolve_auto_preset(at(8, 0), &schedule), "deep");
+        assert_eq!(resolve_auto_preset(at(9, 0), &schedule), "deep");
+    }
+
+    #[test]
+    fn empty_schedule_falls_back_to_the_first_preset() {
+        assert_eq!(resolve_auto_preset(at(12, 0), &[]), PRESETS[0].name);
+    }
+
+    #[test]
+    fn random_preset_is_reproducible_for_a_seed() {
+        for seed in 0..20 {
+            let first = random_preset(&mut StdRng::seed_from_u64(seed));
+            let again = random_preset(&mut StdRng::seed_from_u64(seed));
+            assert_eq!(first, again);
+        }
+    }
+
+    #[test]
+    fn random_preset_reaches_every_preset() {
+        let mut rng = StdRng::seed_from_u64(69);
+        let picked: HashSet<&str> = (0..200).map(|_| random_preset(&mut rng)).collect();
+        let names: HashSet<&str> = get_preset_names().into_iter().collect();
+        assert_eq!(picked, names);
+        assert!(!picked.contains(AUTO_PRESET) && !picked.contains(RANDOM_PRESET));
+    }
 }
@@ -0,0 +1,41 @@
+//! Where playback stopped, so the next launch can pick the track up again.
+//!
+//! Saved as `resume.json` in the data directory on exit and with the
+//! periodic stats save, so a crash loses at most a minute.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::tracks::loader::{get_data_dir, write_atomic};
+
+/// The track playing when fomu last stopped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumePoint {
+    pub preset: String,
+    pub slug: String,
+    /// Seconds into the track
+    pub position: f64,
+    /// Size of the track file, so a file replaced since isn't resumed into
+    pub size: u64,
+}
+
+impl ResumePoint {
+    /// Default resume file location.
+    pub fn default_path() -> PathBuf {
+        get_data_dir().join("resume.json")
+    }
+
+    /// Load the saved point, if there is a readable one.
+    pub fn load(path: &Path) -> Option<Self> {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+    }
+
+    /// Write the point to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, serde_json::to_string_pretty(self)?)
+    }
+}
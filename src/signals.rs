@@ -0,0 +1,55 @@
+//! Unix signal handling for scripted control.
+//!
+//! `SIGUSR1` toggles pause, `SIGUSR2` skips to the next track, and
+//! `SIGTERM`/`SIGHUP` quit through the normal shutdown path. Signals are
+//! turned into `Command`s on a watcher thread and applied in `run_loop`.
+
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use anyhow::Result;
+use signal_hook::consts::{SIGHUP, SIGTERM, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::{Handle, Signals};
+
+use crate::app::Command;
+
+/// Background thread translating signals into commands.
+pub struct SignalHandler {
+    handle: Handle,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl SignalHandler {
+    /// Register handlers, forwarding signals to `commands`.
+    pub fn start(commands: Sender<Command>) -> Result<Self> {
+        let mut signals = Signals::new([SIGUSR1, SIGUSR2, SIGTERM, SIGHUP])?;
+        let handle = signals.handle();
+
+        let thread_handle = thread::spawn(move || {
+            for signal in signals.forever() {
+                let command = match signal {
+                    SIGUSR1 => Command::TogglePause,
+                    SIGUSR2 => Command::Next,
+                    _ => Command::Quit,
+                };
+                if commands.send(command).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            handle,
+            thread_handle: Some(thread_handle),
+        })
+    }
+}
+
+impl Drop for SignalHandler {
+    fn drop(&mut self) {
+        self.handle.close();
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Listening statistics persisted across sessions.
+//!
+//! Seconds of actual playback are bucketed by local date, then by preset
+//! and track slug, in `stats.json` in the data directory.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{Days, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::tracks::catalog::all_tracks;
+use crate::tracks::loader::{get_data_dir, write_atomic};
+
+/// Listening seconds for one day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DayStats {
+    #[serde(default)]
+    pub presets: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub tracks: BTreeMap<String, f64>,
+}
+
+impl DayStats {
+    fn merge(&mut self, other: &DayStats) {
+        for (preset, secs) in &other.presets {
+            *self.presets.entry(preset.clone()).or_default() += secs;
+        }
+        for (slug, secs) in &other.tracks {
+            *self.tracks.entry(slug.clone()).or_default() += secs;
+        }
+    }
+
+    /// Total listening seconds.
+    pub fn total(&self) -> f64 {
+        self.presets.values().sum()
+    }
+
+    /// Presets sorted by listening time, longest first.
+    pub fn top_presets(&self) -> Vec<(&str, f64)> {
+        sorted_desc(&self.presets)
+    }
+
+    /// Tracks sorted by listening time, longest first.
+    pub fn top_tracks(&self) -> Vec<(&str, f64)> {
+        sorted_desc(&self.tracks)
+    }
+}
+
+fn sorted_desc(map: &BTreeMap<String, f64>) -> Vec<(&str, f64)> {
+    let mut entries: Vec<(&str, f64)> = map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    entries.sort_by(|a, b| b.1.total_cmp(&a.1));
+    entries
+}
+
+/// All recorded listening, keyed by `YYYY-MM-DD`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    #[serde(default)]
+    days: BTreeMap<NaiveDate, DayStats>,
+}
+
+impl Stats {
+    /// Default stats file location.
+    pub fn default_path() -> PathBuf {
+        get_data_dir().join("stats.json")
+    }
+
+    /// Load stats, starting fresh if the file is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write stats to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Add listening time for today.
+    pub fn add(&mut self, preset: &str, track_slug: &str, secs: f64) {
+        let day = self.days.entry(Local::now().date_naive()).or_default();
+        *day.presets.entry(preset.to_string()).or_default() += secs;
+        *day.tracks.entry(track_slug.to_string()).or_default() += secs;
+    }
+
+    /// Today's listening.
+    pub fn today(&self) -> DayStats {
+        self.days
+            .get(&Local::now().date_naive())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Listening over the last `days` days, including today.
+    pub fn last_days(&self, days: u64) -> DayStats {
+        let today = Local::now().date_naive();
+        let since = today.checked_sub_days(Days::new(days.saturating_sub(1)));
+        let mut total = DayStats::default();
+        for (_, day) in self.days.range(since.unwrap_or(NaiveDate::MIN)..) {
+            total.merge(day);
+        }
+        total
+    }
+
+    /// All recorded listening.
+    pub fn all_time(&self) -> DayStats {
+        let mut total = DayStats::default();
+        for day in self.days.values() {
+            total.merge(day);
+        }
+        total
+    }
+}
+
+/// Display name for a track slug, falling back to the slug itself.
+pub fn track_display_name(slug: &str) -> &str {
+    all_tracks()
+        .find(|t| t.slug == slug)
+        .map(|t| t.name)
+        .unwrap_or(slug)
+}
+
+/// Format seconds as `2h 14m` (or `5m` under an hour).
+pub fn format_hours_minutes(secs: f64) -> String {
+    let mins = (secs / 60.0) as u64;
+    if mins >= 60 {
+        format!("{}h {}m", mins / 60, mins % 60)
+    } else {
+        format!("{}m", mins)
+    }
+}
+
+/// One-line summary printed on exit.
+pub fn session_summary(stats: &Stats) -> Option<String> {
+    let today = stats.today();
+    if today.total() < 1.0 {
+        return None;
+    }
+
+    let mut parts = vec![format!("Today: {}", format_hours_minutes(today.total()))];
+    if let Some((preset, secs)) = today.top_presets().first() {
+        parts.push(format!("{} {}", preset, format_hours_minutes(*secs)));
+    }
+    if let Some((slug, _)) = today.top_tracks().first() {
+        parts.push(format!("most played: {}", track_display_name(slug)));
+    }
+    Some(parts.join(" · "))
+}
+
+/// Print all-time and last-7-days aggregates for `--stats`.
+pub fn print_stats(stats: &Stats) {
+    for (label, period) in [("Last 7 days", stats.last_days(7)), ("All time", stats.all_time())] {
+        println!("{}: {}", label, format_hours_minutes(period.total()));
+        for (preset, secs) in period.top_presets() {
+            println!("  {:<12} {:>8}", preset, format_hours_minutes(secs));
+        }
+        let top_tracks = period.top_tracks();
+        if !top_tracks.is_empty() {
+            println!("  Top tracks:");
+            for (slug, secs) in top_tracks.iter().take(5) {
+                println!(
+                    "    {:<24} {:>8}",
+                    track_display_name(slug),
+                    format_hours_minutes(*secs)
+                );
+            }
+        }
+        println!();
+    }
+}
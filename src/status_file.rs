@@ -0,0 +1,88 @@
+//! `--status-file`: now-playing JSON for status bars such as waybar or
+//! polybar to poll.
+//!
+//! The file is replaced atomically whenever what it says changes, which
+//! is at most once a second for the position, and removed on exit so a
+//! bar can tell fomu stopped.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::tracks::loader::write_atomic;
+
+/// Least time between two writes, so holding a volume key doesn't write
+/// on every repeat. A change that arrives sooner is written on a later tick.
+const MIN_WRITE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What the status file holds.
+#[derive(Debug, Serialize)]
+pub struct NowPlaying {
+    pub track: Option<String>,
+    pub artist: Option<String>,
+    pub preset: String,
+    pub paused: bool,
+    pub muted: bool,
+    /// Position in the current track (whole seconds)
+    pub position: u64,
+    pub volume: f32,
+}
+
+pub struct StatusFile {
+    path: PathBuf,
+    /// Contents last written, to skip writes that change nothing
+    written: String,
+    written_at: Option<Instant>,
+    /// Whether a failed write has been logged
+    warned: bool,
+}
+
+impl StatusFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            written: String::new(),
+            written_at: None,
+            warned: false,
+        }
+    }
+
+    /// Write `status` if it changed since the last write and the last write
+    /// wasn't too recent.
+    pub fn update(&mut self, status: &NowPlaying) {
+        if self.written_at.is_some_and(|at| at.elapsed() < MIN_WRITE_INTERVAL) {
+            return;
+        }
+        let Ok(json) = serde_json::to_string(status) else {
+            return;
+        };
+        if json == self.written {
+            return;
+        }
+        match self.write(&json) {
+            Ok(()) => {
+                self.written = json;
+                self.written_at = Some(Instant::now());
+            }
+            Err(e) if !self.warned => {
+                self.warned = true;
+                log::warn!("Could not write status file: {:#}", e);
+            }
+            Err(_) => {}
+        }
+    }
+
+    fn write(&self, json: &str) -> Result<()> {
+        write_atomic(&self.path, format!("{}\n", json))
+    }
+}
+
+impl Drop for StatusFile {
+    fn drop(&mut self) {
+        if self.written_at.is_some() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
@@ -1,10 +1,78 @@
 //! Track catalog with all Scott Buckley tracks metadata.
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Which pool a track belongs to. Stored in config and data files by its
+/// variant name (`CalmFocus`), see `serialize_name`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TrackPool {
     CalmFocus,
     Atmospheric,
     GentleMovement,
+    /// User-supplied tracks from `--pool-file`
+    Custom,
+    /// Long nature recordings looped under the music
+    Soundscape,
+}
+
+impl TrackPool {
+    /// Human-readable pool name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrackPool::CalmFocus => "calm-focus",
+            TrackPool::Atmospheric => "atmospheric",
+            TrackPool::GentleMovement => "gentle-movement",
+            TrackPool::Custom => "custom",
+            TrackPool::Soundscape => "soundscape",
+        }
+    }
+
+    /// Every pool, in catalog order.
+    pub const ALL: [TrackPool; 5] = [
+        TrackPool::CalmFocus,
+        TrackPool::Atmospheric,
+        TrackPool::GentleMovement,
+        TrackPool::Custom,
+        TrackPool::Soundscape,
+    ];
+
+    /// Name used in config and data files, matching the serde form.
+    pub fn serialize_name(&self) -> &'static str {
+        match self {
+            TrackPool::CalmFocus => "CalmFocus",
+            TrackPool::Atmospheric => "Atmospheric",
+            TrackPool::GentleMovement => "GentleMovement",
+            TrackPool::Custom => "Custom",
+            TrackPool::Soundscape => "Soundscape",
+        }
+    }
+}
+
+impl fmt::Display for TrackPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.serialize_name())
+    }
+}
+
+impl FromStr for TrackPool {
+    type Err = anyhow::Error;
+
+    /// Parse a `serialize_name`, or the human-readable `name` shown in the UI.
+    fn from_str(s: &str) -> Result<Self> {
+        TrackPool::ALL
+            .into_iter()
+            .find(|pool| pool.serialize_name() == s || pool.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = TrackPool::ALL.iter().map(|pool| pool.serialize_name()).collect();
+                anyhow::anyhow!("Unknown track pool '{}' (expected one of {})", s, names.join(", "))
+            })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -13,12 +81,80 @@ pub struct Track {
     pub slug: &'static str,
     pub pool: TrackPool,
     pub download_url: &'static str,
+    /// Tempo in beats per minute, where it's known
+    pub bpm: Option<f32>,
 }
 
 impl Track {
     pub fn filename(&self) -> String {
         format!("{}.mp3", self.slug)
     }
+
+    /// Where to download the track from.
+    ///
+    /// `FOMU_TRACKS_BASE_URL` swaps the host and path of catalog URLs,
+    /// keeping the file name, so a mirror or a local test server can stand
+    /// in for scottbuckley.com.au. Custom tracks always use their own URL.
+    pub fn resolved_url(&self) -> String {
+        self.url_from(std::env::var("FOMU_TRACKS_BASE_URL").ok().as_deref())
+    }
+
+    /// The download URL with `base` in place of its host and path, like
+    /// `resolved_url` does for `FOMU_TRACKS_BASE_URL`.
+    pub fn url_from(&self, base: Option<&str>) -> String {
+        match base.filter(|b| !b.is_empty()) {
+            Some(base) if self.pool != TrackPool::Custom => {
+                let file = self.download_url.rsplit('/').next().unwrap_or(self.download_url);
+                format!("{}/{}", base.trim_end_matches('/'), file)
+            }
+            _ => self.download_url.to_string(),
+        }
+    }
+
+    /// Artist credit, unknown for custom tracks.
+    pub fn artist(&self) -> Option<&'static str> {
+        match self.pool {
+            TrackPool::Custom | TrackPool::Soundscape => None,
+            _ => Some("Scott Buckley"),
+        }
+    }
+}
+
+/// Playlist order other than a shuffle (`--sort-by`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistSort {
+    /// Slowest first, for a gradual energy ramp
+    BpmAscending,
+    /// Fastest first
+    BpmDescending,
+}
+
+impl PlaylistSort {
+    /// Names accepted by `--sort-by`.
+    pub const NAMES: &'static [&'static str] = &["bpm-asc", "bpm-desc"];
+
+    /// Look up an order by its `--sort-by` name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "bpm-asc" => Some(Self::BpmAscending),
+            "bpm-desc" => Some(Self::BpmDescending),
+            _ => None,
+        }
+    }
+
+    /// Sort `tracks` in place. Tracks without a tempo go last, keeping
+    /// their order.
+    pub fn sort(&self, tracks: &mut [&Track]) {
+        tracks.sort_by(|a, b| match (a.bpm, b.bpm) {
+            (Some(a), Some(b)) => match self {
+                PlaylistSort::BpmAscending => a.total_cmp(&b),
+                PlaylistSort::BpmDescending => b.total_cmp(&a),
+            },
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+    }
 }
 
 pub static TRACK_CATALOG: &[Track] = &[
@@ -28,42 +164,49 @@ pub static TRACK_CATALOG: &[Track] = &[
         slug: "permafrost",
         pool: TrackPool::CalmFocus,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2022/08/Permafrost.mp3",
+        bpm: None,
     },
     Track {
         name: "Petrichor",
         slug: "petrichor",
         pool: TrackPool::CalmFocus,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2019/05/sb_petrichor.mp3",
+        bpm: None,
     },
     Track {
         name: "Borealis",
         slug: "borealis",
         pool: TrackPool::CalmFocus,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2019/09/sb_borealis.mp3",
+        bpm: None,
     },
     Track {
         name: "She Moved Mountains",
         slug: "she-moved-mountains",
         pool: TrackPool::CalmFocus,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2014/07/sb_shemovedmountains.mp3",
+        bpm: None,
     },
     Track {
         name: "Reverie",
         slug: "reverie",
         pool: TrackPool::CalmFocus,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2020/03/sb_reverie.mp3",
+        bpm: None,
     },
     Track {
         name: "Cobalt",
         slug: "cobalt",
         pool: TrackPool::CalmFocus,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2017/11/sb_cobalt.mp3",
+        bpm: None,
     },
     Track {
         name: "Life Is",
         slug: "life-is",
         pool: TrackPool::CalmFocus,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2017/10/sb_lifeis.mp3",
+        bpm: None,
     },
     // Pool: Atmospheric
     Track {
@@ -71,36 +214,42 @@ pub static TRACK_CATALOG: &[Track] = &[
         slug: "shadows-and-dust",
         pool: TrackPool::Atmospheric,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2023/11/ShadowsAndDust.mp3",
+        bpm: None,
     },
     Track {
         name: "Decoherence",
         slug: "decoherence",
         pool: TrackPool::Atmospheric,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2022/03/sb_decoherence.mp3",
+        bpm: None,
     },
     Track {
         name: "Aurora",
         slug: "aurora",
         pool: TrackPool::Atmospheric,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2021/10/Aurora.mp3",
+        bpm: None,
     },
     Track {
         name: "Hymn to the Dawn",
         slug: "hymn-to-the-dawn",
         pool: TrackPool::Atmospheric,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2022/11/HymnToTheDawn.mp3",
+        bpm: None,
     },
     Track {
         name: "Cirrus",
         slug: "cirrus",
         pool: TrackPool::Atmospheric,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2023/03/Cirrus.mp3",
+        bpm: None,
     },
     Track {
         name: "Meanwhile",
         slug: "meanwhile",
         pool: TrackPool::Atmospheric,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2025/01/Meanwhile.mp3",
+        bpm: None,
     },
     // Pool: GentleMovement
     Track {
@@ -108,42 +257,150 @@ pub static TRACK_CATALOG: &[Track] = &[
         slug: "cicadas",
         pool: TrackPool::GentleMovement,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2023/12/Cicadas.mp3",
+        bpm: None,
     },
     Track {
         name: "Effervescence",
         slug: "effervescence",
         pool: TrackPool::GentleMovement,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2023/07/Effervescence.mp3",
+        bpm: None,
     },
     Track {
         name: "Golden Hour",
         slug: "golden-hour",
         pool: TrackPool::GentleMovement,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2023/02/GoldenHour.mp3",
+        bpm: None,
     },
     Track {
         name: "Castles in the Sky",
         slug: "castles-in-the-sky",
         pool: TrackPool::GentleMovement,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2021/11/sb_castlesinthesky.mp3",
+        bpm: None,
     },
     Track {
         name: "First Snow",
         slug: "first-snow",
         pool: TrackPool::GentleMovement,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2022/12/FirstSnow.mp3",
+        bpm: None,
     },
     Track {
         name: "Snowfall",
         slug: "snowfall",
         pool: TrackPool::GentleMovement,
         download_url: "https://www.scottbuckley.com.au/library/wp-content/uploads/2018/12/sb_snowfall.mp3",
+        bpm: None,
     },
 ];
 
-pub fn get_tracks_by_pools(pools: &[TrackPool]) -> Vec<&'static Track> {
-    TRACK_CATALOG
+/// CC0 nature recordings for the soundscape layer, in the order `g` cycles
+/// through them. They're hosted with fomu's releases rather than on
+/// scottbuckley.com.au.
+pub static SOUNDSCAPES: &[Track] = &[
+    Track {
+        name: "Rain",
+        slug: "soundscape-rain",
+        pool: TrackPool::Soundscape,
+        download_url: "https://github.com/yuya-fujimoto/fomu.cli/releases/download/soundscapes-v1/soundscape-rain.mp3",
+        bpm: None,
+    },
+    Track {
+        name: "Forest",
+        slug: "soundscape-forest",
+        pool: TrackPool::Soundscape,
+        download_url: "https://github.com/yuya-fujimoto/fomu.cli/releases/download/soundscapes-v1/soundscape-forest.mp3",
+        bpm: None,
+    },
+    Track {
+        name: "Stream",
+        slug: "soundscape-stream",
+        pool: TrackPool::Soundscape,
+        download_url: "https://github.com/yuya-fujimoto/fomu.cli/releases/download/soundscapes-v1/soundscape-stream.mp3",
+        bpm: None,
+    },
+];
+
+/// Soundscape by name (`rain`) or slug, ignoring case.
+pub fn get_soundscape(name: &str) -> Option<&'static Track> {
+    SOUNDSCAPES
         .iter()
+        .find(|t| t.name.eq_ignore_ascii_case(name) || t.slug == name)
+}
+
+/// Custom tracks registered at startup from `--pool-file`.
+static CUSTOM_TRACKS: OnceLock<&'static [Track]> = OnceLock::new();
+
+/// Register custom tracks. Must be called before any catalog lookups;
+/// only the first call has an effect.
+pub fn register_custom_tracks(tracks: Vec<Track>) {
+    let _ = CUSTOM_TRACKS.set(Box::leak(tracks.into_boxed_slice()));
+}
+
+/// Custom tracks, empty unless `register_custom_tracks` was called.
+pub fn custom_tracks() -> &'static [Track] {
+    CUSTOM_TRACKS.get().copied().unwrap_or(&[])
+}
+
+/// Built-in catalog followed by any custom tracks, then the soundscapes.
+pub fn all_tracks() -> impl Iterator<Item = &'static Track> {
+    TRACK_CATALOG.iter().chain(custom_tracks()).chain(SOUNDSCAPES)
+}
+
+/// Parse a pool file: one MP3 URL per line, `#` starts a comment.
+pub fn load_pool_file(path: &Path) -> Result<Vec<Track>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read pool file {:?}", path))?;
+
+    let mut tracks: Vec<Track> = Vec::new();
+    for line in contents.lines() {
+        let url = line.split('#').next().unwrap_or("").trim();
+        if url.is_empty() || tracks.iter().any(|t| t.download_url == url) {
+            continue;
+        }
+
+        let basename = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|u| u.rsplit('/').next())
+            .unwrap_or(url);
+        let name = basename
+            .strip_suffix(".mp3")
+            .unwrap_or(basename)
+            .replace("%20", " ");
+
+        // Prefix so custom files can't collide with catalog files
+        let base_slug: String = name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let mut slug = format!("custom-{}", base_slug.trim_matches('-'));
+        let mut suffix = 2;
+        while tracks.iter().any(|t| t.slug == slug) {
+            slug = format!("custom-{}-{}", base_slug.trim_matches('-'), suffix);
+            suffix += 1;
+        }
+
+        tracks.push(Track {
+            name: Box::leak(name.into_boxed_str()),
+            slug: Box::leak(slug.into_boxed_str()),
+            pool: TrackPool::Custom,
+            download_url: Box::leak(url.to_string().into_boxed_str()),
+            bpm: None,
+        });
+    }
+
+    if tracks.is_empty() {
+        anyhow::bail!("No URLs found in pool file {:?}", path);
+    }
+    Ok(tracks)
+}
+
+pub fn get_tracks_by_pools(pools: &[TrackPool]) -> Vec<&'static Track> {
+    all_tracks()
         .filter(|t| pools.contains(&t.pool))
         .collect()
 }
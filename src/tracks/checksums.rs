@@ -0,0 +1,136 @@
+//! SHA-256 sidecar for downloaded tracks.
+//!
+//! `checksums.json` in the tracks directory maps each track file to the
+//! hash and length it had when downloaded, so bit rot and truncated
+//! writes can be told apart from a good file.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use ring::digest::{Context as Digest, SHA256};
+use serde::{Deserialize, Serialize};
+
+use super::loader::write_atomic;
+
+/// Sidecar file name inside the tracks directory
+const CHECKSUMS_FILE: &str = "checksums.json";
+
+/// Serializes read-modify-write cycles between download workers
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Hash and length of a track file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checksum {
+    pub sha256: String,
+    pub len: u64,
+}
+
+/// Incremental hasher for data as it's written.
+pub struct Hasher {
+    digest: Digest,
+    len: u64,
+}
+
+impl Hasher {
+    pub fn new() -> Self {
+        Self {
+            digest: Digest::new(&SHA256),
+            len: 0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.digest.update(data);
+        self.len += data.len() as u64;
+    }
+
+    pub fn finish(self) -> Checksum {
+        let sha256 = self
+            .digest
+            .finish()
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Checksum { sha256, len: self.len }
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hash a file on disk.
+pub fn hash_file(path: &Path) -> Result<Checksum> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Hasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).with_context(|| format!("Failed to read {:?}", path))?;
+        if n == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ChecksumFile {
+    #[serde(default)]
+    files: BTreeMap<String, Checksum>,
+}
+
+/// The checksums recorded for one tracks directory.
+pub struct Checksums {
+    path: PathBuf,
+}
+
+impl Checksums {
+    pub fn new(tracks_dir: &Path) -> Self {
+        Self {
+            path: tracks_dir.join(CHECKSUMS_FILE),
+        }
+    }
+
+    /// Checksum recorded for `filename`, if any.
+    pub fn get(&self, filename: &str) -> Option<Checksum> {
+        let _guard = FILE_LOCK.lock().unwrap();
+        self.load().files.remove(filename)
+    }
+
+    /// Record `checksum` for `filename`, replacing any earlier entry.
+    pub fn record(&self, filename: &str, checksum: Checksum) -> Result<()> {
+        let _guard = FILE_LOCK.lock().unwrap();
+        let mut file = self.load();
+        file.files.insert(filename.to_string(), checksum);
+        self.save(&file)
+    }
+
+    /// Drop the entry for `filename`, e.g. after deleting the file.
+    pub fn forget(&self, filename: &str) -> Result<()> {
+        let _guard = FILE_LOCK.lock().unwrap();
+        let mut file = self.load();
+        if file.files.remove(filename).is_some() {
+            self.save(&file)?;
+        }
+        Ok(())
+    }
+
+    /// Load the sidecar, starting empty if it's missing or unreadable.
+    fn load(&self) -> ChecksumFile {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, file: &ChecksumFile) -> Result<()> {
+        write_atomic(&self.path, serde_json::to_string_pretty(file)?)
+    }
+}
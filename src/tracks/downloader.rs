@@ -1,61 +1,238 @@
 //! Track downloading from scottbuckley.com.au
 
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
+use reqwest::blocking::{Client, Response};
+use reqwest::{Certificate, NoProxy, Proxy, StatusCode};
 
 use super::catalog::{Track, TrackPool};
+use super::checksums::{Checksum, Checksums, Hasher};
 use super::loader::{get_tracks_dir, TrackLoader};
+use super::sizes::TrackSizes;
 
+/// Background download batch: the queue and its workers.
 #[derive(Clone, Default)]
 pub struct DownloadProgress {
+    /// Missing tracks of the batch, in queue order
+    pub queue: Vec<QueuedTrack>,
+    /// Workers currently downloading a track
+    pub active_workers: usize,
+    /// Size of the worker pool for the current batch
+    pub workers: usize,
+    /// Bumped for every new batch so stale workers can't touch the queue
+    batch: u64,
+}
+
+impl DownloadProgress {
+    /// Tracks of the batch downloaded so far.
+    pub fn done(&self) -> usize {
+        self.count(|state| *state == QueueState::Done)
+    }
+
+    /// Tracks of the batch whose download failed.
+    pub fn failed(&self) -> usize {
+        self.count(|state| matches!(state, QueueState::Failed(_)))
+    }
+
+    /// Whether tracks are still queued or downloading.
+    pub fn is_active(&self) -> bool {
+        self.count(|state| matches!(state, QueueState::Queued | QueueState::Downloading(_))) > 0
+    }
+
+    fn count(&self, pred: impl Fn(&QueueState) -> bool) -> usize {
+        self.queue.iter().filter(|item| pred(&item.state)).count()
+    }
+
+    /// Set the state of `slug`'s queue item, unless a newer batch replaced it.
+    fn set_state(&mut self, batch: u64, slug: &str, state: QueueState) {
+        if self.batch != batch {
+            return;
+        }
+        if let Some(item) = self.queue.iter_mut().find(|item| item.track.slug == slug) {
+            item.state = state;
+        }
+    }
+
+    /// Claim the first queued track of `batch` for a worker.
+    fn take_next(&mut self, batch: u64) -> Option<&'static Track> {
+        if self.batch != batch {
+            return None;
+        }
+        let item = self.queue.iter_mut().find(|item| item.state == QueueState::Queued)?;
+        item.state = QueueState::Downloading(0.0);
+        Some(item.track)
+    }
+
+    /// Queue `slug` again after everything else in `batch`.
+    fn move_to_end(&mut self, batch: u64, slug: &str) {
+        if self.batch != batch {
+            return;
+        }
+        if let Some(index) = self.queue.iter().position(|item| item.track.slug == slug) {
+            let mut item = self.queue.remove(index);
+            item.state = QueueState::Queued;
+            self.queue.push(item);
+        }
+    }
+}
+
+/// Where a queued track is in its batch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueueState {
+    Queued,
+    /// Downloading, with the fraction received (0.0 until the size is known)
+    Downloading(f32),
+    Done,
+    Failed(String),
+}
+
+/// One track of a background download batch.
+#[derive(Clone, Debug)]
+pub struct QueuedTrack {
+    pub track: &'static Track,
+    pub state: QueueState,
+}
+
+/// State of one download in the history.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DownloadState {
+    InProgress,
+    Completed,
+    Failed(String),
+    /// Stopped partway to make room for higher-priority tracks, skipped
+    /// with `cancel_track`, or stopped on exit
+    Interrupted,
+}
+
+/// One entry in the download history, oldest first.
+#[derive(Clone, Debug)]
+pub struct DownloadEntry {
     pub track_name: String,
-    pub progress: f32,
-    pub completed: bool,
+    pub state: DownloadState,
+    pub started: Instant,
+}
+
+/// Shared download history, appended to by every download.
+#[derive(Clone, Default)]
+struct History(Arc<Mutex<Vec<DownloadEntry>>>);
+
+impl History {
+    /// Record a new in-progress download and return its index.
+    fn start(&self, track: &Track) -> usize {
+        let mut entries = self.0.lock().unwrap();
+        entries.push(DownloadEntry {
+            track_name: track.name.to_string(),
+            state: DownloadState::InProgress,
+            started: Instant::now(),
+        });
+        entries.len() - 1
+    }
+
+    fn finish(&self, index: usize, result: &Result<()>) {
+        self.set_state(
+            index,
+            match result {
+                Ok(()) => DownloadState::Completed,
+                Err(e) => DownloadState::Failed(format!("{:#}", e)),
+            },
+        );
+    }
+
+    fn set_state(&self, index: usize, state: DownloadState) {
+        if let Some(entry) = self.0.lock().unwrap().get_mut(index) {
+            entry.state = state;
+        }
+    }
 }
 
 pub struct TrackDownloader {
+    /// Proxy-aware client shared by all downloads
+    client: Client,
     tracks_dir: PathBuf,
     loader: TrackLoader,
     should_stop: Arc<AtomicBool>,
+    /// Downloads in progress that `cancel_track` asked to skip
+    cancelled_slugs: Arc<Mutex<HashSet<String>>>,
     progress: Arc<Mutex<DownloadProgress>>,
-    thread_handle: Option<thread::JoinHandle<()>>,
+    /// Workers of the current batch and of its retries
+    thread_handles: Vec<thread::JoinHandle<()>>,
+    /// Maximum concurrent background downloads
+    max_downloads: usize,
+    /// Every download started this session
+    history: History,
+    /// Download sizes learned from HEAD requests
+    sizes: TrackSizes,
+    /// Background HEAD pass filling in `sizes`
+    size_probe: Option<thread::JoinHandle<()>>,
+    /// Host and path catalog tracks download from instead of their own
+    base_url: Option<String>,
 }
 
 impl TrackDownloader {
-    pub fn new() -> Self {
+    pub fn new() -> Result<Self> {
+        Ok(Self::with_base(get_tracks_dir(), http_client()?))
+    }
+
+    /// Downloader saving into `tracks_dir` through `client`, e.g. a temp
+    /// directory and a client for a local test server.
+    pub fn with_base(tracks_dir: PathBuf, client: Client) -> Self {
         Self {
-            tracks_dir: get_tracks_dir(),
-            loader: TrackLoader::new(),
+            client,
+            loader: TrackLoader::with_dir(tracks_dir.clone()),
+            tracks_dir,
             should_stop: Arc::new(AtomicBool::new(false)),
+            cancelled_slugs: Arc::new(Mutex::new(HashSet::new())),
             progress: Arc::new(Mutex::new(DownloadProgress::default())),
-            thread_handle: None,
+            thread_handles: Vec::new(),
+            max_downloads: 1,
+            history: History::default(),
+            sizes: TrackSizes::load(&TrackSizes::default_path()),
+            size_probe: None,
+            base_url: std::env::var("FOMU_TRACKS_BASE_URL").ok(),
         }
     }
 
+    /// Download catalog tracks from `base` instead of their own host, like
+    /// `FOMU_TRACKS_BASE_URL` but for this downloader only.
+    pub fn set_base_url(&mut self, base: impl Into<String>) {
+        self.base_url = Some(base.into());
+    }
+
+    /// Set how many tracks background downloads fetch at once.
+    pub fn set_max_downloads(&mut self, max: usize) {
+        self.max_downloads = max.max(1);
+    }
+
     pub fn download_track(&self, track: &Track) -> Result<PathBuf> {
+        self.download_track_with_progress(track, |_| {})
+    }
+
+    /// Download `track` unless it's already cached, reporting the fraction
+    /// received to `on_progress`.
+    pub fn download_track_with_progress(&self, track: &Track, on_progress: impl FnMut(f32)) -> Result<PathBuf> {
         let path = self.tracks_dir.join(track.filename());
         if path.exists() {
             return Ok(path);
         }
 
-        let url = track.download_url;
-        let response = reqwest::blocking::get(url)
-            .with_context(|| format!("Failed to fetch {}", url))?;
-
-        if !response.status().is_success() {
-            anyhow::bail!("HTTP error: {}", response.status());
+        log::info!("Downloading {}", track.name);
+        let entry = self.history.start(track);
+        let url = track.url_from(self.base_url.as_deref());
+        let result = save_track(&self.client, track, &url, &self.tracks_dir, || false, on_progress);
+        self.history.finish(entry, &result);
+        if let Err(e) = result {
+            log::warn!("Download of {} failed: {:#}", track.name, e);
+            return Err(e);
         }
-
-        let bytes = response.bytes().context("Failed to read response bytes")?;
-        let mut file = File::create(&path)
-            .with_context(|| format!("Failed to create file {:?}", path))?;
-        file.write_all(&bytes).context("Failed to write file")?;
+        log::info!("Downloaded {}", track.name);
 
         Ok(path)
     }
@@ -70,75 +247,136 @@ impl TrackDownloader {
         }
     }
 
+    /// Download missing tracks from `pools` on a pool of worker threads.
+    ///
+    /// Replaces the queue with the missing tracks of `pools`, followed by
+    /// whatever the previous batch hadn't finished, so a newly picked
+    /// preset downloads first. Downloads in progress stop and are queued
+    /// again in the new order; see `get_progress`.
     pub fn start_background_download(&mut self, pools: Vec<TrackPool>) {
         self.stop_background_download();
+        self.should_stop = Arc::new(AtomicBool::new(false));
+        self.cancelled_slugs.lock().unwrap().clear();
 
-        let should_stop = Arc::new(AtomicBool::new(false));
-        self.should_stop = Arc::clone(&should_stop);
+        let mut missing = self.loader.get_missing_tracks_from_pools(&pools);
+        let (batch, workers) = {
+            let mut prog = self.progress.lock().unwrap();
+            let unfinished: Vec<&'static Track> = prog
+                .queue
+                .iter()
+                .filter(|item| matches!(item.state, QueueState::Queued | QueueState::Downloading(_)))
+                .map(|item| item.track)
+                .filter(|track| !missing.iter().any(|t| t.slug == track.slug) && !self.loader.track_exists(track))
+                .collect();
+            missing.extend(unfinished);
 
-        let progress = Arc::clone(&self.progress);
-        let tracks_dir = self.tracks_dir.clone();
-
-        let missing: Vec<Track> = self
-            .loader
-            .get_missing_tracks_from_pools(&pools)
-            .into_iter()
-            .cloned()
-            .collect();
+            prog.batch += 1;
+            prog.queue = missing
+                .iter()
+                .map(|&track| QueuedTrack {
+                    track,
+                    state: QueueState::Queued,
+                })
+                .collect();
+            prog.active_workers = 0;
+            prog.workers = self.max_downloads.min(missing.len());
+            (prog.batch, prog.workers)
+        };
 
         if missing.is_empty() {
             return;
         }
+        self.spawn_workers(batch, workers);
+    }
 
-        let handle = thread::spawn(move || {
-            for track in missing {
-                if should_stop.load(Ordering::Relaxed) {
-                    break;
-                }
-
-                {
-                    let mut prog = progress.lock().unwrap();
-                    prog.track_name = track.name.to_string();
-                    prog.progress = 0.0;
-                    prog.completed = false;
-                }
-
-                let path = tracks_dir.join(track.filename());
-                if !path.exists() {
-                    if let Ok(response) = reqwest::blocking::get(track.download_url) {
-                        if response.status().is_success() {
-                            if let Ok(bytes) = response.bytes() {
-                                if let Ok(mut file) = File::create(&path) {
-                                    let _ = file.write_all(&bytes);
-                                }
-                            }
-                        }
-                    }
-                }
+    /// Queue the batch's failed tracks again. Returns how many were retried.
+    pub fn retry_failed(&mut self) -> usize {
+        let (batch, count) = {
+            let mut prog = self.progress.lock().unwrap();
+            let mut count = 0;
+            for item in prog.queue.iter_mut().filter(|item| matches!(item.state, QueueState::Failed(_))) {
+                item.state = QueueState::Queued;
+                count += 1;
+            }
+            (prog.batch, count)
+        };
+        if count == 0 {
+            return 0;
+        }
 
-                {
-                    let mut prog = progress.lock().unwrap();
-                    prog.progress = 1.0;
-                    prog.completed = true;
-                }
+        log::info!("Retrying {} failed downloads", count);
+        let workers = self.max_downloads.min(count);
+        self.progress.lock().unwrap().workers += workers;
+        self.spawn_workers(batch, workers);
+        count
+    }
 
-                thread::sleep(std::time::Duration::from_millis(100));
+    /// Skip the download of `slug`: one in progress stops and the track
+    /// moves to the end of the queue, to be fetched again after the rest.
+    /// A track still waiting just moves to the end. Returns whether the
+    /// track was in the queue and not yet downloaded.
+    pub fn cancel_track(&self, slug: &str) -> bool {
+        let mut prog = self.progress.lock().unwrap();
+        let Some(item) = prog.queue.iter().find(|item| item.track.slug == slug) else {
+            return false;
+        };
+        match item.state {
+            QueueState::Downloading(_) => {
+                self.cancelled_slugs.lock().unwrap().insert(slug.to_string());
+                true
             }
-        });
+            QueueState::Queued => {
+                let batch = prog.batch;
+                prog.move_to_end(batch, slug);
+                true
+            }
+            _ => false,
+        }
+    }
 
-        self.thread_handle = Some(handle);
+    /// Start `workers` threads that download the batch's queued tracks in
+    /// queue order until none are left.
+    fn spawn_workers(&mut self, batch: u64, workers: usize) {
+        self.thread_handles.retain(|handle| !handle.is_finished());
+        for _ in 0..workers {
+            let should_stop = Arc::clone(&self.should_stop);
+            let cancelled_slugs = Arc::clone(&self.cancelled_slugs);
+            let progress = Arc::clone(&self.progress);
+            let tracks_dir = self.tracks_dir.clone();
+            let history = self.history.clone();
+            let client = self.client.clone();
+            let base_url = self.base_url.clone();
+            self.thread_handles.push(thread::spawn(move || {
+                while !should_stop.load(Ordering::Relaxed) {
+                    let Some(track) = progress.lock().unwrap().take_next(batch) else {
+                        break;
+                    };
+                    let job = Job {
+                        batch,
+                        track,
+                        url: track.url_from(base_url.as_deref()),
+                    };
+                    let stop = Stop {
+                        all: &should_stop,
+                        cancelled_slugs: &cancelled_slugs,
+                    };
+                    download_in_background(&client, &job, &tracks_dir, &progress, &history, &stop);
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }));
+        }
     }
 
     pub fn stop_background_download(&mut self) {
         self.should_stop.store(true, Ordering::SeqCst);
-        if let Some(handle) = self.thread_handle.take() {
-            // Wait with timeout - HTTP requests can block
-            let start = std::time::Instant::now();
+        // Wait with timeout - HTTP requests can block
+        let start = Instant::now();
+        for handle in self.thread_handles.drain(..) {
             while !handle.is_finished() {
-                if start.elapsed() > std::time::Duration::from_millis(500) {
+                if start.elapsed() > Duration::from_millis(500) {
                     break;
                 }
-                thread::sleep(std::time::Duration::from_millis(10));
+                thread::sleep(Duration::from_millis(10));
             }
             if handle.is_finished() {
                 let _ = handle.join();
@@ -146,14 +384,293 @@ impl TrackDownloader {
         }
     }
 
+    /// Learn the download sizes of `tracks` on a background thread.
+    ///
+    /// Does nothing while an earlier probe is still running.
+    pub fn probe_sizes(&mut self, tracks: Vec<&'static Track>) {
+        if self.size_probe.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return;
+        }
+        let sizes = self.sizes.clone();
+        let client = self.client.clone();
+        self.size_probe = Some(thread::spawn(move || {
+            if let Err(e) = sizes.probe(&client, &tracks) {
+                log::warn!("Could not save track sizes: {:#}", e);
+            }
+        }));
+    }
+
+    /// Download sizes known so far.
+    pub fn sizes(&self) -> &TrackSizes {
+        &self.sizes
+    }
+
     pub fn get_progress(&self) -> DownloadProgress {
         self.progress.lock().unwrap().clone()
     }
+
+    /// Every download started this session, oldest first.
+    pub fn history(&self) -> Vec<DownloadEntry> {
+        self.history.0.lock().unwrap().clone()
+    }
+}
+
+/// A queued track handed to a worker.
+struct Job {
+    batch: u64,
+    track: &'static Track,
+    url: String,
+}
+
+/// Why a worker's download should stop: the whole batch stopping, or its
+/// own track being cancelled.
+struct Stop<'a> {
+    all: &'a AtomicBool,
+    cancelled_slugs: &'a Mutex<HashSet<String>>,
+}
+
+impl Stop<'_> {
+    fn cancelled(&self, track: &Track) -> bool {
+        self.cancelled_slugs.lock().unwrap().contains(track.slug)
+    }
+}
+
+/// Download one track on a worker thread, reporting to `progress`.
+fn download_in_background(
+    client: &Client,
+    job: &Job,
+    tracks_dir: &Path,
+    progress: &Mutex<DownloadProgress>,
+    history: &History,
+    stop: &Stop,
+) {
+    let track = job.track;
+    progress.lock().unwrap().active_workers += 1;
+
+    let path = tracks_dir.join(track.filename());
+    let mut state = QueueState::Done;
+    if !path.exists() {
+        log::info!("Background download: {}", track.name);
+        let entry = history.start(track);
+        let should_stop = || stop.all.load(Ordering::Relaxed) || stop.cancelled(track);
+        let result = save_track(client, track, &job.url, tracks_dir, should_stop, |fraction| {
+            let mut prog = progress.lock().unwrap();
+            prog.set_state(job.batch, track.slug, QueueState::Downloading(fraction));
+        });
+        match &result {
+            Ok(()) => log::info!("Downloaded {}", track.name),
+            // A newer batch has queued the track again if it still wants it
+            Err(_) if stop.all.load(Ordering::Relaxed) => {
+                log::info!("Stopped downloading {}", track.name);
+                history.set_state(entry, DownloadState::Interrupted);
+                let mut prog = progress.lock().unwrap();
+                prog.active_workers = prog.active_workers.saturating_sub(1);
+                return;
+            }
+            Err(_) if stop.cancelled(track) => {
+                log::info!("Skipped downloading {}, queued again last", track.name);
+                history.set_state(entry, DownloadState::Interrupted);
+                stop.cancelled_slugs.lock().unwrap().remove(track.slug);
+                let mut prog = progress.lock().unwrap();
+                prog.move_to_end(job.batch, track.slug);
+                prog.active_workers = prog.active_workers.saturating_sub(1);
+                return;
+            }
+            Err(e) => {
+                log::warn!("Download of {} failed: {:#}", track.name, e);
+                state = QueueState::Failed(format!("{:#}", e));
+            }
+        }
+        history.finish(entry, &result);
+    }
+    // A cancel that came too late has nothing left to stop
+    stop.cancelled_slugs.lock().unwrap().remove(track.slug);
+
+    let mut prog = progress.lock().unwrap();
+    prog.set_state(job.batch, track.slug, state);
+    prog.active_workers = prog.active_workers.saturating_sub(1);
+}
+
+/// Network settings from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    /// Proxy for every request (`--proxy`), credentials allowed in the URL
+    pub proxy: Option<String>,
+    /// PEM file of extra root certificates to trust (`--ca-cert`)
+    pub ca_cert: Option<PathBuf>,
+}
+
+static HTTP_OPTIONS: OnceLock<HttpOptions> = OnceLock::new();
+
+/// The client every download shares, built on first use.
+static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Use `options` for all HTTP requests.
+///
+/// Must be called before anything asks for `http_client`.
+pub fn set_http_options(options: HttpOptions) -> Result<()> {
+    HTTP_OPTIONS
+        .set(options)
+        .map_err(|_| anyhow::anyhow!("HTTP client already in use"))
+}
+
+/// HTTP client for track downloads, shared so connections are reused.
+///
+/// `--proxy`, else `FOMU_HTTP_PROXY`, sends every request through the given
+/// proxy. Without either, reqwest already honors `HTTP_PROXY`, `HTTPS_PROXY`
+/// and `ALL_PROXY`. Hosts listed in `NO_PROXY` bypass the proxy either way.
+pub fn http_client() -> Result<Client> {
+    if let Some(client) = HTTP_CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let client = build_http_client(HTTP_OPTIONS.get_or_init(HttpOptions::default))?;
+    Ok(HTTP_CLIENT.get_or_init(|| client).clone())
+}
+
+fn build_http_client(options: &HttpOptions) -> Result<Client> {
+    let mut builder = Client::builder();
+
+    let env_proxy = std::env::var("FOMU_HTTP_PROXY").ok().filter(|url| !url.is_empty());
+    if let Some((url, source)) = options
+        .proxy
+        .clone()
+        .map(|url| (url, "--proxy"))
+        .or(env_proxy.map(|url| (url, "FOMU_HTTP_PROXY")))
+    {
+        let proxy = Proxy::all(&url)
+            .with_context(|| format!("Invalid {}", source))?
+            .no_proxy(NoProxy::from_env());
+        // The URL may carry credentials, so don't log it
+        log::info!("Downloading through the {} proxy", source);
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = &options.ca_cert {
+        let pem = fs::read(path).with_context(|| format!("Failed to read CA certificate {:?}", path))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid CA certificate {:?}", path))?;
+        if certs.is_empty() {
+            anyhow::bail!("No certificates found in {:?}", path);
+        }
+        log::info!("Trusting {} extra CA certificate(s) from {:?}", certs.len(), path);
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    builder.build().context("Failed to create HTTP client")
+}
+
+/// Explain a failed request, calling out a rejected proxy login.
+pub fn request_error(err: reqwest::Error, url: &str) -> anyhow::Error {
+    // HTTPS through a proxy fails in the CONNECT tunnel, before any response
+    let mut source: Option<&dyn std::error::Error> = Some(&err);
+    while let Some(e) = source {
+        if e.to_string().contains("proxy authorization required") {
+            return proxy_auth_error();
+        }
+        source = e.source();
+    }
+    anyhow::Error::new(err).context(format!("Failed to fetch {}", url))
+}
+
+/// Fail on an error status, calling out a rejected proxy login.
+pub fn check_status(response: &Response) -> Result<()> {
+    // Plain HTTP through a proxy gets the 407 as a response
+    if response.status() == StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+        return Err(proxy_auth_error());
+    }
+    if !response.status().is_success() {
+        anyhow::bail!("HTTP error: {}", response.status());
+    }
+    Ok(())
 }
 
-impl Default for TrackDownloader {
-    fn default() -> Self {
-        Self::new()
+fn proxy_auth_error() -> anyhow::Error {
+    anyhow::anyhow!("Proxy authentication failed: check the user and password in the proxy URL")
+}
+
+/// Download `track` from `url` into `tracks_dir` and record its checksum,
+/// giving up once `should_stop` returns true.
+fn save_track(
+    client: &Client,
+    track: &Track,
+    url: &str,
+    tracks_dir: &Path,
+    should_stop: impl Fn() -> bool,
+    on_progress: impl FnMut(f32),
+) -> Result<()> {
+    let filename = track.filename();
+    let checksum = fetch_to_file(client, url, &tracks_dir.join(&filename), &should_stop, on_progress)?;
+    // The track itself is fine, so a failed sidecar write only costs the check
+    if let Err(e) = Checksums::new(tracks_dir).record(&filename, checksum) {
+        log::warn!("Could not record checksum for {}: {:#}", track.name, e);
+    }
+    Ok(())
+}
+
+/// Fetch `url` and write the body to `path`, returning its checksum.
+///
+/// The body goes to a `.part` file that is renamed once complete, so an
+/// interrupted download never looks like a cached track. `on_progress`
+/// gets the fraction received when the server sends a length.
+fn fetch_to_file(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    should_stop: &impl Fn() -> bool,
+    mut on_progress: impl FnMut(f32),
+) -> Result<Checksum> {
+    let mut response = client.get(url).send().map_err(|e| request_error(e, url))?;
+
+    check_status(&response)?;
+
+    let total = response.content_length().filter(|len| *len > 0);
+    let part = path.with_extension("part");
+    let mut file = File::create(&part)
+        .with_context(|| format!("Failed to create file {:?}", part))?;
+
+    let checksum = match copy_body(&mut response, &mut file, total, should_stop, &mut on_progress) {
+        Ok(checksum) => checksum,
+        Err(e) => {
+            drop(file);
+            let _ = fs::remove_file(&part);
+            return Err(e);
+        }
+    };
+    drop(file);
+
+    fs::rename(&part, path).with_context(|| format!("Failed to move {:?} into place", part))?;
+    Ok(checksum)
+}
+
+/// Copy the response body into `file`, reporting the fraction received,
+/// and return the checksum of what was written. Fails once `should_stop`
+/// returns true, checked before every chunk.
+fn copy_body(
+    response: &mut impl Read,
+    file: &mut File,
+    total: Option<u64>,
+    should_stop: &impl Fn() -> bool,
+    on_progress: &mut impl FnMut(f32),
+) -> Result<Checksum> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut hasher = Hasher::new();
+    let mut received = 0u64;
+    loop {
+        if should_stop() {
+            anyhow::bail!("Download stopped");
+        }
+        let n = response.read(&mut buf).context("Failed to read response bytes")?;
+        if n == 0 {
+            return Ok(hasher.finish());
+        }
+        file.write_all(&buf[..n]).context("Failed to write file")?;
+        hasher.update(&buf[..n]);
+        received += n as u64;
+        if let Some(total) = total {
+            on_progress((received as f32 / total as f32).min(1.0));
+        }
     }
 }
 
@@ -0,0 +1,54 @@
+//! Favorite tracks, kept as a list of slugs in `favorites.json` in the
+//! data directory.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::catalog::Track;
+use super::loader::{get_data_dir, write_atomic};
+
+/// The favorited tracks.
+pub struct Favorites {
+    path: PathBuf,
+    slugs: BTreeSet<String>,
+}
+
+impl Favorites {
+    /// Default favorites location.
+    pub fn default_path() -> PathBuf {
+        get_data_dir().join("favorites.json")
+    }
+
+    /// Load the list, starting empty if the file is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        let slugs = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path: path.to_path_buf(),
+            slugs,
+        }
+    }
+
+    pub fn contains(&self, track: &Track) -> bool {
+        self.slugs.contains(track.slug)
+    }
+
+    /// Add `track` if it isn't a favorite, remove it if it is, and save.
+    /// Returns whether it's a favorite now.
+    pub fn toggle(&mut self, track: &Track) -> Result<bool> {
+        let added = self.slugs.insert(track.slug.to_string());
+        if !added {
+            self.slugs.remove(track.slug);
+        }
+        self.save()?;
+        Ok(added)
+    }
+
+    fn save(&self) -> Result<()> {
+        write_atomic(&self.path, serde_json::to_string_pretty(&self.slugs)?)
+    }
+}
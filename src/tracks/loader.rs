@@ -1,25 +1,108 @@
 //! Track loading and playlist management.
 
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
+use anyhow::{Context, Result};
 use directories::ProjectDirs;
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::seq::SliceRandom;
+use rand::Rng;
 
-use super::catalog::{get_tracks_by_pools, Track, TrackPool, TRACK_CATALOG};
+use super::catalog::{all_tracks, get_tracks_by_pools, Track, TrackPool};
+use super::checksums::{hash_file, Checksums};
+use super::favorites::Favorites;
 
-pub fn get_tracks_dir() -> PathBuf {
-    if let Some(proj_dirs) = ProjectDirs::from("", "", "fomu") {
-        let tracks_dir = proj_dirs.data_dir().join("tracks").join("scott-buckley");
-        std::fs::create_dir_all(&tracks_dir).ok();
-        tracks_dir
+/// Tracks directory, resolved once so every loader and downloader agrees.
+static TRACKS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Base data directory (e.g. `~/.local/share/fomu`), `FOMU_DATA_DIR` when set.
+pub fn get_data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("FOMU_DATA_DIR").filter(|dir| !dir.is_empty()) {
+        PathBuf::from(dir)
+    } else if let Some(proj_dirs) = ProjectDirs::from("", "", "fomu") {
+        proj_dirs.data_dir().to_path_buf()
     } else {
         let home = std::env::var("HOME")
             .map(PathBuf::from)
             .unwrap_or_else(|_| PathBuf::from("."));
-        let tracks_dir = home.join(".fomu").join("tracks").join("scott-buckley");
-        std::fs::create_dir_all(&tracks_dir).ok();
-        tracks_dir
+        home.join(".fomu")
+    }
+}
+
+/// Replace `path` with `contents` via a temp file beside it, so a crash
+/// mid-write or a reader at the wrong moment never sees half a file.
+/// Creates the parent directory if missing.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, contents).with_context(|| format!("Failed to write {:?}", tmp))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {:?}", path))?;
+    Ok(())
+}
+
+/// Use `dir` for downloaded tracks (`--tracks-dir`).
+///
+/// Must be called before anything asks for the tracks directory, and
+/// creates it if missing. Fails if it can't be written to.
+pub fn set_tracks_dir(dir: PathBuf) -> Result<()> {
+    if TRACKS_DIR.set(dir).is_err() {
+        anyhow::bail!("Tracks directory already in use");
     }
+    check_tracks_dir()
+}
+
+/// Downloaded tracks directory: `--tracks-dir`, else `FOMU_TRACKS_DIR`,
+/// else `<data dir>/tracks/scott-buckley`.
+pub fn get_tracks_dir() -> PathBuf {
+    let tracks_dir = TRACKS_DIR.get_or_init(|| default_tracks_dir(std::env::var_os("FOMU_TRACKS_DIR"), get_data_dir));
+    std::fs::create_dir_all(tracks_dir).ok();
+    tracks_dir.clone()
+}
+
+/// `FOMU_TRACKS_DIR` when set to something, else the tracks directory
+/// under `data_dir`.
+fn default_tracks_dir(env_dir: Option<OsString>, data_dir: impl FnOnce() -> PathBuf) -> PathBuf {
+    env_dir
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| data_dir().join("tracks").join("scott-buckley"))
+}
+
+/// Whether `path` is a file fomu leaves in the tracks directory: a track,
+/// or the `.part` file of a download an exit cut short.
+pub fn is_track_file(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "mp3" || e == "part")
+}
+
+/// Create the tracks directory if needed and make sure tracks can be saved there.
+pub fn check_tracks_dir() -> Result<()> {
+    let dir = get_tracks_dir();
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Cannot create tracks directory {:?}", dir))?;
+    let probe = dir.join(".fomu-write-test");
+    std::fs::write(&probe, b"")
+        .with_context(|| format!("Tracks directory {:?} is not writable", dir))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Outcome of checking a downloaded track against its checksum.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyResult {
+    /// Matches the recorded checksum
+    Ok,
+    /// No checksum was recorded, so the file's current one was adopted
+    Adopted,
+    /// Not downloaded
+    Missing,
+    /// Doesn't match the recorded checksum, or couldn't be read
+    Corrupt(String),
 }
 
 pub struct TrackLoader {
@@ -28,9 +111,17 @@ pub struct TrackLoader {
 
 impl TrackLoader {
     pub fn new() -> Self {
-        Self {
-            tracks_dir: get_tracks_dir(),
-        }
+        Self::with_dir(get_tracks_dir())
+    }
+
+    /// Loader for tracks stored in `tracks_dir`.
+    pub fn with_dir(tracks_dir: PathBuf) -> Self {
+        Self { tracks_dir }
+    }
+
+    /// Look up a catalog or custom track by slug.
+    pub fn get_track_by_slug(slug: &str) -> Option<&'static Track> {
+        all_tracks().find(|t| t.slug == slug)
     }
 
     pub fn get_track_path(&self, track: &Track) -> PathBuf {
@@ -41,9 +132,50 @@ impl TrackLoader {
         self.get_track_path(track).exists()
     }
 
+    /// Check a downloaded track against the checksum recorded when it was
+    /// downloaded. Tracks from before checksums existed are adopted.
+    pub fn verify(&self, track: &Track) -> VerifyResult {
+        let path = self.get_track_path(track);
+        if !path.exists() {
+            return VerifyResult::Missing;
+        }
+        let actual = match hash_file(&path) {
+            Ok(checksum) => checksum,
+            Err(e) => return VerifyResult::Corrupt(format!("{:#}", e)),
+        };
+
+        let checksums = Checksums::new(&self.tracks_dir);
+        let filename = track.filename();
+        match checksums.get(&filename) {
+            Some(expected) if expected.len != actual.len => VerifyResult::Corrupt(format!(
+                "{} bytes, expected {}",
+                actual.len, expected.len
+            )),
+            Some(expected) if expected.sha256 != actual.sha256 => {
+                VerifyResult::Corrupt("checksum mismatch".to_string())
+            }
+            Some(_) => VerifyResult::Ok,
+            None => match checksums.record(&filename, actual) {
+                Ok(()) => VerifyResult::Adopted,
+                Err(e) => {
+                    log::warn!("Could not record checksum for {}: {:#}", track.name, e);
+                    VerifyResult::Adopted
+                }
+            },
+        }
+    }
+
+    /// Delete a downloaded track and its checksum so it downloads again.
+    pub fn discard(&self, track: &Track) -> Result<()> {
+        let path = self.get_track_path(track);
+        if path.exists() {
+            std::fs::remove_file(&path).with_context(|| format!("Failed to delete {:?}", path))?;
+        }
+        Checksums::new(&self.tracks_dir).forget(&track.filename())
+    }
+
     pub fn get_available_tracks_from_pools(&self, pools: &[TrackPool]) -> Vec<&'static Track> {
-        TRACK_CATALOG
-            .iter()
+        all_tracks()
             .filter(|t| pools.contains(&t.pool) && self.track_exists(t))
             .collect()
     }
@@ -55,18 +187,284 @@ impl TrackLoader {
             .collect()
     }
 
-    pub fn create_playlist(&self, pools: &[TrackPool], shuffle: bool) -> Vec<&'static Track> {
-        let mut tracks = self.get_available_tracks_from_pools(pools);
-        if shuffle {
-            let mut rng = rand::thread_rng();
-            tracks.shuffle(&mut rng);
-        }
+    /// Downloaded favorites from `favorites.json`, in catalog order.
+    pub fn create_playlist_from_favorites(&self) -> Vec<&'static Track> {
+        let favorites = Favorites::load(&Favorites::default_path());
+        all_tracks()
+            .filter(|t| t.pool != TrackPool::Soundscape && favorites.contains(t) && self.track_exists(t))
+            .collect()
+    }
+
+    /// Shuffle the available tracks of `pool_weights` into a playlist
+    /// where each pool plays in proportion to its weight.
+    ///
+    /// With weights 2.0 and 1.0, tracks of the first pool come up twice as
+    /// often. Equal weights give a plain shuffle of every available track.
+    /// The start of the playlist avoids the `recent` tracks (oldest first)
+    /// so a reshuffle doesn't replay what just finished.
+    pub fn create_weighted_playlist(
+        &self,
+        pool_weights: &[(TrackPool, f32)],
+        recent: &[&'static Track],
+    ) -> Vec<&'static Track> {
+        let pool_list: Vec<TrackPool> = pool_weights.iter().map(|(pool, _)| *pool).collect();
+        let tracks = self.get_available_tracks_from_pools(&pool_list);
+        weighted_playlist(tracks, pool_weights, recent, &mut rand::thread_rng())
+    }
+}
+
+/// `create_weighted_playlist` over `tracks`, drawing from `rng`.
+fn weighted_playlist(
+    mut tracks: Vec<&'static Track>,
+    pool_weights: &[(TrackPool, f32)],
+    recent: &[&'static Track],
+    rng: &mut impl Rng,
+) -> Vec<&'static Track> {
+    let mut playlist = if pool_weights.windows(2).all(|w| w[0].1 == w[1].1) {
+        tracks.shuffle(rng);
         tracks
+    } else {
+        weighted_shuffle(tracks, pool_weights, rng)
+    };
+    avoid_recent(&mut playlist, recent, rng);
+    playlist
+}
+
+/// Move recently played tracks away from the start of a new playlist.
+///
+/// The first track never repeats the last one played; with four or more
+/// distinct tracks, no track plays again within two of its last play.
+/// Offending tracks are pulled out and reinserted at a random later slot,
+/// so the rest of the shuffle keeps its order.
+fn avoid_recent(playlist: &mut Vec<&'static Track>, recent: &[&'static Track], rng: &mut impl Rng) {
+    let mut distinct: Vec<&str> = playlist.iter().map(|t| t.slug).collect();
+    distinct.sort_unstable();
+    distinct.dedup();
+    let window = match distinct.len() {
+        0 | 1 => return,
+        2 | 3 => 1,
+        _ => 2,
+    };
+
+    // Most recent first
+    let recent: Vec<&str> = recent.iter().rev().take(window).map(|t| t.slug).collect();
+    for i in 0..window {
+        // Slot `i` plays `i + 1` tracks after the most recent one
+        let avoid = &recent[..(window - i).min(recent.len())];
+        // Bounded in case repeated entries of a weighted playlist keep landing here
+        for _ in 0..playlist.len() {
+            if i >= playlist.len() || !avoid.contains(&playlist[i].slug) {
+                break;
+            }
+            let track = playlist.remove(i);
+            let at = rng.gen_range(window.min(playlist.len())..=playlist.len());
+            playlist.insert(at, track);
+        }
     }
 }
 
+/// Pick a pool by weight for each slot, then the next track from that
+/// pool's shuffled deck, reshuffling a deck once it runs out.
+///
+/// The playlist has one slot per available track, so heavier pools repeat
+/// within a cycle while lighter ones may be skipped.
+fn weighted_shuffle(
+    mut tracks: Vec<&'static Track>,
+    pools: &[(TrackPool, f32)],
+    rng: &mut impl Rng,
+) -> Vec<&'static Track> {
+    let sources: Vec<(Vec<&'static Track>, f32)> = pools
+        .iter()
+        .filter(|(_, weight)| *weight > 0.0)
+        .filter_map(|(pool, weight)| {
+            let pool_tracks: Vec<_> = tracks.iter().copied().filter(|t| t.pool == *pool).collect();
+            (!pool_tracks.is_empty()).then_some((pool_tracks, *weight))
+        })
+        .collect();
+
+    let Ok(dist) = WeightedIndex::new(sources.iter().map(|(_, weight)| *weight)) else {
+        tracks.shuffle(rng);
+        return tracks;
+    };
+
+    let mut decks: Vec<Vec<&'static Track>> = vec![Vec::new(); sources.len()];
+    let mut playlist: Vec<&'static Track> = Vec::with_capacity(tracks.len());
+    while playlist.len() < tracks.len() {
+        let i = dist.sample(rng);
+        let deck = &mut decks[i];
+        if deck.is_empty() {
+            deck.extend_from_slice(&sources[i].0);
+            deck.shuffle(rng);
+            // Don't play the same track twice in a row across a reshuffle
+            let last_slug = playlist.last().map(|t| t.slug);
+            if deck.len() > 1 && deck.last().map(|t| t.slug) == last_slug {
+                let end = deck.len() - 1;
+                deck.swap(0, end);
+            }
+        }
+        if let Some(track) = deck.pop() {
+            playlist.push(track);
+        }
+    }
+    playlist
+}
+
 impl Default for TrackLoader {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::tracks::catalog::TRACK_CATALOG;
+
+    fn pool_tracks(pools: &[TrackPool]) -> Vec<&'static Track> {
+        TRACK_CATALOG.iter().filter(|t| pools.contains(&t.pool)).collect()
+    }
+
+    fn slugs(tracks: &[&'static Track]) -> Vec<&'static str> {
+        tracks.iter().map(|t| t.slug).collect()
+    }
+
+    #[test]
+    fn equal_weights_are_the_plain_shuffle() {
+        let pools = [TrackPool::Atmospheric, TrackPool::CalmFocus];
+        for seed in 0..20 {
+            let mut expected = pool_tracks(&pools);
+            expected.shuffle(&mut StdRng::seed_from_u64(seed));
+
+            let weights = [(TrackPool::Atmospheric, 1.0), (TrackPool::CalmFocus, 1.0)];
+            let playlist = weighted_playlist(pool_tracks(&pools), &weights, &[], &mut StdRng::seed_from_u64(seed));
+            assert_eq!(slugs(&playlist), slugs(&expected));
+        }
+    }
+
+    #[test]
+    fn built_in_presets_shuffle_evenly() {
+        for preset in crate::presets::PRESETS {
+            assert!(preset.has_equal_weights(), "{} has uneven pool weights", preset.name);
+        }
+    }
+
+    #[test]
+    fn heavier_pool_comes_up_more_often() {
+        let pools = [TrackPool::CalmFocus, TrackPool::Atmospheric];
+        let weights = [(TrackPool::CalmFocus, 3.0), (TrackPool::Atmospheric, 1.0)];
+        let mut rng = StdRng::seed_from_u64(1);
+        let (mut calm, mut atmospheric) = (0, 0);
+        for _ in 0..200 {
+            for track in weighted_playlist(pool_tracks(&pools), &weights, &[], &mut rng) {
+                match track.pool {
+                    TrackPool::CalmFocus => calm += 1,
+                    _ => atmospheric += 1,
+                }
+            }
+        }
+        let share = calm as f32 / (calm + atmospheric) as f32;
+        assert!((0.7..0.8).contains(&share), "calm-focus share was {}", share);
+    }
+
+    /// Check the start of `playlist` against `avoid_recent`'s rules for
+    /// `recent` (oldest first).
+    fn check_no_recent_repeat(playlist: &[&'static Track], recent: &[&'static Track]) {
+        let distinct: std::collections::HashSet<_> = playlist.iter().map(|t| t.slug).collect();
+        let last = recent.last().unwrap();
+        if distinct.len() >= 2 {
+            assert_ne!(playlist[0].slug, last.slug, "replayed the last track");
+        }
+        if distinct.len() >= 4 {
+            let before_last = recent[recent.len() - 2];
+            assert_ne!(playlist[0].slug, before_last.slug, "replayed a track two plays later");
+            assert_ne!(playlist[1].slug, last.slug, "replayed the last track one play later");
+        }
+    }
+
+    #[test]
+    fn reshuffle_avoids_recent_tracks() {
+        let all = pool_tracks(&[TrackPool::CalmFocus, TrackPool::Atmospheric]);
+        let weights = [(TrackPool::CalmFocus, 1.0), (TrackPool::Atmospheric, 1.0)];
+        let mut rng = StdRng::seed_from_u64(65);
+        for count in 1..=6 {
+            let tracks = &all[..count];
+            for _ in 0..500 {
+                let recent = [tracks[count - 1], tracks[0]];
+                let playlist = weighted_playlist(tracks.to_vec(), &weights, &recent, &mut rng);
+                assert_eq!(playlist.len(), count);
+                check_no_recent_repeat(&playlist, &recent);
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_reshuffle_avoids_recent_tracks() {
+        let all = pool_tracks(&[TrackPool::CalmFocus, TrackPool::Atmospheric]);
+        let weights = [(TrackPool::CalmFocus, 5.0), (TrackPool::Atmospheric, 1.0)];
+        let mut rng = StdRng::seed_from_u64(66);
+        for _ in 0..500 {
+            let recent = [all[1], all[0]];
+            let playlist = weighted_playlist(all.clone(), &weights, &recent, &mut rng);
+            check_no_recent_repeat(&playlist, &recent);
+        }
+    }
+
+    #[test]
+    fn single_track_still_plays() {
+        let track = pool_tracks(&[TrackPool::CalmFocus])[0];
+        let playlist = weighted_playlist(vec![track], &[(track.pool, 1.0)], &[track], &mut StdRng::seed_from_u64(1));
+        assert_eq!(slugs(&playlist), vec![track.slug]);
+    }
+
+    #[test]
+    fn random_preset_and_its_playlist_repeat_for_a_seed() {
+        let start = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let preset = crate::presets::get_preset(crate::presets::random_preset(&mut rng)).unwrap();
+            let playlist = weighted_playlist(pool_tracks(&preset.pool_list()), preset.pool_weights, &[], &mut rng);
+            (preset.name, slugs(&playlist))
+        };
+        for seed in 0..10 {
+            let (name, playlist) = start(seed);
+            assert_eq!(start(seed), (name, playlist.clone()));
+            let preset = crate::presets::get_preset(name).unwrap();
+            assert_eq!(playlist.len(), pool_tracks(&preset.pool_list()).len());
+        }
+    }
+
+    #[test]
+    fn tracks_dir_override_and_default() {
+        let data_dir = || PathBuf::from("/data/fomu");
+        assert_eq!(
+            default_tracks_dir(Some("/mnt/music".into()), data_dir),
+            PathBuf::from("/mnt/music")
+        );
+        let default = PathBuf::from("/data/fomu").join("tracks").join("scott-buckley");
+        assert_eq!(default_tracks_dir(None, data_dir), default);
+        // An empty variable counts as unset
+        assert_eq!(default_tracks_dir(Some("".into()), data_dir), default);
+    }
+
+    #[test]
+    fn track_files_include_interrupted_downloads() {
+        for name in ["morning.mp3", "morning.part", "dir/with.dots/track.mp3"] {
+            assert!(is_track_file(Path::new(name)), "{}", name);
+        }
+        for name in ["favorites.json", "checksums.json.tmp", "mp3", "notes.mp3.txt", "part"] {
+            assert!(!is_track_file(Path::new(name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("stats.json");
+        write_atomic(&path, "old").unwrap();
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert!(!dir.path().join("nested").join("stats.json.tmp").exists());
+    }
+}
@@ -1,7 +1,14 @@
 pub mod catalog;
+pub mod checksums;
 pub mod downloader;
+pub mod favorites;
 pub mod loader;
+pub mod sizes;
 
-pub use catalog::{Track, TrackPool};
-pub use downloader::{DownloadProgress, TrackDownloader};
-pub use loader::TrackLoader;
+pub use catalog::{PlaylistSort, Track, TrackPool};
+pub use downloader::{
+    DownloadEntry, DownloadProgress, DownloadState, QueueState, QueuedTrack, TrackDownloader,
+};
+pub use favorites::Favorites;
+pub use loader::{TrackLoader, VerifyResult};
+pub use sizes::TrackSizes;
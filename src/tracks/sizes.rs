@@ -0,0 +1,106 @@
+//! Download sizes of tracks, learned from HEAD requests.
+//!
+//! Sizes are keyed by download URL and cached in `track-sizes.json` in the
+//! data directory, so the picker can show what a preset costs to fetch
+//! without asking the server again every session.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_LENGTH;
+use serde::{Deserialize, Serialize};
+
+use super::catalog::Track;
+use super::loader::{get_data_dir, write_atomic};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SizeFile {
+    #[serde(default)]
+    sizes: BTreeMap<String, u64>,
+}
+
+/// Shared cache of track sizes in bytes.
+#[derive(Clone)]
+pub struct TrackSizes {
+    path: PathBuf,
+    file: Arc<Mutex<SizeFile>>,
+}
+
+impl TrackSizes {
+    /// Default size cache location.
+    pub fn default_path() -> PathBuf {
+        get_data_dir().join("track-sizes.json")
+    }
+
+    /// Load the cache, starting empty if the file is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        let file = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path: path.to_path_buf(),
+            file: Arc::new(Mutex::new(file)),
+        }
+    }
+
+    /// Size of `track`'s download, if known.
+    pub fn get(&self, track: &Track) -> Option<u64> {
+        self.file.lock().unwrap().sizes.get(&track.resolved_url()).copied()
+    }
+
+    /// Total size of `tracks`, or `None` if any of them is unknown.
+    pub fn total<'a>(&self, tracks: impl IntoIterator<Item = &'a Track>) -> Option<u64> {
+        tracks.into_iter().map(|track| self.get(track)).sum()
+    }
+
+    /// Ask the server for the size of each track not in the cache, then
+    /// save it. Tracks whose server doesn't answer HEAD stay unknown.
+    pub fn probe(&self, client: &Client, tracks: &[&Track]) -> Result<()> {
+        let mut learned = 0;
+        for track in tracks {
+            if self.get(track).is_some() {
+                continue;
+            }
+            let url = track.resolved_url();
+            match head_size(client, &url) {
+                Some(size) => {
+                    self.file.lock().unwrap().sizes.insert(url, size);
+                    learned += 1;
+                }
+                None => log::debug!("No size for {}", track.name),
+            }
+        }
+
+        if learned > 0 {
+            log::info!("Learned the size of {} tracks", learned);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&*self.file.lock().unwrap())?;
+        write_atomic(&self.path, json)
+    }
+}
+
+/// Content-Length from a HEAD request, if the server gives one.
+fn head_size(client: &Client, url: &str) -> Option<u64> {
+    let response = client.head(url).send().ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    // Read the header itself: a HEAD response has no body to size
+    response
+        .headers()
+        .get(CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|size| *size > 0)
+}
@@ -0,0 +1,141 @@
+//! Characters the UI draws with, in a Unicode and a plain ASCII set.
+//!
+//! A plain Linux console or a non-UTF-8 locale turns block elements,
+//! braille and symbols into mojibake, so the set is picked once on startup
+//! (see `platform::unicode_supported`, or `--ascii`) and handed to the app
+//! and keymap, which everything that draws gets it from.
+
+use ratatui::symbols::border;
+
+/// One glyph set.
+pub struct Glyphs {
+    /// Bar cells from empty to full, in even steps
+    pub blocks: &'static [char],
+    /// Meter bar cells in eighths, from empty to full
+    pub hblocks: &'static [char],
+    /// Marker drawn at each band's recent peak
+    pub peak: char,
+    /// Marker drawn at each meter bar's recent peak
+    pub meter_peak: char,
+    /// Tick showing the sample peak beside the bars
+    pub peak_tick: &'static str,
+    /// Needle on the meter's phase scale
+    pub phase_needle: char,
+    /// The phase scale itself
+    pub phase_line: char,
+    /// Whether the radial view draws braille dots; otherwise each cell
+    /// shows its dot count with `blocks`
+    pub braille: bool,
+    /// Download spinner frames
+    pub spinner: &'static [char],
+    pub play: &'static str,
+    pub pause: &'static str,
+    /// Between the volume and the key hints
+    pub separator: &'static str,
+    /// Between title and artist
+    pub dash: &'static str,
+    /// Between parts of a panel caption
+    pub dot: &'static str,
+    /// After text cut short to fit
+    pub ellipsis: &'static str,
+    /// Marks the highlighted preset in the picker
+    pub selected: &'static str,
+    pub done: char,
+    pub failed: char,
+    pub queued: char,
+    /// Download count in the header
+    pub download: &'static str,
+    /// Before a preset that will be switched to
+    pub arrow: &'static str,
+    /// After the track name: a favorite, and not one
+    pub favorite: &'static str,
+    pub not_favorite: &'static str,
+    /// Volume bar cells: filled and empty
+    pub volume_on: &'static str,
+    pub volume_off: &'static str,
+    /// Before the recording timer
+    pub recording: &'static str,
+    /// Before the time left of a `--for` or `--duration` session, with a
+    /// trailing space when not empty
+    pub fade_timer: &'static str,
+    pub timer: &'static str,
+    /// Arrow key labels: up, down, left, right
+    pub arrow_keys: [&'static str; 4],
+    /// Panel borders
+    pub border: border::Set,
+}
+
+pub const UNICODE: Glyphs = Glyphs {
+    blocks: &[' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'],
+    hblocks: &[' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'],
+    peak: '▔',
+    meter_peak: '│',
+    peak_tick: "▐",
+    phase_needle: '●',
+    phase_line: '─',
+    braille: true,
+    spinner: &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'],
+    play: "▶",
+    pause: "⏸",
+    separator: "│",
+    dash: "—",
+    dot: "·",
+    ellipsis: "…",
+    selected: "▸",
+    done: '✓',
+    failed: '✗',
+    queued: '·',
+    download: "↓",
+    arrow: "→",
+    favorite: "★",
+    not_favorite: "☆",
+    volume_on: "▮",
+    volume_off: "▯",
+    recording: "●",
+    fade_timer: "⏳ ",
+    timer: "⏱ ",
+    arrow_keys: ["↑", "↓", "←", "→"],
+    border: border::PLAIN,
+};
+
+pub const ASCII: Glyphs = Glyphs {
+    blocks: &[' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'],
+    hblocks: &[' ', '.', '.', ':', ':', '=', '=', '=', '#'],
+    peak: '~',
+    meter_peak: '|',
+    peak_tick: "|",
+    phase_needle: 'o',
+    phase_line: '-',
+    braille: false,
+    spinner: &['|', '/', '-', '\\'],
+    play: ">",
+    pause: "||",
+    separator: "|",
+    dash: "-",
+    dot: "-",
+    ellipsis: "...",
+    selected: ">",
+    done: '+',
+    failed: 'x',
+    queued: '.',
+    download: "dl",
+    arrow: "->",
+    favorite: "*",
+    not_favorite: "-",
+    volume_on: "#",
+    volume_off: "-",
+    recording: "*",
+    fade_timer: "",
+    timer: "",
+    arrow_keys: ["up", "down", "left", "right"],
+    border: border::Set {
+        top_left: "+",
+        top_right: "+",
+        bottom_left: "+",
+        bottom_right: "+",
+        vertical_left: "|",
+        vertical_right: "|",
+        horizontal_top: "-",
+        horizontal_bottom: "-",
+    },
+};
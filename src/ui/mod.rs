@@ -1,2 +1,3 @@
+pub mod glyphs;
 pub mod render;
 pub mod visualizers;
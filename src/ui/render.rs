@@ -1,45 +1,275 @@
 //! UI rendering with ratatui.
 
+use std::time::UNIX_EPOCH;
+
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::Paragraph,
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
     Frame,
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::app::App;
+use crate::app::{App, TOAST_DURATION};
+use crate::keys::Action;
+use crate::tracks::{DownloadState, QueueState};
+use crate::ui::visualizers::VisualizerStyle;
 
 const PRIMARY_COLOR: Color = Color::Cyan;
 
-pub fn render_ui(frame: &mut Frame, app: &App) {
+/// Cells in the volume bar on the controls line
+const VOLUME_BAR_CELLS: usize = 5;
+
+/// Toast colors over its last second, stepping down the visualizer's
+/// cyan to blue-gray gradient as it ends
+const TOAST_FADE: [Color; 4] = [
+    Color::Rgb(100, 120, 140),
+    Color::Rgb(67, 165, 178),
+    Color::Rgb(33, 210, 217),
+    PRIMARY_COLOR,
+];
+
+/// How far an onset pulse brightens the header accent toward white
+const PULSE_MAX_BRIGHTEN: f32 = 0.4;
+
+/// Column of the peak meter tick, inside the visualizer's left padding
+const PEAK_COLUMN: usize = 3;
+
+/// Clickable regions recorded during the last render.
+#[derive(Debug, Clone, Default)]
+pub struct UiLayout {
+    /// Track info line (click toggles pause)
+    pub track_info: Rect,
+    /// Preset rows in the selection overlay, by preset index
+    pub presets: Vec<(usize, Rect)>,
+    /// Support link in the attribution block
+    pub support_link: Rect,
+}
+
+impl UiLayout {
+    /// Find the preset index at the given position.
+    pub fn preset_at(&self, column: u16, row: u16) -> Option<usize> {
+        self.presets
+            .iter()
+            .find(|(_, rect)| contains(*rect, column, row))
+            .map(|(idx, _)| *idx)
+    }
+
+    /// Check if the position is on the track info line.
+    pub fn is_track_info(&self, column: u16, row: u16) -> bool {
+        contains(self.track_info, column, row)
+    }
+
+    /// Check if the position is on the support link.
+    pub fn is_support_link(&self, column: u16, row: u16) -> bool {
+        contains(self.support_link, column, row)
+    }
+}
+
+fn contains(rect: Rect, column: u16, row: u16) -> bool {
+    column >= rect.x && column < rect.x + rect.width && row >= rect.y && row < rect.y + rect.height
+}
+
+pub fn render_ui(frame: &mut Frame, app: &App) -> UiLayout {
     let area = frame.area();
 
+    // Minimal mode drops the visualization, but the picker still overlays its rows
+    let visualization_height = if app.is_minimal() && !app.is_selecting_preset() { 0 } else { 7 };
+
     // Compact layout with fixed-height visualization above track info
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(1),  // Header
             Constraint::Length(1),  // Spacer
-            Constraint::Length(7),  // Visualization (fixed height)
+            Constraint::Length(visualization_height),  // Visualization (fixed height)
             Constraint::Length(1),  // Spacer
             Constraint::Length(1),  // Track Info
+            Constraint::Length(u16::from(app.up_next().is_some())),  // Up next
             Constraint::Length(1),  // Controls
             Constraint::Length(3),  // Attribution
         ])
         .split(area);
 
+    let mut layout = UiLayout {
+        track_info: chunks[4],
+        ..Default::default()
+    };
+
     render_header(frame, chunks[0], app);
-    render_visualization(frame, chunks[2], app);
+    if !app.is_minimal() {
+        render_visualization(frame, chunks[2], app);
+    }
     render_track_info(frame, chunks[4], app);
 
     if app.is_selecting_preset() {
-        render_preset_selection(frame, chunks[5], app);
+        // Overlay the picker on the visualization and the spacers around it
+        let overlay = Rect {
+            height: chunks[4].y - chunks[1].y,
+            ..chunks[1]
+        };
+        layout.presets = render_preset_selection(frame, overlay, app);
+        render_preset_controls(frame, chunks[6], app);
     } else {
-        render_controls(frame, chunks[5], app);
+        render_controls(frame, chunks[6], app);
     }
 
-    render_attribution(frame, chunks[6]);
+    if let Some(track) = app.up_next() {
+        let line = Line::from(Span::styled(
+            format!("    next: {}", track.name),
+            Style::default().fg(Color::DarkGray),
+        ));
+        frame.render_widget(Paragraph::new(line), chunks[5]);
+    }
+
+    layout.support_link = render_attribution(frame, chunks[7]);
+
+    if app.is_showing_downloads() {
+        let height = (area.height / 3).max(3);
+        let panel = Rect {
+            y: area.y + area.height.saturating_sub(height),
+            height: height.min(area.height),
+            ..area
+        };
+        render_downloads(frame, panel, app);
+    } else if app.is_showing_queue() {
+        let height = (area.height / 3).max(3);
+        let panel = Rect {
+            y: area.y + area.height.saturating_sub(height),
+            height: height.min(area.height),
+            ..area
+        };
+        render_queue(frame, panel, app);
+    }
+
+    // Over the visualization, or the spacer under the header without one
+    let toast_area = if chunks[2].height >= 3 { chunks[2] } else { chunks[1] };
+    render_toast(frame, toast_area, app);
+
+    if app.is_showing_debug() {
+        render_debug(frame, area, app);
+    }
+    if app.is_showing_help() {
+        render_help(frame, area, app);
+    }
+    if app.is_minimal() {
+        // Plain text for screen readers: no colors, bold or reverse video
+        frame.buffer_mut().set_style(area, Style::reset());
+    }
+    layout
+}
+
+/// The current toast, centered at the bottom of `area`, boxed when there's
+/// room. It dims in steps over its last second.
+fn render_toast(frame: &mut Frame, area: Rect, app: &App) {
+    let Some((message, age)) = app.toast() else {
+        return;
+    };
+    let left = TOAST_DURATION.saturating_sub(age).as_millis() as usize;
+    let color = TOAST_FADE[(left * TOAST_FADE.len() / 1000).min(TOAST_FADE.len() - 1)];
+    let text = Span::styled(format!(" {} ", message), Style::default().fg(color).add_modifier(Modifier::BOLD));
+
+    let boxed = area.height >= 3;
+    let width = (text.width() as u16 + if boxed { 2 } else { 0 }).min(area.width);
+    let height = if boxed { 3 } else { 1 };
+    let panel = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + area.height - height,
+        width,
+        height,
+    );
+    frame.render_widget(Clear, panel);
+    let mut paragraph = Paragraph::new(Line::from(text));
+    if boxed {
+        paragraph = paragraph.block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(app.glyphs().border)
+                .border_style(Style::default().fg(color)),
+        );
+    }
+    frame.render_widget(paragraph, panel);
+}
+
+/// Buffer fill levels and sample counters, in the top right corner.
+fn render_debug(frame: &mut Frame, area: Rect, app: &App) {
+    let stats = app.buffer_stats();
+    let fill = |(occupied, capacity): (usize, usize)| {
+        format!("{:>5}/{} {:>3}%", occupied, capacity, occupied * 100 / capacity.max(1))
+    };
+    let rows = [
+        ("audio buf", fill(stats.audio)),
+        ("analysis buf", fill(stats.analysis)),
+        ("dropped", stats.dropped.to_string()),
+        ("skipped", stats.skipped.to_string()),
+        ("latency", format!("{} ms", stats.latency.as_millis())),
+        ("underruns", stats.underruns.to_string()),
+    ];
+
+    let width = 36.min(area.width);
+    let height = (rows.len() as u16 + 2).min(area.height);
+    let panel = Rect::new(area.x + area.width - width, area.y, width, height);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_set(app.glyphs().border)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(Span::styled(" Debug ", Style::default().add_modifier(Modifier::BOLD)));
+    let inner = block.inner(panel);
+    frame.render_widget(Clear, panel);
+    frame.render_widget(block, panel);
+
+    let lines: Vec<Line> = rows
+        .into_iter()
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(format!(" {:<13}", label), Style::default().fg(Color::DarkGray)),
+                Span::raw(value),
+            ])
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Every action and the keys bound to it, in two columns when it won't fit in one.
+fn render_help(frame: &mut Frame, area: Rect, app: &App) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_set(app.glyphs().border)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(Span::styled(" Keys ", Style::default().add_modifier(Modifier::BOLD)))
+        .title_bottom(Span::styled(
+            format!(" esc closes {} remap in the [keys] config section ", app.glyphs().dot),
+            Style::default().fg(Color::DarkGray),
+        ));
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let entries = app.keymap().help();
+    let key_width = entries.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+    let rows = usize::from(inner.height.max(1));
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+        .split(inner);
+    let areas: Vec<Rect> = if entries.len() > rows { columns.to_vec() } else { vec![inner] };
+
+    for (chunk, column) in entries.chunks(rows).zip(areas) {
+        let items: Vec<ListItem> = chunk
+            .iter()
+            .map(|(keys, description)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!(" {:<width$}  ", keys, width = key_width),
+                        Style::default().fg(PRIMARY_COLOR).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(*description, Style::default().fg(Color::Gray)),
+                ]))
+            })
+            .collect();
+        frame.render_widget(List::new(items), column);
+    }
 }
 
 fn render_header(frame: &mut Frame, area: Rect, app: &App) {
@@ -47,34 +277,121 @@ fn render_header(frame: &mut Frame, area: Rect, app: &App) {
         Span::styled("  Fomu", Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
         Span::styled(
             format!("  [{}]", app.preset().name),
-            Style::default().fg(PRIMARY_COLOR),
+            Style::default().fg(pulse_color(app.pulse())),
         ),
     ];
 
-    if let Some(pending) = app.pending_preset() {
-        let progress = app.download_progress();
-        if progress.progress > 0.0 && !progress.completed {
-            spans.push(Span::styled(
-                format!("  → [{}] {}%", pending, (progress.progress * 100.0) as u32),
-                Style::default().fg(Color::Yellow),
-            ));
+    if let Some(remaining) = app.session_remaining() {
+        let secs = remaining.as_secs();
+        let text = if secs >= 3600 {
+            format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
         } else {
-            spans.push(Span::styled(
-                format!("  → [{}] downloading...", pending),
-                Style::default().fg(Color::Yellow),
-            ));
-        }
+            format!("{}:{:02}", secs / 60, secs % 60)
+        };
+        let icon = if app.session_fades() { app.glyphs().fade_timer } else { app.glyphs().timer };
+        spans.push(Span::styled(
+            format!("  {}{} left", icon, text),
+            Style::default().fg(PRIMARY_COLOR),
+        ));
+    }
+
+    if let Some(elapsed) = app.recording_elapsed() {
+        let secs = elapsed.as_secs();
+        spans.push(Span::styled(
+            format!("  {} REC {}:{:02}", app.glyphs().recording, secs / 60, secs % 60),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if let Some(message) = app.status_message() {
+        spans.push(Span::styled(
+            format!("  {}", message),
+            Style::default().fg(Color::Green),
+        ));
+    }
+
+    if let Some(prompt) = app.resume_prompt() {
+        spans.push(Span::styled(
+            format!("  {}", prompt),
+            Style::default().fg(PRIMARY_COLOR).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    let progress = app.download_progress();
+    let mut summary = format!("{} {}/{}", app.glyphs().download, progress.done(), progress.queue.len());
+    if progress.workers > 1 && progress.is_active() {
+        summary.push_str(&format!(" ({} of {} workers)", progress.active_workers, progress.workers));
+    }
+    if let Some(pending) = app.pending_preset() {
+        spans.push(Span::styled(
+            format!("  {} [{}] {}", app.glyphs().arrow, pending, summary),
+            Style::default().fg(Color::Yellow),
+        ));
+    } else if progress.is_active() {
+        spans.push(Span::styled(
+            format!("  {}", summary),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if progress.failed() > 0 {
+        spans.push(Span::styled(
+            format!("  {} {} failed [D]", app.glyphs().failed, progress.failed()),
+            Style::default().fg(Color::Red),
+        ));
     }
 
     frame.render_widget(Paragraph::new(Line::from(spans)), area);
 }
 
+/// Header accent, brightened toward white while an onset pulse fades.
+fn pulse_color(pulse: f32) -> Color {
+    if pulse <= 0.01 {
+        return PRIMARY_COLOR;
+    }
+    let t = pulse.min(1.0) * PULSE_MAX_BRIGHTEN;
+    let red = (t * 255.0) as u8;
+    Color::Rgb(red, 255, 255)
+}
+
 fn render_visualization(frame: &mut Frame, area: Rect, app: &App) {
+    match app.visualizer().style() {
+        VisualizerStyle::Meter => return render_meter(frame, area, app),
+        VisualizerStyle::Radial => {
+            let lines: Vec<Line> = app
+                .visualizer()
+                .render_radial(app.bands(), area.width as usize, area.height as usize)
+                .into_iter()
+                .map(|s| Line::from(Span::styled(s, Style::default().fg(PRIMARY_COLOR))))
+                .collect();
+            frame.render_widget(Paragraph::new(lines), area);
+            return;
+        }
+        VisualizerStyle::Bars => {}
+    }
+
+    // Frequency labels take the bottom row when there's room for bars above
+    let mut area = area;
+    if area.height >= 4 {
+        let labels = app
+            .visualizer()
+            .render_spectrum_text(&app.band_center_freqs(), area.width as usize);
+        let label_area = Rect { y: area.bottom() - 1, height: 1, ..area };
+        frame.render_widget(
+            Paragraph::new(Line::from(Span::styled(labels, Style::default().fg(Color::DarkGray)))),
+            label_area,
+        );
+        area.height -= 1;
+    }
+
     // Use actual terminal area dimensions for responsive visualization
     let width = area.width as usize;
     let height = area.height as usize;
 
     let lines = app.visualizer().render_sized(app.rms(), app.bands(), width, height);
+
+    // Peak tick sits in the left padding, on the row matching the held peak
+    let peak_row = height.saturating_sub((app.peak() * height as f32).ceil() as usize);
+
     let viz_lines: Vec<Line> = lines
         .iter()
         .enumerate()
@@ -86,78 +403,404 @@ fn render_visualization(frame: &mut Frame, area: Rect, app: &App) {
                 (255.0 - t * 135.0) as u8,  // G: 255 → 120
                 (255.0 - t * 115.0) as u8,  // B: 255 → 140
             );
-            Line::from(Span::styled(s.clone(), Style::default().fg(color)))
+            let style = Style::default().fg(color);
+
+            if row == peak_row && app.peak() > 0.0 {
+                let rest: String = s.chars().skip(PEAK_COLUMN + 1).collect();
+                Line::from(vec![
+                    Span::styled(" ".repeat(PEAK_COLUMN), style),
+                    Span::styled(app.glyphs().peak_tick, Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+                    Span::styled(rest, style),
+                ])
+            } else {
+                Line::from(Span::styled(s.clone(), style))
+            }
         })
         .collect();
     frame.render_widget(Paragraph::new(viz_lines), area);
 }
 
+/// Stereo meter; the phase needle turns red when the channels cancel.
+fn render_meter(frame: &mut Frame, area: Rect, app: &App) {
+    let correlation = app.correlation();
+    let lines = app.visualizer().render_meter(
+        app.rms_stereo(),
+        correlation,
+        area.width as usize,
+        area.height as usize,
+    );
+    let phase_color = if correlation < 0.0 { Color::Red } else { Color::Green };
+
+    let meter_lines: Vec<Line> = lines
+        .into_iter()
+        .map(|s| {
+            let color = if s.contains(app.glyphs().phase_needle) { phase_color } else { PRIMARY_COLOR };
+            Line::from(Span::styled(s, Style::default().fg(color)))
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(meter_lines), area);
+}
+
 fn render_track_info(frame: &mut Frame, area: Rect, app: &App) {
-    let status_icon = if app.is_playing() { "▶" } else { "⏸" };
-    let track_name = app.current_track().map(|t| t.name).unwrap_or("Loading...");
+    let status_icon = if app.is_playing() { app.glyphs().play } else { app.glyphs().pause };
+    let track = app.current_track();
+    // File tags win over the catalog, which knows nothing about local files
+    let meta = track.and(app.current_track_meta()).unwrap_or_default();
+    let track_name = meta
+        .title
+        .or_else(|| track.map(|t| t.name.to_string()))
+        .unwrap_or_else(|| "Loading...".to_string());
+    let artist = meta
+        .artist
+        .or_else(|| track.and_then(|t| t.artist()).map(str::to_string));
 
-    let spans = vec![
-        Span::styled(format!("  {} ", status_icon), Style::default().add_modifier(Modifier::BOLD)),
-        Span::styled(track_name, Style::default().fg(Color::White)),
-        Span::styled(" — Scott Buckley", Style::default().fg(Color::DarkGray)),
-        Span::styled(format!("  {}", app.elapsed_time()), Style::default().fg(Color::DarkGray)),
-    ];
+    let mut spans = vec![Span::styled(
+        format!("  {} ", status_icon),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    if let Some((started, total)) = app.playlist_progress() {
+        spans.push(Span::styled(
+            format!("[{}/{}] ", started, total),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    let name_at = spans.len();
+    if track.is_some() {
+        let (star, color) = if app.is_current_track_favorited() {
+            (app.glyphs().favorite, Color::Yellow)
+        } else {
+            (app.glyphs().not_favorite, Color::DarkGray)
+        };
+        spans.push(Span::styled(format!(" {}", star), Style::default().fg(color)));
+    }
+    if let Some(artist) = artist {
+        spans.push(Span::styled(format!(" {} {}", app.glyphs().dash, artist), Style::default().fg(Color::DarkGray)));
+    }
+    spans.push(Span::styled(format!("  {}", app.track_position()), Style::default().fg(Color::DarkGray)));
+    spans.push(Span::styled(
+        format!("  Session: {}", app.listening_time()),
+        Style::default().fg(Color::DarkGray),
+    ));
+
+    // The name gets whatever width the rest of the line leaves
+    let room = usize::from(area.width).saturating_sub(spans.iter().map(Span::width).sum());
+    let name = if track_name.width() > room {
+        let ellipsis = app.glyphs().ellipsis;
+        let kept = truncate_to_width(&track_name, room.saturating_sub(ellipsis.width()));
+        format!("{}{}", kept, ellipsis)
+    } else {
+        track_name
+    };
+    spans.insert(name_at, Span::styled(name, Style::default().fg(Color::White)));
 
     frame.render_widget(Paragraph::new(Line::from(spans)), area);
 }
 
+/// The longest prefix of `s` that fits in `max` terminal columns, going by
+/// display width so wide (CJK) characters count double.
+fn truncate_to_width(s: &str, max: usize) -> &str {
+    let mut width = 0;
+    for (i, c) in s.char_indices() {
+        width += c.width().unwrap_or(0);
+        if width > max {
+            return &s[..i];
+        }
+    }
+    s
+}
+
 fn render_controls(frame: &mut Frame, area: Rect, app: &App) {
-    let volume_pct = (app.volume() * 100.0) as u32;
+    let volume = app.volume();
+    let filled = (volume * VOLUME_BAR_CELLS as f32).round() as usize;
+    let bar = format!(
+        "{}{}",
+        app.glyphs().volume_on.repeat(filled),
+        app.glyphs().volume_off.repeat(VOLUME_BAR_CELLS - filled)
+    );
+    let db = match app.volume_db() {
+        db if db == f32::NEG_INFINITY => "-inf dB".to_string(),
+        db if db > -0.5 => "0 dB".to_string(),
+        db => format!("{:.0} dB", db),
+    };
 
-    let spans = vec![
-        Span::styled(format!("  Vol: {}%", volume_pct), Style::default().fg(PRIMARY_COLOR)),
-        Span::styled("  │  ", Style::default().fg(Color::DarkGray)),
-        Span::styled("[space]", Style::default().add_modifier(Modifier::BOLD)),
+    let mut spans = if app.is_muted() {
+        vec![
+            Span::styled(format!("  Vol: {} ", bar), Style::default().fg(Color::DarkGray)),
+            Span::styled("muted", Style::default().fg(PRIMARY_COLOR).add_modifier(Modifier::BOLD)),
+        ]
+    } else {
+        vec![Span::styled(
+            format!("  Vol: {} {:.0}% {}", bar, volume * 100.0, db),
+            Style::default().fg(PRIMARY_COLOR),
+        )]
+    };
+
+    if app.is_showing_bpm() {
+        let bpm = app
+            .bpm()
+            .map(|b| format!("{:.0}", b))
+            .unwrap_or_else(|| "--".to_string());
+        spans.push(Span::styled(format!("  BPM: {}", bpm), Style::default().fg(PRIMARY_COLOR)));
+    }
+
+    if app.is_showing_peak_frequency() {
+        let hz = app
+            .peak_frequency()
+            .map(|hz| format!("{:.0}", hz))
+            .unwrap_or_else(|| "--".to_string());
+        spans.push(Span::styled(format!("  Peak: {} Hz", hz), Style::default().fg(PRIMARY_COLOR)));
+    }
+
+    if let Some((track, level, playing)) = app.soundscape() {
+        let status = if playing {
+            format!("{}%", (level * 100.0) as u32)
+        } else {
+            "(downloading)".to_string()
+        };
+        spans.push(Span::styled(format!("  {}: {}", track.name, status), Style::default().fg(PRIMARY_COLOR)));
+    }
+
+    let keys = app.keymap();
+    spans.extend([
+        Span::styled(format!("  {}  ", app.glyphs().separator), Style::default().fg(Color::DarkGray)),
+        Span::styled(format!("[{}]", keys.label(Action::Pause)), Style::default().add_modifier(Modifier::BOLD)),
         Span::styled(" pause  ", Style::default().fg(Color::DarkGray)),
-        Span::styled("[+/-]", Style::default().add_modifier(Modifier::BOLD)),
+        Span::styled(
+            format!("[{}/{}]", keys.label(Action::VolumeUp), keys.label(Action::VolumeDown)),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
         Span::styled(" vol  ", Style::default().fg(Color::DarkGray)),
-        Span::styled("[n]", Style::default().add_modifier(Modifier::BOLD)),
+        Span::styled(format!("[{}]", keys.label(Action::Next)), Style::default().add_modifier(Modifier::BOLD)),
         Span::styled(" skip  ", Style::default().fg(Color::DarkGray)),
-        Span::styled("[p]", Style::default().add_modifier(Modifier::BOLD)),
+        Span::styled(format!("[{}]", keys.label(Action::PresetMenu)), Style::default().add_modifier(Modifier::BOLD)),
         Span::styled(" preset  ", Style::default().fg(Color::DarkGray)),
-        Span::styled("[q]", Style::default().add_modifier(Modifier::BOLD)),
+        Span::styled(format!("[{}]", keys.label(Action::Help)), Style::default().add_modifier(Modifier::BOLD)),
+        Span::styled(" keys  ", Style::default().fg(Color::DarkGray)),
+        Span::styled(format!("[{}]", keys.label(Action::Quit)), Style::default().add_modifier(Modifier::BOLD)),
         Span::styled(" quit", Style::default().fg(Color::DarkGray)),
-    ];
+    ]);
 
     frame.render_widget(Paragraph::new(Line::from(spans)), area);
 }
 
-fn render_preset_selection(frame: &mut Frame, area: Rect, app: &App) {
-    let mut spans = vec![Span::styled(
-        "  Select preset: ",
-        Style::default().add_modifier(Modifier::BOLD),
-    )];
+fn render_preset_selection(frame: &mut Frame, area: Rect, app: &App) -> Vec<(usize, Rect)> {
+    let presets = app.all_presets();
+    let selected = app.selected_preset_index();
 
-    for (i, preset) in app.all_presets().iter().enumerate() {
-        if i > 0 {
-            spans.push(Span::styled(" ", Style::default().fg(Color::DarkGray)));
-        }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_set(app.glyphs().border)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(Span::styled(
+            format!(" Select preset ({}/{}) ", selected + 1, presets.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(
+            format!(" {} ", presets[selected].description),
+            Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+        ));
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
 
-        let has_tracks = app.preset_has_tracks(preset);
+    let rows = inner.height as usize;
+    if rows == 0 {
+        return Vec::new();
+    }
+    // Scroll just far enough to keep the selection on screen
+    let first = (selected + 1).saturating_sub(rows);
 
-        if i == app.selected_preset_index() {
-            spans.push(Span::styled(
-                format!("[{}]", preset.name),
-                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD | Modifier::REVERSED),
-            ));
-        } else if has_tracks {
-            spans.push(Span::styled(preset.name, Style::default().fg(Color::White)));
+    let mut lines = Vec::new();
+    let mut hit_regions = Vec::new();
+    for (i, preset) in presets.iter().enumerate().skip(first).take(rows) {
+        let (available, total) = app.preset_track_stats(preset);
+        let name_style = if i == selected {
+            Style::default().fg(PRIMARY_COLOR).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else if available > 0 {
+            Style::default().fg(Color::White)
         } else {
-            spans.push(Span::styled(
-                preset.name,
-                Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
-            ));
-        }
+            Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)
+        };
+
+        // [focus ✓] when everything is downloaded, [focus 8/13] otherwise
+        let label = if available == total {
+            format!("[{} {}]", preset.name, app.glyphs().done)
+        } else {
+            format!("[{} {}/{}]", preset.name, available, total)
+        };
+        let fetch = if available < total {
+            match app.preset_missing_bytes(preset) {
+                Some(bytes) => format!("(~{})", format_mb(bytes)),
+                None => "(size unknown)".to_string(),
+            }
+        } else {
+            String::new()
+        };
+
+        lines.push(Line::from(vec![
+            Span::raw(if i == selected { format!(" {} ", app.glyphs().selected) } else { "   ".to_string() }),
+            Span::styled(
+                if i < 9 { format!("{} ", i + 1) } else { "  ".to_string() },
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(format!("{:<16}", label), name_style),
+            Span::styled(
+                format!("  {:<32}", preset.pools_label()),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(format!("  {}", fetch), Style::default().fg(Color::Gray)),
+        ]));
+        hit_regions.push((
+            i,
+            Rect::new(inner.x, inner.y + (i - first) as u16, inner.width, 1),
+        ));
     }
 
+    frame.render_widget(Paragraph::new(lines), inner);
+    hit_regions
+}
+
+fn render_preset_controls(frame: &mut Frame, area: Rect, app: &App) {
+    let keys = app.keymap();
+    let spans = vec![
+        Span::styled(
+            format!("  [{}/{}/1-9]", keys.label(Action::MenuUp), keys.label(Action::MenuDown)),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(" choose  ", Style::default().fg(Color::DarkGray)),
+        Span::styled(
+            format!("[enter/{}]", keys.picker_label(Action::PresetMenu)),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(" switch  ", Style::default().fg(Color::DarkGray)),
+        Span::styled(format!("[{}]", keys.label(Action::Prefetch)), Style::default().add_modifier(Modifier::BOLD)),
+        Span::styled(" download  ", Style::default().fg(Color::DarkGray)),
+        Span::styled("[esc]", Style::default().add_modifier(Modifier::BOLD)),
+        Span::styled(" cancel", Style::default().fg(Color::DarkGray)),
+    ];
     frame.render_widget(Paragraph::new(Line::from(spans)), area);
 }
 
+/// Download history panel, newest at the bottom.
+fn render_downloads(frame: &mut Frame, area: Rect, app: &App) {
+    let history = app.download_history();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_set(app.glyphs().border)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(Span::styled(
+            format!(" Downloads ({}) ", history.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    if history.is_empty() {
+        let empty = Line::from(Span::styled(
+            "  Nothing downloaded this session",
+            Style::default().fg(Color::DarkGray),
+        ));
+        frame.render_widget(Paragraph::new(empty), inner);
+        return;
+    }
+
+    let items: Vec<ListItem> = history
+        .iter()
+        .map(|entry| {
+            let (icon, detail, color) = match &entry.state {
+                DownloadState::InProgress => {
+                    let frame = entry.started.elapsed().as_millis() / 100;
+                    let spinner = app.glyphs().spinner;
+                    (spinner[frame as usize % spinner.len()], String::new(), Color::Yellow)
+                }
+                DownloadState::Completed => (app.glyphs().done, String::new(), Color::Green),
+                DownloadState::Failed(e) => (app.glyphs().failed, format!("  {}", e), Color::Red),
+                DownloadState::Interrupted => (app.glyphs().queued, "  stopped".to_string(), Color::DarkGray),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!(" {} {}", icon, entry.track_name), Style::default().fg(color)),
+                Span::styled(detail, Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+
+    // Follow the newest entry unless scrolled back
+    let rows = inner.height as usize;
+    let offset = history.len().saturating_sub(rows + app.downloads_scroll());
+    let visible: Vec<ListItem> = items.into_iter().skip(offset).take(rows).collect();
+    frame.render_widget(List::new(visible), inner);
+}
+
+/// Download queue panel: the missing tracks of the current batch.
+fn render_queue(frame: &mut Frame, area: Rect, app: &App) {
+    let progress = app.download_progress();
+    let left = match app.queue_missing_bytes() {
+        Some(0) => String::new(),
+        Some(bytes) => format!(", ~{} left", format_mb(bytes)),
+        None => ", size unknown".to_string(),
+    };
+    let mut title = vec![Span::styled(
+        format!(" Download queue ({}/{}{}) ", progress.done(), progress.queue.len(), left),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    if progress.failed() > 0 {
+        title.push(Span::styled("[r] retry failed ", Style::default().fg(Color::Red)));
+    }
+    if progress.queue.iter().any(|item| matches!(item.state, QueueState::Downloading(_))) {
+        title.push(Span::styled("[ctrl+x] skip ", Style::default().fg(Color::DarkGray)));
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_set(app.glyphs().border)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(Line::from(title));
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    if progress.queue.is_empty() {
+        let empty = Line::from(Span::styled(
+            "  Every track of this preset is downloaded",
+            Style::default().fg(Color::DarkGray),
+        ));
+        frame.render_widget(Paragraph::new(empty), inner);
+        return;
+    }
+
+    let millis = UNIX_EPOCH.elapsed().unwrap_or_default().as_millis();
+    let spinner = app.glyphs().spinner;
+    let spinner = spinner[(millis / 100) as usize % spinner.len()];
+    let items: Vec<ListItem> = progress
+        .queue
+        .iter()
+        .skip(app.queue_scroll())
+        .take(inner.height as usize)
+        .map(|item| {
+            let (icon, detail, color) = match &item.state {
+                QueueState::Queued => (app.glyphs().queued, "  queued".to_string(), Color::DarkGray),
+                QueueState::Downloading(fraction) => (
+                    spinner,
+                    format!("  {}%", (fraction * 100.0) as u32),
+                    Color::Yellow,
+                ),
+                QueueState::Done => (app.glyphs().done, String::new(), Color::Green),
+                QueueState::Failed(e) => (app.glyphs().failed, format!("  {}", e), Color::Red),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!(" {} {}", icon, item.track.name), Style::default().fg(color)),
+                Span::styled(detail, Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+    frame.render_widget(List::new(items), inner);
+}
+
+/// Whole megabytes, rounded up so a small download never shows as 0.
+fn format_mb(bytes: u64) -> String {
+    format!("{} MB", bytes.div_ceil(1024 * 1024))
+}
+
 const SUPPORT_URL: &str = "https://www.scottbuckley.com.au/library/donate/";
 
 /// Create OSC 8 hyperlink text (clickable in supported terminals).
@@ -165,22 +808,47 @@ fn hyperlink(url: &str, text: &str) -> String {
     format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
 }
 
-fn render_attribution(frame: &mut Frame, area: Rect) {
-    let link_text = hyperlink(SUPPORT_URL, "scottbuckley.com.au");
+/// Render the attribution block and return the support link's hit region.
+fn render_attribution(frame: &mut Frame, area: Rect) -> Rect {
+    const LINK_LABEL: &str = "scottbuckley.com.au";
+    // Where the escape would show as garbage, print the whole URL to copy
+    let (link_text, link_label) = if crate::platform::hyperlinks_supported() {
+        (hyperlink(SUPPORT_URL, LINK_LABEL), LINK_LABEL)
+    } else {
+        (SUPPORT_URL.to_string(), SUPPORT_URL)
+    };
     let line1 = Line::from(vec![
         Span::styled("  Music by Scott Buckley (CC-BY 4.0)", Style::default().fg(Color::DarkGray)),
     ]);
-    let line2 = Line::from(vec![
+    let prefix = vec![
         Span::styled("  ", Style::default()),
         Span::styled("[s]", Style::default().add_modifier(Modifier::BOLD)),
         Span::styled(" support him at ", Style::default().fg(Color::DarkGray)),
-        Span::styled(link_text, Style::default().fg(Color::DarkGray).add_modifier(Modifier::UNDERLINED)),
-    ]);
+    ];
+    // Measure before appending the link, whose escape sequences have no width
+    let link_x = Line::from(prefix.clone()).width() as u16;
+    let mut line2 = Line::from(prefix);
+    line2.push_span(Span::styled(
+        link_text,
+        Style::default().fg(Color::DarkGray).add_modifier(Modifier::UNDERLINED),
+    ));
 
     frame.render_widget(Paragraph::new(vec![Line::default(), line1, line2]), area);
+
+    // The link sits on the third line of the block
+    if area.height < 3 || link_x >= area.width {
+        return Rect::default();
+    }
+    let link_width = (link_label.len() as u16).min(area.width - link_x);
+    Rect::new(area.x + link_x, area.y + 2, link_width, 1)
 }
 
 /// Open the support URL in the default browser.
+///
+/// Detached, so a browser that takes a while to start (or `start` in the
+/// Windows console) can't stall the UI.
 pub fn open_support_url() {
-    let _ = open::that(SUPPORT_URL);
+    if let Err(e) = open::that_detached(SUPPORT_URL) {
+        log::warn!("Could not open {}: {}", SUPPORT_URL, e);
+    }
 }
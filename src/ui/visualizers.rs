@@ -1,32 +1,345 @@
-//! Bar visualization for the audio player.
+//! Bar, stereo meter and radial visualizations for the audio player.
 
-const BLOCKS: &[char] = &[' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+use std::time::Duration;
+
+use super::glyphs::{Glyphs, UNICODE};
+
+/// Frequencies labelled under the spectrum bars (Hz)
+const LABEL_FREQS: &[(f32, &str)] = &[
+    (31.0, "31"),
+    (63.0, "63"),
+    (125.0, "125"),
+    (250.0, "250"),
+    (500.0, "500"),
+    (1000.0, "1k"),
+    (2000.0, "2k"),
+    (4000.0, "4k"),
+    (8000.0, "8k"),
+    (16000.0, "16k"),
+];
+
+/// Empty braille cell; dots are OR-ed onto it
+const BRAILLE_BLANK: u32 = 0x2800;
+
+/// Braille dot bit for each (column, row) within a 2x4 cell
+const BRAILLE_DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Radius of the radial mode's base circle, as a fraction of the largest radius
+const RADIAL_INNER: f32 = 0.3;
+
+/// Columns left blank before the first bar
+const LEFT_PADDING: usize = 6;
+
+/// Columns between bars
+const BAR_GAP: usize = 1;
+
+/// How fast peak markers fall, in full-scale units per second
+const PEAK_DECAY_PER_SEC: f32 = 0.5;
+
+/// How long a beat boost takes to decay back to normal bar heights
+const BEAT_DECAY: Duration = Duration::from_millis(100);
+
+/// Position within the current beat (0.0 on the beat, approaching 1.0
+/// just before the next) after `elapsed` of playback at `bpm`.
+pub fn beat_phase(elapsed: Duration, bpm: f32) -> f32 {
+    (elapsed.as_secs_f32() * bpm / 60.0).fract()
+}
+
+/// What the visualization area shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisualizerStyle {
+    /// Spectrum bars
+    Bars,
+    /// Left/right level bars with a phase indicator
+    Meter,
+    /// Bands as spokes around a circle
+    Radial,
+}
+
+impl VisualizerStyle {
+    /// Names accepted in the config file.
+    pub const NAMES: &'static [&'static str] = &["bars", "meter", "radial"];
+
+    /// Look up a style by its config name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "bars" => Some(Self::Bars),
+            "meter" => Some(Self::Meter),
+            "radial" => Some(Self::Radial),
+            _ => None,
+        }
+    }
+}
 
 pub struct Visualizer {
-    _placeholder: (),
+    /// Held peak level per band
+    peaks: Vec<f32>,
+    /// Held peak level per meter channel (left, right)
+    meter_peaks: (f32, f32),
+    /// Whether peak markers are drawn
+    show_peaks: bool,
+    style: VisualizerStyle,
+    /// Bar height multiplier right on a beat; 1.0 disables the pulse
+    beat_boost: f32,
+    /// Multiplier applied to bar heights this frame
+    boost: f32,
+    /// Characters to draw with
+    glyphs: &'static Glyphs,
 }
 
 impl Visualizer {
-    pub fn new() -> Self {
-        Self { _placeholder: () }
+    pub fn new(glyphs: &'static Glyphs) -> Self {
+        Self {
+            peaks: Vec::new(),
+            meter_peaks: (0.0, 0.0),
+            show_peaks: true,
+            style: VisualizerStyle::Bars,
+            beat_boost: 1.0,
+            boost: 1.0,
+            glyphs,
+        }
+    }
+
+    /// Draw with `glyphs` from the next frame.
+    pub fn set_glyphs(&mut self, glyphs: &'static Glyphs) {
+        self.glyphs = glyphs;
+    }
+
+    pub fn style(&self) -> VisualizerStyle {
+        self.style
+    }
+
+    pub fn set_style(&mut self, style: VisualizerStyle) {
+        self.style = style;
+    }
+
+    /// Switch to the next visualization style.
+    pub fn cycle_style(&mut self) {
+        self.style = match self.style {
+            VisualizerStyle::Bars => VisualizerStyle::Meter,
+            VisualizerStyle::Meter => VisualizerStyle::Radial,
+            VisualizerStyle::Radial => VisualizerStyle::Bars,
+        };
+    }
+
+    /// Switch between the radial style and the bars.
+    pub fn toggle_radial(&mut self) {
+        self.style = match self.style {
+            VisualizerStyle::Radial => VisualizerStyle::Bars,
+            _ => VisualizerStyle::Radial,
+        };
+    }
+
+    pub fn set_show_peaks(&mut self, show: bool) {
+        self.show_peaks = show;
+    }
+
+    /// Multiply bar heights by `boost` on each beat (1.0 disables it).
+    pub fn set_beat_boost(&mut self, boost: f32) {
+        self.beat_boost = boost.max(1.0);
+    }
+
+    /// Peak markers and the beat boost, to tell whether a frame moved.
+    pub fn levels(&self) -> impl Iterator<Item = f32> + '_ {
+        self.peaks
+            .iter()
+            .copied()
+            .chain([self.meter_peaks.0, self.meter_peaks.1, self.boost])
+    }
+
+    /// Advance peak markers by `dt`: they jump up with a band and fall
+    /// back at a fixed rate, independent of the frame rate.
+    ///
+    /// `beat` is the tempo and `beat_phase` while a BPM estimate is
+    /// available. Bars are boosted on the beat and ease back within
+    /// `BEAT_DECAY`.
+    pub fn update(&mut self, _rms: f32, bands: &[f32], dt: Duration, beat: Option<(f32, f32)>) {
+        self.boost = match beat {
+            Some((bpm, phase)) if bpm > 0.0 => {
+                let since_beat = phase * 60.0 / bpm;
+                let fade = (1.0 - since_beat / BEAT_DECAY.as_secs_f32()).max(0.0);
+                1.0 + (self.beat_boost - 1.0) * fade
+            }
+            _ => 1.0,
+        };
+
+        self.peaks.resize(bands.len(), 0.0);
+        let decay = PEAK_DECAY_PER_SEC * dt.as_secs_f32();
+        for (peak, &level) in self.peaks.iter_mut().zip(bands) {
+            *peak = (*peak - decay).max(level).max(0.0);
+        }
+    }
+
+    /// Advance the meter's peak markers by `dt`, like `update` does for bands.
+    pub fn update_meter(&mut self, levels: (f32, f32), dt: Duration) {
+        let decay = PEAK_DECAY_PER_SEC * dt.as_secs_f32();
+        let (left, right) = &mut self.meter_peaks;
+        *left = (*left - decay).max(levels.0).max(0.0);
+        *right = (*right - decay).max(levels.1).max(0.0);
+    }
+
+    /// Render the stereo meter: an L and an R bar with peak hold, and a
+    /// needle on a -1..+1 scale for the channel correlation.
+    pub fn render_meter(&self, levels: (f32, f32), correlation: f32, width: usize, height: usize) -> Vec<String> {
+        let left_padding = 6;
+        let bar_width = width.saturating_sub(left_padding * 2 + 2).max(1);
+
+        let glyphs = self.glyphs;
+        let bar = |label: char, level: f32, peak: f32| {
+            let mut line = format!("{}{} ", " ".repeat(left_padding), label);
+            let eighths = (level.clamp(0.0, 1.0) * (bar_width * 8) as f32) as usize;
+            let peak_col = ((peak.clamp(0.0, 1.0) * bar_width as f32) as usize).min(bar_width - 1);
+            for col in 0..bar_width {
+                let filled = eighths.saturating_sub(col * 8).min(8);
+                let ch = if filled == 0 && self.show_peaks && peak > 0.01 && col == peak_col {
+                    glyphs.meter_peak
+                } else {
+                    glyphs.hblocks[filled]
+                };
+                line.push(ch);
+            }
+            line
+        };
+
+        // Needle position on the scale, -1 at the left end, +1 at the right
+        let scale_width = bar_width.saturating_sub(6).max(3);
+        let needle = (((correlation.clamp(-1.0, 1.0) + 1.0) / 2.0) * (scale_width - 1) as f32).round() as usize;
+        let mut phase = format!("{}  -1 ", " ".repeat(left_padding));
+        for col in 0..scale_width {
+            phase.push(if col == needle { glyphs.phase_needle } else { glyphs.phase_line });
+        }
+        phase.push_str(" +1");
+
+        let rows = [
+            bar('L', levels.0, self.meter_peaks.0),
+            String::new(),
+            bar('R', levels.1, self.meter_peaks.1),
+            String::new(),
+            phase,
+        ];
+
+        // Center the meter block vertically
+        let top = height.saturating_sub(rows.len()) / 2;
+        let mut lines = vec![String::new(); height];
+        for (line, row) in lines.iter_mut().skip(top).zip(rows) {
+            *line = row;
+        }
+        lines
     }
 
-    pub fn update(&mut self, _rms: f32, _bands: &[f32]) {
-        // No state needed for bars visualization
+    /// Render a row of frequency labels (`31 63 125 … 16k`), each starting
+    /// at the column of the bar whose center frequency is closest.
+    ///
+    /// `freqs` holds each band's center frequency. Labels that would run
+    /// into the next one are cut short on the right.
+    pub fn render_spectrum_text(&self, freqs: &[f32], width: usize) -> String {
+        let bar_width = bar_width(freqs.len(), width);
+        let mut placed: Vec<(usize, &str)> = LABEL_FREQS
+            .iter()
+            .filter(|(hz, _)| {
+                let (low, high) = (freqs.first().copied(), freqs.last().copied());
+                low.zip(high).is_some_and(|(low, high)| *hz >= low / 1.5 && *hz <= high * 1.5)
+            })
+            .filter_map(|&(hz, label)| {
+                let band = freqs
+                    .iter()
+                    .enumerate()
+                    .min_by(|a, b| (a.1 / hz).ln().abs().total_cmp(&(b.1 / hz).ln().abs()))?
+                    .0;
+                Some((LEFT_PADDING + band * (bar_width + BAR_GAP), label))
+            })
+            .collect();
+        // Two labels can land on the same bar when bands are wide
+        placed.dedup_by_key(|(col, _)| *col);
+
+        let mut row = String::with_capacity(width);
+        for (i, &(col, label)) in placed.iter().enumerate() {
+            // Stop a space short of the next label and of the row end
+            let end = placed.get(i + 1).map_or(width, |(next, _)| next.saturating_sub(1));
+            let start = col.max(row.chars().count());
+            let room = end.saturating_sub(start);
+            if room == 0 {
+                continue;
+            }
+            row.push_str(&" ".repeat(start - row.chars().count()));
+            row.extend(label.chars().take(room));
+        }
+        row
+    }
+
+    /// Render bands as spokes radiating from a circle in the middle of the
+    /// area, drawn with braille dots (2x4 per cell) for finer angles.
+    ///
+    /// Braille dots are about square on a typical terminal font, so the
+    /// circle stays round. Spoke length beyond the circle follows the band level.
+    pub fn render_radial(&self, bands: &[f32], width: usize, height: usize) -> Vec<String> {
+        let (dots_w, dots_h) = (width * 2, height * 4);
+        let mut cells = vec![BRAILLE_BLANK; width * height];
+        let mut plot = |x: f32, y: f32| {
+            if x < 0.0 || y < 0.0 {
+                return;
+            }
+            let (x, y) = (x.round() as usize, y.round() as usize);
+            if x < dots_w && y < dots_h {
+                cells[(y / 4) * width + x / 2] |= BRAILLE_DOTS[x % 2][y % 4];
+            }
+        };
+
+        let center_x = dots_w as f32 / 2.0;
+        let center_y = dots_h as f32 / 2.0;
+        let max_radius = center_x.min(center_y) - 1.0;
+        if max_radius < 2.0 || bands.is_empty() {
+            return vec![String::new(); height];
+        }
+        let inner = max_radius * RADIAL_INNER;
+
+        // Base circle, dense enough to close up at any size
+        let circle_steps = (std::f32::consts::TAU * inner * 2.0).ceil() as usize;
+        for step in 0..circle_steps {
+            let angle = std::f32::consts::TAU * step as f32 / circle_steps as f32;
+            plot(center_x + inner * angle.cos(), center_y + inner * angle.sin());
+        }
+
+        // One spoke per band, clockwise from the top
+        for (i, &level) in bands.iter().enumerate() {
+            let level = level * self.boost;
+            let angle = std::f32::consts::TAU * i as f32 / bands.len() as f32 - std::f32::consts::FRAC_PI_2;
+            let (dx, dy) = (angle.cos(), angle.sin());
+            let length = level.clamp(0.0, 1.0) * (max_radius - inner);
+            let mut r = inner;
+            while r <= inner + length {
+                plot(center_x + r * dx, center_y + r * dy);
+                r += 0.5;
+            }
+        }
+
+        let glyphs = self.glyphs;
+        let cell_char = |c: u32| {
+            if glyphs.braille {
+                char::from_u32(c)
+            } else {
+                // Denser cells get heavier characters
+                let dots = (c - BRAILLE_BLANK).count_ones() as usize;
+                Some(glyphs.blocks[dots * (glyphs.blocks.len() - 1) / 8])
+            }
+        };
+        cells
+            .chunks(width)
+            .map(|row| row.iter().filter_map(|&c| cell_char(c)).collect())
+            .collect()
     }
 
     /// Render bar visualization with dynamic sizing.
     pub fn render_sized(&self, _rms: f32, bands: &[f32], width: usize, height: usize) -> Vec<String> {
         let num_bars = bands.len();
-
-        // Fixed 1-space gap between bars, bar width capped for tighter look
-        let gap = 1;
-        let total_gaps = (num_bars - 1) * gap;
-        let bar_width = (width.saturating_sub(total_gaps) / num_bars).clamp(1, 2);
+        let gap = BAR_GAP;
+        let bar_width = bar_width(num_bars, width);
 
         let mut lines = Vec::with_capacity(height);
 
-        let left_padding = 6;
+        let left_padding = LEFT_PADDING;
+        let blocks = self.glyphs.blocks;
+        let full = blocks[blocks.len() - 1];
 
         for row in 0..height {
             let mut row_chars = String::with_capacity(width);
@@ -37,12 +350,16 @@ impl Visualizer {
             let threshold = 1.0 - (row as f32 / height as f32);
 
             for (i, &level) in bands.iter().take(num_bars).enumerate() {
+                let level = (level * self.boost).min(1.0);
+                let peak = self.peaks.get(i).copied().unwrap_or(0.0);
                 let ch = if level >= threshold {
-                    '█'
+                    full
                 } else if level >= threshold - (1.0 / height as f32) {
                     let partial_idx = ((level - threshold + (1.0 / height as f32))
-                        * height as f32 * (BLOCKS.len() - 1) as f32) as usize;
-                    BLOCKS[partial_idx.min(BLOCKS.len() - 1)]
+                        * height as f32 * (blocks.len() - 1) as f32) as usize;
+                    blocks[partial_idx.min(blocks.len() - 1)]
+                } else if self.show_peaks && peak > 0.01 && peak_row(peak, height) == row {
+                    self.glyphs.peak
                 } else {
                     ' '
                 };
@@ -62,8 +379,120 @@ impl Visualizer {
     }
 }
 
+/// Bar width for `num_bars` bars in `width` columns, capped for a tighter look.
+fn bar_width(num_bars: usize, width: usize) -> usize {
+    let total_gaps = num_bars.saturating_sub(1) * BAR_GAP;
+    (width.saturating_sub(total_gaps) / num_bars.max(1)).clamp(1, 2)
+}
+
+/// Row (0 = top) whose cell a level falls in.
+fn peak_row(level: f32, height: usize) -> usize {
+    (((1.0 - level) * height as f32) as usize).min(height.saturating_sub(1))
+}
+
 impl Default for Visualizer {
     fn default() -> Self {
-        Self::new()
+        Self::new(&UNICODE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::glyphs::ASCII;
+
+    const BANDS: [f32; 8] = [0.1, 0.35, 0.6, 0.9, 1.0, 0.7, 0.45, 0.2];
+
+    fn visualizer(glyphs: &'static Glyphs) -> Visualizer {
+        Visualizer::new(glyphs)
+    }
+
+    fn bars(glyphs: &'static Glyphs) -> Vec<String> {
+        let mut visualizer = visualizer(glyphs);
+        // Peaks held from a louder frame show above the bars
+        visualizer.update(0.0, &[0.3, 0.6, 0.9, 1.0, 1.0, 0.9, 0.8, 0.5], Duration::ZERO, None);
+        visualizer.render_sized(0.0, &BANDS, 22, 4)
+    }
+
+    fn meter(glyphs: &'static Glyphs) -> Vec<String> {
+        let mut visualizer = visualizer(glyphs);
+        visualizer.update_meter((0.75, 0.3), Duration::ZERO);
+        visualizer.render_meter((0.5, 0.25), 0.5, 26, 5)
+    }
+
+    fn radial(glyphs: &'static Glyphs) -> Vec<String> {
+        visualizer(glyphs).render_radial(&[0.8; 8], 10, 5)
+    }
+
+    #[test]
+    fn bars_snapshot() {
+        assert_eq!(
+            bars(&UNICODE),
+            [
+                "          ▔ ▄ █ ▔ ▔  ",
+                "        ▔ ▃ █ █ ▆    ",
+                "      ▔ ▃ █ █ █ █ ▆ ▔",
+                "      ▃ █ █ █ █ █ █ ▆",
+            ]
+        );
+        assert_eq!(
+            bars(&ASCII),
+            [
+                "          ~ + @ ~ ~  ",
+                "        ~ - @ @ #    ",
+                "      ~ - @ @ @ @ # ~",
+                "      - @ @ @ @ @ @ #",
+            ]
+        );
+    }
+
+    #[test]
+    fn meter_snapshot() {
+        assert_eq!(
+            meter(&UNICODE),
+            ["      L ██████   │  ", "", "      R ███│        ", "", "        -1 ────●─ +1"]
+        );
+        assert_eq!(
+            meter(&ASCII),
+            ["      L ######   |  ", "", "      R ###|        ", "", "        -1 ----o- +1"]
+        );
+    }
+
+    #[test]
+    fn radial_snapshot() {
+        assert_eq!(
+            radial(&UNICODE),
+            ["⠀⠀⠀⠀⠀⡄⠀⠀⠀⠀", "⠀⠀⠐⢄⢀⣇⢀⠔⠀⠀", "⠀⠤⠤⢴⡋⠈⣳⠤⠤⠄", "⠀⠀⢀⠔⠙⡞⠑⢄⠀⠀", "⠀⠀⠀⠀⠀⠇⠀⠀⠀⠀"]
+        );
+        // Without braille, each cell's dot count picks a heavier character
+        assert_eq!(radial(&ASCII), ["     :    ", "  .:.+.:  ", " ::==.*::.", "  .:-+::  ", "     -    "]);
+    }
+
+    #[test]
+    fn ascii_set_is_plain_ascii() {
+        let lines = [bars(&ASCII), meter(&ASCII), radial(&ASCII)].concat();
+        for line in lines {
+            assert!(line.is_ascii(), "{:?}", line);
+        }
+        let g = &ASCII;
+        let chars = [g.blocks, g.hblocks, g.spinner].concat();
+        let strs = [
+            g.peak_tick, g.play, g.pause, g.separator, g.dash, g.dot, g.ellipsis, g.selected, g.download,
+            g.arrow, g.favorite, g.not_favorite, g.volume_on, g.volume_off, g.recording, g.fade_timer, g.timer,
+        ];
+        assert!(chars.iter().chain([&g.peak, &g.meter_peak, &g.phase_needle, &g.phase_line]).all(char::is_ascii));
+        assert!(strs.iter().chain(&g.arrow_keys).all(|s| s.is_ascii()));
+        assert!(!g.braille);
+    }
+
+    #[test]
+    fn both_sets_cover_the_same_levels() {
+        for g in [&UNICODE, &ASCII] {
+            // Meter cells are eighths, and bars start empty and end full
+            assert_eq!(g.hblocks.len(), 9);
+            assert_eq!(g.blocks[0], ' ');
+            assert_eq!(g.hblocks[0], ' ');
+            assert!(g.blocks.len() >= 2);
+        }
     }
 }
@@ -0,0 +1,196 @@
+//! Local HTTP server standing in for the track host.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use fomu::tracks::catalog::get_tracks_by_pools;
+use fomu::tracks::{Track, TrackDownloader, TrackPool};
+use tiny_http::{Response, Server};
+
+/// How the server answers requests for a file.
+#[derive(Clone)]
+pub enum Route {
+    /// 200 with this body
+    Body(Vec<u8>),
+    /// 200 with this body, sent a kilobyte at a time with a pause before each
+    Slow(Vec<u8>),
+    /// An empty response with this status
+    Status(u16),
+}
+
+/// Serves files by name from `url()`. Unrouted names get a 404.
+pub struct MockServer {
+    server: Arc<Server>,
+    url: String,
+    routes: Arc<Mutex<HashMap<String, Route>>>,
+    /// File names requested so far, in order
+    hits: Arc<Mutex<Vec<String>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let server = Arc::new(Server::http("127.0.0.1:0").expect("bind test server"));
+        let url = format!("http://{}/tracks", server.server_addr());
+        let routes: Arc<Mutex<HashMap<String, Route>>> = Arc::default();
+        let hits: Arc<Mutex<Vec<String>>> = Arc::default();
+
+        let thread = {
+            let server = Arc::clone(&server);
+            let routes = Arc::clone(&routes);
+            let hits = Arc::clone(&hits);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    let name = request.url().trim_start_matches("/tracks/").to_string();
+                    let route = routes.lock().unwrap().get(&name).cloned();
+                    hits.lock().unwrap().push(name);
+                    // Slow bodies must not hold up other workers' requests
+                    thread::spawn(move || {
+                        let _ = match route {
+                            Some(Route::Body(body)) => request.respond(Response::from_data(body)),
+                            Some(Route::Slow(body)) => {
+                                let len = body.len();
+                                let reader = Trickle { body, pos: 0 };
+                                // Send a length so the downloader reports progress
+                                let response = Response::new(200.into(), Vec::new(), reader, Some(len), None)
+                                    .with_chunked_threshold(usize::MAX);
+                                request.respond(response)
+                            }
+                            Some(Route::Status(code)) => request.respond(Response::empty(code)),
+                            None => request.respond(Response::empty(404)),
+                        };
+                    });
+                }
+            })
+        };
+
+        Self {
+            server,
+            url,
+            routes,
+            hits,
+            thread: Some(thread),
+        }
+    }
+
+    /// Base URL to download catalog tracks from.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn route(&self, track: &Track, route: Route) {
+        self.routes.lock().unwrap().insert(file_name(track), route);
+    }
+
+    /// Serve every track of `pool` with its `fake_mp3` body.
+    pub fn serve_pool(&self, pool: TrackPool) {
+        for track in get_tracks_by_pools(&[pool]) {
+            self.route(track, Route::Body(fake_mp3(track)));
+        }
+    }
+
+    /// Serve every track of `pool` slowly.
+    pub fn serve_pool_slowly(&self, pool: TrackPool) {
+        for track in get_tracks_by_pools(&[pool]) {
+            self.route(track, Route::Slow(fake_mp3(track)));
+        }
+    }
+
+    /// File names requested so far, in order.
+    pub fn hits(&self) -> Vec<String> {
+        self.hits.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Body reader that takes ~1.3s for a `fake_mp3`, long enough to stop a
+/// download part way.
+struct Trickle {
+    body: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.body.len() {
+            return Ok(0);
+        }
+        thread::sleep(Duration::from_millis(20));
+        let end = (self.pos + 1024).min(self.body.len()).min(self.pos + buf.len());
+        let len = end - self.pos;
+        buf[..len].copy_from_slice(&self.body[self.pos..end]);
+        self.pos = end;
+        Ok(len)
+    }
+}
+
+/// The name the server knows `track` by, the last segment of its URL.
+pub fn file_name(track: &Track) -> String {
+    track.download_url.rsplit('/').next().unwrap().to_string()
+}
+
+/// 64 KiB standing in for `track`'s MP3, different for every track.
+pub fn fake_mp3(track: &Track) -> Vec<u8> {
+    let mut body = b"ID3".to_vec();
+    body.extend(track.slug.bytes().cycle().take(64 * 1024 - 3));
+    body
+}
+
+/// A playable MP3 of `duration` seconds of silence: MPEG-1 Layer III
+/// frames at 128 kbps, 44.1 kHz stereo, whose empty side info decodes to
+/// zeros.
+pub fn silent_mp3(duration: Duration) -> Vec<u8> {
+    const FRAME_LEN: usize = 144 * 128_000 / 44_100;
+    let frames = (duration.as_secs_f64() * 44_100.0 / 1152.0).ceil() as usize;
+    let mut frame = vec![0; FRAME_LEN];
+    frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+    frame.repeat(frames)
+}
+
+/// Serve every track of `pool` as `duration` of silence.
+pub fn serve_silence(server: &MockServer, pool: TrackPool, duration: Duration) {
+    for track in get_tracks_by_pools(&[pool]) {
+        server.route(track, Route::Body(silent_mp3(duration)));
+    }
+}
+
+/// Downloader saving into `dir` and fetching catalog tracks from `server`.
+pub fn downloader(dir: &Path, server: &MockServer) -> TrackDownloader {
+    // Bypass any proxy from the environment, the server is local
+    let client = reqwest::blocking::Client::builder().no_proxy().build().unwrap();
+    let mut downloader = TrackDownloader::with_base(dir.to_path_buf(), client);
+    downloader.set_base_url(server.url());
+    downloader
+}
+
+/// Wait until `done` holds for the downloader's progress.
+pub fn wait_for(downloader: &TrackDownloader, what: &str, done: impl Fn(&fomu::tracks::DownloadProgress) -> bool) {
+    let start = Instant::now();
+    while !done(&downloader.get_progress()) {
+        assert!(start.elapsed() < Duration::from_secs(20), "timed out waiting for {}", what);
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Wait for the background batch to finish.
+pub fn wait_until_idle(downloader: &TrackDownloader) {
+    wait_for(downloader, "downloads to finish", |progress| !progress.is_active());
+}
+
+pub fn slugs(tracks: &[&Track]) -> Vec<&'static str> {
+    tracks.iter().map(|t| t.slug).collect()
+}
@@ -0,0 +1,180 @@
+//! Downloading tracks from a local server into a temp tracks directory.
+
+mod common;
+
+use std::fs;
+
+use common::{downloader, fake_mp3, file_name, slugs, wait_for, wait_until_idle, MockServer, Route};
+use fomu::tracks::catalog::get_tracks_by_pools;
+use fomu::tracks::{DownloadState, QueueState, TrackLoader, TrackPool, VerifyResult};
+use tempfile::tempdir;
+
+fn no_partial_files(dir: &std::path::Path) -> bool {
+    fs::read_dir(dir)
+        .unwrap()
+        .all(|entry| entry.unwrap().path().extension().is_none_or(|ext| ext != "part"))
+}
+
+#[test]
+fn first_run_downloads_one_track_to_start_with() {
+    let server = MockServer::start();
+    server.serve_pool(TrackPool::CalmFocus);
+    let dir = tempdir().unwrap();
+    let downloader = downloader(dir.path(), &server);
+    let loader = TrackLoader::with_dir(dir.path().to_path_buf());
+    assert!(loader.get_available_tracks_from_pools(&[TrackPool::CalmFocus]).is_empty());
+
+    let track = downloader.download_one_track(&[TrackPool::CalmFocus]).unwrap().unwrap();
+
+    assert_eq!(fs::read(loader.get_track_path(track)).unwrap(), fake_mp3(track));
+    assert_eq!(slugs(&loader.get_available_tracks_from_pools(&[TrackPool::CalmFocus])), [track.slug]);
+    assert_eq!(loader.verify(track), VerifyResult::Ok);
+    assert_eq!(server.hits(), [file_name(track)]);
+
+    // Already cached, so no second request
+    downloader.download_track(track).unwrap();
+    assert_eq!(server.hits().len(), 1);
+}
+
+#[test]
+fn background_download_fetches_the_rest_of_the_pool() {
+    let server = MockServer::start();
+    server.serve_pool(TrackPool::Atmospheric);
+    let dir = tempdir().unwrap();
+    let mut downloader = downloader(dir.path(), &server);
+    downloader.set_max_downloads(3);
+    let pool = get_tracks_by_pools(&[TrackPool::Atmospheric]);
+    let cached = pool[0];
+    fs::write(dir.path().join(cached.filename()), fake_mp3(cached)).unwrap();
+
+    downloader.start_background_download(vec![TrackPool::Atmospheric]);
+    wait_until_idle(&downloader);
+
+    let progress = downloader.get_progress();
+    assert_eq!(progress.queue.len(), pool.len() - 1);
+    assert_eq!(progress.done(), pool.len() - 1);
+    assert_eq!(progress.failed(), 0);
+    for track in &pool[1..] {
+        assert_eq!(fs::read(dir.path().join(track.filename())).unwrap(), fake_mp3(track));
+    }
+    assert!(!server.hits().contains(&file_name(cached)));
+    let history = downloader.history();
+    assert_eq!(history.len(), pool.len() - 1);
+    assert!(history.iter().all(|entry| entry.state == DownloadState::Completed));
+    assert!(no_partial_files(dir.path()));
+}
+
+#[test]
+fn stopping_leaves_no_partial_file() {
+    let server = MockServer::start();
+    server.serve_pool_slowly(TrackPool::Soundscape);
+    let dir = tempdir().unwrap();
+    let mut downloader = downloader(dir.path(), &server);
+
+    downloader.start_background_download(vec![TrackPool::Soundscape]);
+    wait_for(&downloader, "a download to start", |progress| {
+        progress.queue.iter().any(|item| matches!(item.state, QueueState::Downloading(f) if f > 0.0))
+    });
+    downloader.stop_background_download();
+
+    let loader = TrackLoader::with_dir(dir.path().to_path_buf());
+    assert!(loader.get_available_tracks_from_pools(&[TrackPool::Soundscape]).is_empty());
+    assert!(no_partial_files(dir.path()));
+    let history = downloader.history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].state, DownloadState::Interrupted);
+}
+
+#[test]
+fn stopped_batch_resumes_when_started_again() {
+    let server = MockServer::start();
+    server.serve_pool_slowly(TrackPool::Soundscape);
+    let dir = tempdir().unwrap();
+    let mut downloader = downloader(dir.path(), &server);
+
+    downloader.start_background_download(vec![TrackPool::Soundscape]);
+    wait_for(&downloader, "a download to start", |progress| {
+        progress.queue.iter().any(|item| matches!(item.state, QueueState::Downloading(f) if f > 0.0))
+    });
+    downloader.stop_background_download();
+
+    server.serve_pool(TrackPool::Soundscape);
+    downloader.set_max_downloads(3);
+    downloader.start_background_download(vec![TrackPool::Soundscape]);
+    wait_until_idle(&downloader);
+
+    let pool = get_tracks_by_pools(&[TrackPool::Soundscape]);
+    assert_eq!(downloader.get_progress().done(), pool.len());
+    for track in pool {
+        assert_eq!(fs::read(dir.path().join(track.filename())).unwrap(), fake_mp3(track));
+    }
+    assert!(no_partial_files(dir.path()));
+}
+
+#[test]
+fn missing_file_fails_the_download() {
+    let server = MockServer::start();
+    server.serve_pool(TrackPool::Soundscape);
+    let track = get_tracks_by_pools(&[TrackPool::Soundscape])[1];
+    server.route(track, Route::Status(404));
+    let dir = tempdir().unwrap();
+    let downloader = downloader(dir.path(), &server);
+
+    let err = downloader.download_track(track).unwrap_err();
+
+    assert!(format!("{:#}", err).contains("404"), "{:#}", err);
+    assert!(!dir.path().join(track.filename()).exists());
+    assert!(no_partial_files(dir.path()));
+    assert!(matches!(downloader.history()[0].state, DownloadState::Failed(_)));
+}
+
+#[test]
+fn failed_background_download_succeeds_on_retry() {
+    let server = MockServer::start();
+    server.serve_pool(TrackPool::Soundscape);
+    let pool = get_tracks_by_pools(&[TrackPool::Soundscape]);
+    let track = pool[1];
+    server.route(track, Route::Status(404));
+    let dir = tempdir().unwrap();
+    let mut downloader = downloader(dir.path(), &server);
+
+    downloader.start_background_download(vec![TrackPool::Soundscape]);
+    wait_until_idle(&downloader);
+
+    let progress = downloader.get_progress();
+    assert_eq!(progress.done(), pool.len() - 1);
+    assert_eq!(progress.failed(), 1);
+    let failed = progress.queue.iter().find(|item| item.track.slug == track.slug).unwrap();
+    assert!(matches!(&failed.state, QueueState::Failed(msg) if msg.contains("404")), "{:?}", failed.state);
+    assert!(!dir.path().join(track.filename()).exists());
+
+    server.route(track, Route::Body(fake_mp3(track)));
+    assert_eq!(downloader.retry_failed(), 1);
+    wait_until_idle(&downloader);
+
+    assert_eq!(downloader.get_progress().done(), pool.len());
+    assert_eq!(fs::read(dir.path().join(track.filename())).unwrap(), fake_mp3(track));
+}
+
+#[test]
+fn loader_follows_the_tracks_directory() {
+    let dir = tempdir().unwrap();
+    let loader = TrackLoader::with_dir(dir.path().to_path_buf());
+    let pool = get_tracks_by_pools(&[TrackPool::Atmospheric]);
+    for track in &pool[..2] {
+        fs::write(loader.get_track_path(track), fake_mp3(track)).unwrap();
+    }
+    // An interrupted download doesn't count
+    fs::write(dir.path().join(format!("{}.part", pool[2].slug)), b"ID3").unwrap();
+
+    let available = loader.get_available_tracks_from_pools(&[TrackPool::Atmospheric]);
+    assert_eq!(slugs(&available), slugs(&pool[..2]));
+    let missing = loader.get_missing_tracks_from_pools(&[TrackPool::Atmospheric]);
+    assert_eq!(slugs(&missing), slugs(&pool[2..]));
+    assert!(loader.get_available_tracks_from_pools(&[TrackPool::CalmFocus]).is_empty());
+
+    loader.discard(pool[0]).unwrap();
+    let available = loader.get_available_tracks_from_pools(&[TrackPool::Atmospheric]);
+    assert_eq!(slugs(&available), [pool[1].slug]);
+}
+
@@ -0,0 +1,262 @@
+//! Driving the library `Player` without a terminal or a sound card.
+
+mod common;
+
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{serve_silence, silent_mp3, MockServer, Route};
+use crossbeam_channel::Receiver;
+use fomu::audio::{AudioDecoder, AudioPlayer};
+use fomu::tracks::catalog::get_tracks_by_pools;
+use fomu::tracks::TrackPool;
+use fomu::{Player, PlayerConfig, PlayerEvent};
+use tempfile::tempdir;
+
+/// Length of the served tracks, short enough to hear a few in a test
+const TRACK: Duration = Duration::from_secs(1);
+
+/// The pools of the `focus` preset
+const FOCUS: [TrackPool; 2] = [TrackPool::Atmospheric, TrackPool::CalmFocus];
+
+/// A server with every track of the `focus` preset.
+fn focus_server() -> MockServer {
+    let server = MockServer::start();
+    for pool in FOCUS {
+        serve_silence(&server, pool, TRACK);
+    }
+    server
+}
+
+/// A player on the null backend, downloading from `server` into `dir`.
+fn player(dir: &Path, server: &MockServer) -> (Player, Receiver<PlayerEvent>) {
+    let player = Player::new(PlayerConfig {
+        backend: Some("null".to_string()),
+        max_downloads: 3,
+        tracks_dir: Some(dir.to_path_buf()),
+        tracks_base_url: Some(server.url().to_string()),
+        ..PlayerConfig::default()
+    })
+    .unwrap();
+    let events = player.events();
+    (player, events)
+}
+
+/// Run the player's loop until `done` holds for the events so far, and
+/// return them.
+fn run_until(
+    player: &mut Player,
+    events: &Receiver<PlayerEvent>,
+    what: &str,
+    done: impl Fn(&[PlayerEvent]) -> bool,
+) -> Vec<PlayerEvent> {
+    let start = Instant::now();
+    let mut seen = Vec::new();
+    loop {
+        player.update();
+        seen.extend(events.try_iter());
+        if done(&seen) {
+            return seen;
+        }
+        assert!(start.elapsed() < Duration::from_secs(20), "timed out waiting for {}: {:?}", what, seen);
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Run the player's loop for `duration` and return the events.
+fn run_for(player: &mut Player, events: &Receiver<PlayerEvent>, duration: Duration) -> Vec<PlayerEvent> {
+    let start = Instant::now();
+    run_until(player, events, "the time to pass", |_| start.elapsed() >= duration)
+}
+
+fn track_changes(events: &[PlayerEvent]) -> Vec<&str> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            PlayerEvent::TrackChanged { slug, .. } => Some(slug.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn downloads(events: &[PlayerEvent]) -> usize {
+    events.iter().filter(|event| matches!(event, PlayerEvent::Downloaded { .. })).count()
+}
+
+#[test]
+fn plays_a_preset_from_an_empty_cache() {
+    let server = focus_server();
+    let dir = tempdir().unwrap();
+    let (mut player, events) = player(dir.path(), &server);
+    assert!(!player.is_playing());
+
+    player.play_preset("focus").unwrap();
+
+    let first = player.current_track().unwrap();
+    match events.try_recv().unwrap() {
+        PlayerEvent::TrackChanged { slug, name, preset } => {
+            assert_eq!((slug.as_str(), name.as_str(), preset.as_str()), (first.slug, first.name, "focus"));
+        }
+        event => panic!("expected a track change, got {:?}", event),
+    }
+    assert!(FOCUS.contains(&first.pool));
+    assert!(dir.path().join(first.filename()).exists());
+    assert_eq!(player.preset().map(|p| p.name), Some("focus"));
+    assert!(player.is_playing());
+
+    // The rest of the preset downloads while the first track plays; the
+    // first download reported is the one that started playback
+    let total = get_tracks_by_pools(&FOCUS).len();
+    let seen = run_until(&mut player, &events, "the downloads", |seen| downloads(seen) == total);
+    assert!(matches!(&seen[0], PlayerEvent::Downloaded { track } if *track == first.name), "{:?}", seen);
+    for track in get_tracks_by_pools(&FOCUS) {
+        assert!(dir.path().join(track.filename()).exists(), "{}", track.slug);
+    }
+
+    // Tracks follow each other as they end, never the same one twice
+    let seen = run_until(&mut player, &events, "track changes", |seen| track_changes(seen).len() >= 3);
+    let changes = track_changes(&seen);
+    assert!(changes.windows(2).all(|pair| pair[0] != pair[1]), "{:?}", changes);
+}
+
+#[test]
+fn pause_holds_the_track() {
+    let server = focus_server();
+    let dir = tempdir().unwrap();
+    let (mut player, events) = player(dir.path(), &server);
+    player.play_preset("focus").unwrap();
+    let first = player.current_track().unwrap();
+    events.try_iter().for_each(drop);
+
+    player.pause();
+    assert!(!player.is_playing());
+    let seen = run_for(&mut player, &events, TRACK * 2);
+    assert!(track_changes(&seen).is_empty(), "{:?}", seen);
+    assert_eq!(player.current_track().map(|t| t.slug), Some(first.slug));
+
+    player.resume();
+    assert!(player.is_playing());
+    run_until(&mut player, &events, "the track to end", |seen| !track_changes(seen).is_empty());
+}
+
+#[test]
+fn next_moves_on_to_a_downloaded_track() {
+    let server = focus_server();
+    let dir = tempdir().unwrap();
+    let (mut player, events) = player(dir.path(), &server);
+    player.play_preset("focus").unwrap();
+    // The first track's own download is reported too
+    run_until(&mut player, &events, "another download", |seen| downloads(seen) > 1);
+    let before = player.current_track().unwrap();
+    events.try_iter().for_each(drop);
+
+    player.next().unwrap();
+
+    let next = player.current_track().unwrap();
+    assert_ne!(next.slug, before.slug);
+    assert!(matches!(events.try_recv(), Ok(PlayerEvent::TrackChanged { slug, .. }) if slug == next.slug));
+}
+
+#[test]
+fn download_failures_are_reported() {
+    let server = focus_server();
+    let broken = *get_tracks_by_pools(&[TrackPool::CalmFocus]).last().unwrap();
+    server.route(broken, Route::Status(404));
+    let dir = tempdir().unwrap();
+    let (mut player, events) = player(dir.path(), &server);
+    player.play_preset("focus").unwrap();
+
+    let seen = run_until(&mut player, &events, "the failed download", |seen| {
+        seen.iter().any(|event| matches!(event, PlayerEvent::DownloadFailed { .. }))
+    });
+    let (track, error) = seen
+        .iter()
+        .find_map(|event| match event {
+            PlayerEvent::DownloadFailed { track, error } => Some((track, error)),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(track, broken.name);
+    assert!(error.contains("404"), "{}", error);
+    assert!(!dir.path().join(broken.filename()).exists());
+}
+
+#[test]
+fn preset_errors() {
+    let server = MockServer::start();
+    let dir = tempdir().unwrap();
+    let (mut player, _events) = player(dir.path(), &server);
+
+    assert!(player.next().is_err(), "nothing to skip before a preset plays");
+    let err = player.play_preset("nope").unwrap_err();
+    assert!(err.to_string().contains("Unknown preset 'nope'"), "{}", err);
+    // Nothing cached and nothing to download
+    let err = player.play_preset("focus").unwrap_err();
+    assert!(format!("{:#}", err).contains("404"), "{:#}", err);
+    assert!(player.current_track().is_none());
+}
+
+#[test]
+fn volume_is_clamped() {
+    let server = MockServer::start();
+    let dir = tempdir().unwrap();
+    let (player, _events) = player(dir.path(), &server);
+    assert_eq!(player.volume(), 0.8);
+
+    player.set_volume(1.5);
+    assert_eq!(player.volume(), 1.0);
+    player.set_volume(-0.5);
+    assert_eq!(player.volume(), 0.0);
+}
+
+#[test]
+fn rapid_skips_leave_the_last_track_playing() {
+    // Long enough that no track ends on its own while the test watches
+    let server = MockServer::start();
+    for pool in FOCUS {
+        serve_silence(&server, pool, TRACK * 3);
+    }
+    let dir = tempdir().unwrap();
+    let (mut player, events) = player(dir.path(), &server);
+    player.play_preset("focus").unwrap();
+    let total = get_tracks_by_pools(&FOCUS).len();
+    run_until(&mut player, &events, "the downloads", |seen| downloads(seen) == total);
+    events.try_iter().for_each(drop);
+
+    for _ in 0..50 {
+        player.next().unwrap();
+    }
+    let skips: Vec<PlayerEvent> = events.try_iter().collect();
+    let changes = track_changes(&skips);
+    assert_eq!(changes.len(), 50);
+    let last = changes.last().unwrap().to_string();
+    assert_eq!(player.current_track().map(|t| t.slug), Some(last.as_str()));
+
+    // The 49 replaced jobs finishing late mustn't end the last track
+    let seen = run_for(&mut player, &events, TRACK);
+    assert!(track_changes(&seen).is_empty(), "{:?}", seen);
+    assert_eq!(player.current_track().map(|t| t.slug), Some(last.as_str()));
+    assert!(player.is_playing());
+}
+
+#[test]
+fn replaced_decode_jobs_never_count_as_finished() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("track.mp3");
+    std::fs::write(&path, silent_mp3(TRACK * 3)).unwrap();
+    let mut audio = AudioPlayer::new(Some("null")).unwrap();
+    let mut decoder = AudioDecoder::new();
+
+    // Each start stops the job before it, which stores its own token
+    for _ in 0..50 {
+        let backend = audio.init_buffer().unwrap();
+        let token = decoder.start(&path, backend, audio.finished_flag()).unwrap();
+        audio.set_decode_token(token);
+        assert!(!audio.is_finished());
+    }
+
+    // Only the job feeding the buffer ends it
+    decoder.stop();
+    assert!(audio.is_finished());
+}
@@ -0,0 +1,48 @@
+//! The soundscape layer's decoder thread, without a sound card.
+
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::silent_mp3;
+use fomu::audio::SoundscapeDecoder;
+use ringbuf::{traits::*, HeapRb};
+use tempfile::tempdir;
+
+#[test]
+fn restarting_from_idle_always_plays() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("rain.mp3");
+    std::fs::write(&path, silent_mp3(Duration::from_secs(2))).unwrap();
+    let (producer, mut consumer) = HeapRb::<f32>::new(4096).split();
+    let flush = Arc::new(AtomicBool::new(false));
+    let decoder = SoundscapeDecoder::new(producer, Arc::clone(&flush));
+
+    for round in 0..20 {
+        decoder.play(None);
+        // Stand in for the output callback: flush on request, else drain
+        let idle = Instant::now();
+        while idle.elapsed() < Duration::from_millis(60) {
+            if flush.swap(false, Ordering::SeqCst) {
+                consumer.clear();
+            }
+            consumer.clear();
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        decoder.play(Some(path.clone()));
+        let start = Instant::now();
+        loop {
+            if flush.swap(false, Ordering::SeqCst) {
+                consumer.clear();
+            } else if consumer.occupied_len() > 0 {
+                break;
+            }
+            assert!(start.elapsed() < Duration::from_secs(2), "round {} stayed silent", round);
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+}